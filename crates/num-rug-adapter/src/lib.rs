@@ -117,6 +117,26 @@ impl Integer {
     pub fn gcd(&self, other: &Self) -> Self {
         Integer(num_integer::Integer::gcd(&self.0, &other.0))
     }
+
+    #[inline]
+    pub fn significant_bits(&self) -> u32 {
+        self.0.bits() as u32
+    }
+
+    pub fn find_one(&self, start: u32) -> Option<u32> {
+        let (_, bytes) = self.0.to_bytes_le();
+
+        (start..8 * bytes.len() as u32).find(|&bit| bytes[bit as usize / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    pub fn count_ones(&self) -> Option<u32> {
+        if self.0.is_negative() {
+            return None;
+        }
+
+        let (_, bytes) = self.0.to_bytes_le();
+        Some(bytes.iter().map(|byte| byte.count_ones()).sum())
+    }
 }
 
 impl From<&Integer> for Integer {
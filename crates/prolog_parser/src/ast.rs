@@ -283,12 +283,26 @@ pub type OpDir = IndexMap<OpDirKey, OpDirValue>;
 #[derive(Debug, Clone, Copy)]
 pub struct MachineFlags {
     pub double_quotes: DoubleQuotes,
+    pub debug: bool,
+    pub float_format: FloatFormat,
+    // the IEEE float flags. a trapped condition raises an evaluation
+    // error, an untrapped one yields the IEEE special value instead.
+    pub trap_float_overflow: bool,
+    pub trap_float_zero_div: bool,
+    pub trap_float_undefined: bool,
+    pub trap_float_underflow: bool,
 }
 
 impl Default for MachineFlags {
     fn default() -> Self {
         MachineFlags {
             double_quotes: DoubleQuotes::default(),
+            debug: false,
+            float_format: FloatFormat::default(),
+            trap_float_overflow: true,
+            trap_float_zero_div: true,
+            trap_float_undefined: true,
+            trap_float_underflow: false,
         }
     }
 }
@@ -320,6 +334,59 @@ impl Default for DoubleQuotes {
     }
 }
 
+// the value of the float_format flag. floats are written in their
+// shortest round-tripping form by default, or else like the ~Ne, ~Nf
+// and ~Ng directives of format/2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatFormat {
+    Shortest,
+    Exponential(usize),
+    Fixed(usize),
+    General(usize),
+}
+
+impl FloatFormat {
+    pub fn from_spec(spec: &str) -> Option<Self> {
+        if spec.is_empty() {
+            return Some(FloatFormat::Shortest);
+        }
+
+        let spec = spec.strip_prefix('~')?;
+        let directive = spec.chars().last()?;
+        let digits = &spec[..spec.len() - directive.len_utf8()];
+
+        let precision = if digits.is_empty() {
+            6
+        } else if digits.chars().all(|c| c.is_ascii_digit()) {
+            digits.parse().ok()?
+        } else {
+            return None;
+        };
+
+        match directive {
+            'e' => Some(FloatFormat::Exponential(precision)),
+            'f' => Some(FloatFormat::Fixed(precision)),
+            'g' => Some(FloatFormat::General(precision)),
+            _ => None,
+        }
+    }
+
+    pub fn to_spec(self) -> String {
+        match self {
+            FloatFormat::Shortest => String::new(),
+            FloatFormat::Exponential(precision) => format!("~{}e", precision),
+            FloatFormat::Fixed(precision) => format!("~{}f", precision),
+            FloatFormat::General(precision) => format!("~{}g", precision),
+        }
+    }
+}
+
+impl Default for FloatFormat {
+    fn default() -> Self {
+        FloatFormat::Shortest
+    }
+}
+
 pub fn default_op_dir() -> OpDir {
     let mut op_dir = OpDir::new();
 
@@ -504,6 +571,7 @@ pub enum Constant {
     Rational(Rc<Rational>),
     Float(OrderedFloat<f64>),
     String(Rc<String>),
+    OpaqueString(Rc<String>),
     Usize(usize),
 }
 
@@ -523,7 +591,7 @@ impl fmt::Display for Constant {
             Constant::Integer(ref n) => write!(f, "{}", n),
             Constant::Rational(ref n) => write!(f, "{}", n),
             Constant::Float(ref n) => write!(f, "{}", n),
-            Constant::String(ref s) => write!(f, "\"{}\"", &s),
+            Constant::String(ref s) | Constant::OpaqueString(ref s) => write!(f, "\"{}\"", &s),
             Constant::Usize(integer) => write!(f, "u{}", integer),
         }
     }
@@ -1,5 +1,4 @@
 use crate::rug::Integer;
-use lexical::parse_lossy;
 use ordered_float::*;
 
 use crate::ast::*;
@@ -79,6 +78,12 @@ impl<'a, R: Read + fmt::Debug> fmt::Debug for Lexer<'a, R> {
     }
 }
 
+// floats are parsed with correct rounding, so that printing a float
+// and reading it back yields the same float.
+fn parse_float(token: &str) -> OrderedFloat<f64> {
+    OrderedFloat(lexical::parse::<f64, _>(token.as_bytes()))
+}
+
 impl<'a, R: Read> Lexer<'a, R> {
     pub fn new(
         atom_tbl: TabledData<Atom>,
@@ -555,10 +560,42 @@ impl<'a, R: Read> Lexer<'a, R> {
     fn vacate_with_float(&mut self, mut token: String) -> Token {
         self.return_char(token.pop().unwrap());
 
-        let result = OrderedFloat(parse_lossy::<f64, _>(token.as_bytes()));
+        let result = parse_float(&token);
         Token::Constant(Constant::Float(result))
     }
 
+    // infinite and not-a-number floats are written 1.0Inf and 1.5NaN,
+    // so the suffix is read right after the fraction digits.
+    fn special_float_suffix(&mut self) -> Result<Option<f64>, ParserError> {
+        let (suffix, f) = match self.lookahead_char() {
+            Ok('I') => ("Inf", f64::INFINITY),
+            Ok('N') => ("NaN", f64::NAN),
+            _ => return Ok(None),
+        };
+
+        let mut consumed = String::new();
+
+        for expected in suffix.chars() {
+            match self.lookahead_char() {
+                Ok(c) if c == expected => consumed.push(self.skip_char()?),
+                _ => break,
+            }
+        }
+
+        let complete = consumed.len() == suffix.len()
+            && !matches!(self.lookahead_char(), Ok(c) if alpha_numeric_char!(c));
+
+        if complete {
+            Ok(Some(f))
+        } else {
+            while let Some(c) = consumed.pop() {
+                self.return_char(c);
+            }
+
+            Ok(None)
+        }
+    }
+
     pub fn number_token(&mut self) -> Result<Token, ParserError> {
         let mut token = String::new();
 
@@ -597,6 +634,10 @@ impl<'a, R: Read> Lexer<'a, R> {
                     c = self.lookahead_char()?;
                 }
 
+                if let Some(f) = self.special_float_suffix()? {
+                    return Ok(Token::Constant(Constant::Float(OrderedFloat(f))));
+                }
+
                 if exponent_char!(self.lookahead_char()?) {
                     token.push(self.skip_char()?);
 
@@ -633,13 +674,13 @@ impl<'a, R: Read> Lexer<'a, R> {
                             token.push(self.skip_char()?);
                         }
 
-                        let n = OrderedFloat(parse_lossy::<f64, _>(token.as_bytes()));
+                        let n = parse_float(&token);
                         Ok(Token::Constant(Constant::Float(n)))
                     } else {
                         return Ok(self.vacate_with_float(token));
                     }
                 } else {
-                    let n = OrderedFloat(parse_lossy::<f64, _>(token.as_bytes()));
+                    let n = parse_float(&token);
                     Ok(Token::Constant(Constant::Float(n)))
                 }
             } else {
@@ -167,6 +167,24 @@ impl<'a> ArithmeticEvaluator<'a> {
             "floor" => Ok(ArithmeticInstruction::Floor(a1, t)),
             "sign" => Ok(ArithmeticInstruction::Sign(a1, t)),
             "\\" => Ok(ArithmeticInstruction::BitwiseComplement(a1, t)),
+            "cot" => Ok(ArithmeticInstruction::Cot(a1, t)),
+            "acot" => Ok(ArithmeticInstruction::ACot(a1, t)),
+            "sinh" => Ok(ArithmeticInstruction::Sinh(a1, t)),
+            "cosh" => Ok(ArithmeticInstruction::Cosh(a1, t)),
+            "tanh" => Ok(ArithmeticInstruction::Tanh(a1, t)),
+            "asinh" => Ok(ArithmeticInstruction::ASinh(a1, t)),
+            "acosh" => Ok(ArithmeticInstruction::ACosh(a1, t)),
+            "atanh" => Ok(ArithmeticInstruction::ATanh(a1, t)),
+            "log2" => Ok(ArithmeticInstruction::Log2(a1, t)),
+            "float_integer_part" => Ok(ArithmeticInstruction::FloatIntegerPart(a1, t)),
+            "float_fractional_part" => Ok(ArithmeticInstruction::FloatFractionalPart(a1, t)),
+            "msb" => Ok(ArithmeticInstruction::Msb(a1, t)),
+            "lsb" => Ok(ArithmeticInstruction::Lsb(a1, t)),
+            "popcount" => Ok(ArithmeticInstruction::PopCount(a1, t)),
+            "trunc" => Ok(ArithmeticInstruction::Truncate(a1, t)),
+            "integer" => Ok(ArithmeticInstruction::Integer(a1, t)),
+            "succ_or_zero" => Ok(ArithmeticInstruction::SuccOrZero(a1, t)),
+            "random" => Ok(ArithmeticInstruction::Random(a1, t)),
             _ => Err(ArithmeticError::NonEvaluableFunctor(
                 Constant::Atom(name, None),
                 1,
@@ -201,6 +219,9 @@ impl<'a> ArithmeticEvaluator<'a> {
             "rem" => Ok(ArithmeticInstruction::Rem(a1, a2, t)),
             "gcd" => Ok(ArithmeticInstruction::Gcd(a1, a2, t)),
             "atan2" => Ok(ArithmeticInstruction::ATan2(a1, a2, t)),
+            "log" => Ok(ArithmeticInstruction::LogBase(a1, a2, t)),
+            "copysign" => Ok(ArithmeticInstruction::CopySign(a1, a2, t)),
+            "nexttoward" => Ok(ArithmeticInstruction::NextToward(a1, a2, t)),
             _ => Err(ArithmeticError::NonEvaluableFunctor(
                 Constant::Atom(name, None),
                 2,
@@ -208,6 +229,15 @@ impl<'a> ArithmeticEvaluator<'a> {
         }
     }
 
+    // evaluable atoms with instructions of their own. is/2 called on
+    // a term built at run time looks them up by name too.
+    fn get_nullary_instr(c: &Constant) -> Option<NullaryInstruction> {
+        match c {
+            &Constant::Atom(ref name, _) => NullaryInstruction::from_name(name.as_str()),
+            _ => None,
+        }
+    }
+
     fn incr_interm(&mut self) -> usize {
         let temp = self.interm_c;
 
@@ -304,7 +334,13 @@ impl<'a> ArithmeticEvaluator<'a> {
 
         for term_ref in src.iter()? {
             match term_ref? {
-                ArithTermRef::Constant(c) => self.push_constant(c)?,
+                ArithTermRef::Constant(c) => match Self::get_nullary_instr(c) {
+                    Some(instr) => {
+                        let t = self.incr_interm();
+                        code.push(Line::Arithmetic(ArithmeticInstruction::Nullary(instr, t)));
+                    }
+                    None => self.push_constant(c)?,
+                },
                 ArithTermRef::Var(cell, name) => {
                     let r = if cell.get().norm().reg_num() == 0 {
                         match self.bindings.get(&name) {
@@ -356,67 +392,105 @@ pub(crate) fn rnd_f(n: &Number) -> f64 {
     }
 }
 
-// floating point result function -- 9.1.4.2.
-pub(crate) fn result_f<Round>(n: &Number, round: Round) -> Result<f64, EvalError>
+// floating point result function -- 9.1.4.2. infinities and NaNs
+// passed in as operands are not exceptional, and so are returned
+// unchanged.
+pub(crate) fn result_f<Round>(
+    flags: &MachineFlags,
+    n: &Number,
+    round: Round,
+) -> Result<f64, EvalError>
 where
     Round: Fn(&Number) -> f64,
 {
-    let f = rnd_f(n);
-    classify_float(f, round)
+    match n {
+        &Number::Float(OrderedFloat(f)) if !f.is_finite() => Ok(f),
+        _ => {
+            let f = float_result(flags, rnd_f(n), &[])?;
+            Ok(round(&Number::Float(OrderedFloat(f))))
+        }
+    }
 }
 
-fn classify_float<Round>(f: f64, round: Round) -> Result<f64, EvalError>
-where
-    Round: Fn(&Number) -> f64,
-{
+// classifies the result of a float operation on the given operands
+// by the float flags. overflow from finite operands and NaN from
+// operands that aren't NaN are exceptional; otherwise infinities and
+// NaNs propagate.
+pub(crate) fn float_result(
+    flags: &MachineFlags,
+    f: f64,
+    operands: &[f64],
+) -> Result<f64, EvalError> {
     match f.classify() {
-        FpCategory::Normal | FpCategory::Zero => Ok(round(&Number::Float(OrderedFloat(f)))),
-        FpCategory::Infinite => {
-            let f = round(&Number::Float(OrderedFloat(f)));
+        FpCategory::Infinite
+            if flags.trap_float_overflow && operands.iter().all(|f| f.is_finite()) =>
+        {
+            Err(EvalError::FloatOverflow)
+        }
+        FpCategory::Nan if flags.trap_float_undefined && !operands.iter().any(|f| f.is_nan()) => {
+            Err(EvalError::Undefined)
+        }
+        FpCategory::Subnormal if flags.trap_float_underflow => Err(EvalError::Underflow),
+        _ => Ok(f),
+    }
+}
 
-            if OrderedFloat(f) == OrderedFloat(f64::MAX) {
-                Ok(f)
-            } else {
-                Err(EvalError::FloatOverflow)
-            }
+// an operand of an arithmetic operator as seen by float_result.
+// operands that aren't floats are finite.
+#[inline]
+pub(crate) fn operand_f(n: &Number) -> f64 {
+    match n {
+        &Number::Float(OrderedFloat(f)) => f,
+        _ => 0f64,
+    }
+}
+
+// classifies the result of an arithmetic operator, which is only
+// exceptional if it's a float.
+pub(crate) fn number_result(
+    flags: &MachineFlags,
+    n: Number,
+    operands: &[f64],
+) -> Result<Number, EvalError> {
+    match n {
+        Number::Float(OrderedFloat(f)) => {
+            Ok(Number::Float(OrderedFloat(float_result(flags, f, operands)?)))
         }
-        FpCategory::Nan => Err(EvalError::Undefined),
-        _ => Ok(round(&Number::Float(OrderedFloat(f)))),
+        n => Ok(n),
     }
 }
 
+// the operators on numbers below yield IEEE floats, leaving it to
+// number_result to apply the float flags of the machine.
+
 #[inline]
-fn float_fn_to_f(n: isize) -> Result<f64, EvalError> {
-    classify_float(n as f64, rnd_f)
+fn float_fn_to_f(n: isize) -> f64 {
+    n as f64
 }
 
 #[inline]
-fn float_i_to_f(n: &Integer) -> Result<f64, EvalError> {
-    classify_float(n.to_f64(), rnd_f)
+fn float_i_to_f(n: &Integer) -> f64 {
+    n.to_f64()
 }
 
 #[inline]
-fn float_r_to_f(r: &Rational) -> Result<f64, EvalError> {
-    classify_float(r.to_f64(), rnd_f)
+fn float_r_to_f(r: &Rational) -> f64 {
+    r.to_f64()
 }
 
 #[inline]
-fn add_f(f1: f64, f2: f64) -> Result<OrderedFloat<f64>, EvalError> {
-    Ok(OrderedFloat(classify_float(f1 + f2, rnd_f)?))
+fn add_f(f1: f64, f2: f64) -> OrderedFloat<f64> {
+    OrderedFloat(f1 + f2)
 }
 
 #[inline]
-fn mul_f(f1: f64, f2: f64) -> Result<OrderedFloat<f64>, EvalError> {
-    Ok(OrderedFloat(classify_float(f1 * f2, rnd_f)?))
+fn mul_f(f1: f64, f2: f64) -> OrderedFloat<f64> {
+    OrderedFloat(f1 * f2)
 }
 
 #[inline]
-fn div_f(f1: f64, f2: f64) -> Result<OrderedFloat<f64>, EvalError> {
-    if FpCategory::Zero == f2.classify() {
-        Err(EvalError::ZeroDivisor)
-    } else {
-        Ok(OrderedFloat(classify_float(f1 / f2, rnd_f)?))
-    }
+fn div_f(f1: f64, f2: f64) -> OrderedFloat<f64> {
+    OrderedFloat(f1 / f2)
 }
 
 impl Add<Number> for Number {
@@ -441,14 +515,14 @@ impl Add<Number> for Number {
             }
             (Number::Fixnum(n1), Number::Float(OrderedFloat(n2)))
             | (Number::Float(OrderedFloat(n2)), Number::Fixnum(n1)) => {
-                Ok(Number::Float(add_f(float_fn_to_f(n1)?, n2)?))
+                Ok(Number::Float(add_f(float_fn_to_f(n1), n2)))
             }
             (Number::Integer(n1), Number::Integer(n2)) => {
                 Ok(Number::from(Integer::from(&*n1) + &*n2)) // add_i
             }
             (Number::Integer(n1), Number::Float(OrderedFloat(n2)))
             | (Number::Float(OrderedFloat(n2)), Number::Integer(n1)) => {
-                Ok(Number::Float(add_f(float_i_to_f(&n1)?, n2)?))
+                Ok(Number::Float(add_f(float_i_to_f(&n1), n2)))
             }
             (Number::Integer(n1), Number::Rational(n2))
             | (Number::Rational(n2), Number::Integer(n1)) => {
@@ -456,10 +530,10 @@ impl Add<Number> for Number {
             }
             (Number::Rational(n1), Number::Float(OrderedFloat(n2)))
             | (Number::Float(OrderedFloat(n2)), Number::Rational(n1)) => {
-                Ok(Number::Float(add_f(float_r_to_f(&n1)?, n2)?))
+                Ok(Number::Float(add_f(float_r_to_f(&n1), n2)))
             }
             (Number::Float(OrderedFloat(f1)), Number::Float(OrderedFloat(f2))) => {
-                Ok(Number::Float(add_f(f1, f2)?))
+                Ok(Number::Float(add_f(f1, f2)))
             }
             (Number::Rational(r1), Number::Rational(r2)) => {
                 Ok(Number::from(Rational::from(&*r1) + &*r2))
@@ -517,14 +591,14 @@ impl Mul<Number> for Number {
             }
             (Number::Fixnum(n1), Number::Float(OrderedFloat(n2)))
             | (Number::Float(OrderedFloat(n2)), Number::Fixnum(n1)) => {
-                Ok(Number::Float(mul_f(float_fn_to_f(n1)?, n2)?))
+                Ok(Number::Float(mul_f(float_fn_to_f(n1), n2)))
             }
             (Number::Integer(n1), Number::Integer(n2)) => {
                 Ok(Number::Integer(Rc::new(Integer::from(&*n1) * &*n2))) // mul_i
             }
             (Number::Integer(n1), Number::Float(OrderedFloat(n2)))
             | (Number::Float(OrderedFloat(n2)), Number::Integer(n1)) => {
-                Ok(Number::Float(mul_f(float_i_to_f(&n1)?, n2)?))
+                Ok(Number::Float(mul_f(float_i_to_f(&n1), n2)))
             }
             (Number::Integer(n1), Number::Rational(n2))
             | (Number::Rational(n2), Number::Integer(n1)) => {
@@ -532,10 +606,10 @@ impl Mul<Number> for Number {
             }
             (Number::Rational(n1), Number::Float(OrderedFloat(n2)))
             | (Number::Float(OrderedFloat(n2)), Number::Rational(n1)) => {
-                Ok(Number::Float(mul_f(float_r_to_f(&n1)?, n2)?))
+                Ok(Number::Float(mul_f(float_r_to_f(&n1), n2)))
             }
             (Number::Float(OrderedFloat(f1)), Number::Float(OrderedFloat(f2))) => {
-                Ok(Number::Float(mul_f(f1, f2)?))
+                Ok(Number::Float(mul_f(f1, f2)))
             }
             (Number::Rational(r1), Number::Rational(r2)) => {
                 Ok(Number::Rational(Rc::new(Rational::from(&*r1) * &*r2)))
@@ -550,62 +624,62 @@ impl Div<Number> for Number {
     fn div(self, rhs: Number) -> Self::Output {
         match (self, rhs) {
             (Number::Fixnum(n1), Number::Fixnum(n2)) => Ok(Number::Float(div_f(
-                float_fn_to_f(n1)?,
-                float_fn_to_f(n2)?,
-            )?)),
+                float_fn_to_f(n1),
+                float_fn_to_f(n2),
+            ))),
             (Number::Fixnum(n1), Number::Integer(n2)) => Ok(Number::Float(div_f(
-                float_fn_to_f(n1)?,
-                float_i_to_f(&n2)?,
-            )?)),
+                float_fn_to_f(n1),
+                float_i_to_f(&n2),
+            ))),
             (Number::Integer(n1), Number::Fixnum(n2)) => Ok(Number::Float(div_f(
-                float_i_to_f(&n1)?,
-                float_fn_to_f(n2)?,
-            )?)),
+                float_i_to_f(&n1),
+                float_fn_to_f(n2),
+            ))),
             (Number::Fixnum(n1), Number::Rational(n2)) => Ok(Number::Float(div_f(
-                float_fn_to_f(n1)?,
-                float_r_to_f(&n2)?,
-            )?)),
+                float_fn_to_f(n1),
+                float_r_to_f(&n2),
+            ))),
             (Number::Rational(n1), Number::Fixnum(n2)) => Ok(Number::Float(div_f(
-                float_r_to_f(&n1)?,
-                float_fn_to_f(n2)?,
-            )?)),
+                float_r_to_f(&n1),
+                float_fn_to_f(n2),
+            ))),
             (Number::Fixnum(n1), Number::Float(OrderedFloat(n2))) => {
-                Ok(Number::Float(div_f(float_fn_to_f(n1)?, n2)?))
+                Ok(Number::Float(div_f(float_fn_to_f(n1), n2)))
             }
             (Number::Float(OrderedFloat(n1)), Number::Fixnum(n2)) => {
-                Ok(Number::Float(div_f(n1, float_fn_to_f(n2)?)?))
+                Ok(Number::Float(div_f(n1, float_fn_to_f(n2))))
             }
             (Number::Integer(n1), Number::Integer(n2)) => Ok(Number::Float(div_f(
-                float_i_to_f(&n1)?,
-                float_i_to_f(&n2)?,
-            )?)),
+                float_i_to_f(&n1),
+                float_i_to_f(&n2),
+            ))),
             (Number::Integer(n1), Number::Float(OrderedFloat(n2))) => {
-                Ok(Number::Float(div_f(float_i_to_f(&n1)?, n2)?))
+                Ok(Number::Float(div_f(float_i_to_f(&n1), n2)))
             }
             (Number::Float(OrderedFloat(n2)), Number::Integer(n1)) => {
-                Ok(Number::Float(div_f(n2, float_i_to_f(&n1)?)?))
+                Ok(Number::Float(div_f(n2, float_i_to_f(&n1))))
             }
             (Number::Integer(n1), Number::Rational(n2)) => Ok(Number::Float(div_f(
-                float_i_to_f(&n1)?,
-                float_r_to_f(&n2)?,
-            )?)),
+                float_i_to_f(&n1),
+                float_r_to_f(&n2),
+            ))),
             (Number::Rational(n2), Number::Integer(n1)) => Ok(Number::Float(div_f(
-                float_r_to_f(&n2)?,
-                float_i_to_f(&n1)?,
-            )?)),
+                float_r_to_f(&n2),
+                float_i_to_f(&n1),
+            ))),
             (Number::Rational(n1), Number::Float(OrderedFloat(n2))) => {
-                Ok(Number::Float(div_f(float_r_to_f(&n1)?, n2)?))
+                Ok(Number::Float(div_f(float_r_to_f(&n1), n2)))
             }
             (Number::Float(OrderedFloat(n2)), Number::Rational(n1)) => {
-                Ok(Number::Float(div_f(n2, float_r_to_f(&n1)?)?))
+                Ok(Number::Float(div_f(n2, float_r_to_f(&n1))))
             }
             (Number::Float(OrderedFloat(f1)), Number::Float(OrderedFloat(f2))) => {
-                Ok(Number::Float(div_f(f1, f2)?))
+                Ok(Number::Float(div_f(f1, f2)))
             }
             (Number::Rational(r1), Number::Rational(r2)) => Ok(Number::Float(div_f(
-                float_r_to_f(&r1)?,
-                float_r_to_f(&r2)?,
-            )?)),
+                float_r_to_f(&r1),
+                float_r_to_f(&r2),
+            ))),
         }
     }
 }
@@ -12,9 +12,8 @@ fn main() {
 
 pub extern "C" fn handle_sigint(signal: libc::c_int) {
     use nix::sys::signal;
-    use std::sync::atomic::Ordering;
     let signal = signal::Signal::from_c_int(signal).unwrap();
     if signal == signal::Signal::SIGINT {
-        scryer_prolog::machine::INTERRUPT.store(true, Ordering::Relaxed);
+        scryer_prolog::machine::raise_event(scryer_prolog::machine::INTERRUPT_EVENT);
     }
 }
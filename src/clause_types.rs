@@ -3,7 +3,6 @@ use prolog_parser::{clause_name, temp_v};
 
 use crate::forms::Number;
 use crate::machine::machine_indices::*;
-use crate::rug::rand::RandState;
 
 use ref_thread_local::{ref_thread_local, RefThreadLocal};
 
@@ -82,10 +81,6 @@ pub(crate) enum InlinedClauseType {
     IsVar(RegType),
 }
 
-ref_thread_local! {
-    pub(crate)static managed RANDOM_STATE: RandState<'static> = RandState::new();
-}
-
 ref_thread_local! {
     pub(crate)static managed CLAUSE_TYPE_FORMS: BTreeMap<(&'static str, usize), ClauseType> = {
         let mut m = BTreeMap::new();
@@ -159,6 +154,7 @@ pub(crate) enum SystemClauseType {
     CharCode,
     CharType,
     CharsToNumber,
+    CharsOutputStreamContents,
     CodesToNumber,
     CopyTermWithoutAttrVars,
     CheckCutPoint,
@@ -183,6 +179,7 @@ pub(crate) enum SystemClauseType {
     DynamicModuleResolution(usize),
     EnqueueAttributedVar,
     FetchGlobalVar,
+    GlobalVarKeys,
     FirstStream,
     FlushOutput,
     GetByte,
@@ -215,12 +212,20 @@ pub(crate) enum SystemClauseType {
     ModuleExists,
     NextEP,
     NoSuchPredicate,
+    PredicateDefined,
     NumberToChars,
     NumberToCodes,
     OpDeclaration,
     Open,
+    OpenCharsOutputStream,
     NextStream,
     PartialStringTail,
+    PStrAffix,
+    PStrAppend,
+    PStrAtom,
+    PStrLength,
+    PStrSearch,
+    PStrSplit,
     PeekByte,
     PeekChar,
     PeekCode,
@@ -230,6 +235,7 @@ pub(crate) enum SystemClauseType {
     PutChars,
     PutCode,
     REPL(REPLCodePtr),
+    QueryHistory,
     ReadQueryTerm,
     ReadTerm,
     RedoAttrVarBinding,
@@ -248,23 +254,53 @@ pub(crate) enum SystemClauseType {
     CleanUpBlock,
     EraseBall,
     Fail,
+    GetBacktrace,
     GetBall,
     GetCurrentBlock,
     GetCutPoint,
+    GetDebug,
     GetDoubleQuotes,
+    GetFloatFlag,
+    GetMemoryLimit,
+    FormatNumber,
+    GetFloatFormat,
+    GetRandomState,
+    GetRawBacktrace,
+    GetInterruptBacktrace,
+    SetTrace,
     InstallNewBlock,
+    KeepBacktrace,
     Maybe,
     CpuNow,
     CurrentTime,
+    GetTime,
+    Statistics,
+    LocalTimeZone,
+    MonotonicTime,
+    NewAlarmId,
+    ScheduleAlarm,
+    CancelAlarm,
+    FiredAlarms,
+    SetSignalAction,
+    PendingSignals,
     QuotedToken,
     ReadTermFromChars,
     ResetBlock,
     ReturnFromVerifyAttr,
+    SetArg,
+    NbSetArg,
     SetBall,
     SetCutPointByDefault(RegType),
+    SetDebug,
     SetDoubleQuotes,
+    SetFloatFlag,
+    SetMemoryLimit,
+    SetFloatFormat,
+    SetRandomState,
     SetSeed,
     SkipMaxList,
+    SplitText,
+    SubText,
     Sleep,
     SocketClientOpen,
     SocketServerOpen,
@@ -273,8 +309,14 @@ pub(crate) enum SystemClauseType {
     Succeed,
     TermAttributedVariables,
     TermVariables,
+    TextConcat,
+    TextLength,
+    TextToString,
+    TextToChars,
+    IsString,
     TruncateLiftedHeapTo,
     UnifyWithOccursCheck,
+    UnifyWithOccursCheckWithError,
     UnwindEnvironments,
     UnwindStack,
     Variant,
@@ -296,6 +338,8 @@ pub(crate) enum SystemClauseType {
     Curve25519ScalarMult,
     LoadHTML,
     LoadXML,
+    XMLStreamElement,
+    XMLStreamRoot,
     GetEnv,
     SetEnv,
     UnsetEnv,
@@ -305,6 +349,8 @@ pub(crate) enum SystemClauseType {
     SetSTOAsUnify,
     SetNSTOAsUnify,
     SetSTOWithErrorAsUnify,
+    ModuleOccursCheck,
+    SetModuleOccursCheck,
     HomeDirectory,
     DebugHook,
 }
@@ -320,6 +366,9 @@ impl SystemClauseType {
             &SystemClauseType::CharCode => clause_name!("$char_code"),
             &SystemClauseType::CharType => clause_name!("$char_type"),
             &SystemClauseType::CharsToNumber => clause_name!("$chars_to_number"),
+            &SystemClauseType::CharsOutputStreamContents => {
+                clause_name!("$chars_output_stream_contents")
+            }
             &SystemClauseType::CheckCutPoint => clause_name!("$check_cp"),
             &SystemClauseType::CodesToNumber => clause_name!("$codes_to_number"),
             &SystemClauseType::CopyTermWithoutAttrVars => {
@@ -434,6 +483,7 @@ impl SystemClauseType {
             &SystemClauseType::DynamicModuleResolution(_) => clause_name!("$module_call"),
             &SystemClauseType::EnqueueAttributedVar => clause_name!("$enqueue_attr_var"),
             &SystemClauseType::FetchGlobalVar => clause_name!("$fetch_global_var"),
+            &SystemClauseType::GlobalVarKeys => clause_name!("$global_var_keys"),
             &SystemClauseType::FirstStream => clause_name!("$first_stream"),
             &SystemClauseType::FlushOutput => clause_name!("$flush_output"),
             &SystemClauseType::GetByte => clause_name!("$get_byte"),
@@ -464,12 +514,24 @@ impl SystemClauseType {
             &SystemClauseType::GetNextOpDBRef => clause_name!("$get_next_op_db_ref"),
             &SystemClauseType::LookupDBRef => clause_name!("$lookup_db_ref"),
             &SystemClauseType::LookupOpDBRef => clause_name!("$lookup_op_db_ref"),
+            &SystemClauseType::GetDebug => clause_name!("$get_debug"),
             &SystemClauseType::GetDoubleQuotes => clause_name!("$get_double_quotes"),
+            &SystemClauseType::GetFloatFlag => clause_name!("$get_float_flag"),
+            &SystemClauseType::GetMemoryLimit => clause_name!("$get_memory_limit"),
+            &SystemClauseType::FormatNumber => clause_name!("$format_number"),
+            &SystemClauseType::GetFloatFormat => clause_name!("$get_float_format"),
+            &SystemClauseType::GetRandomState => clause_name!("$get_random_state"),
+            &SystemClauseType::GetRawBacktrace => clause_name!("$get_raw_backtrace"),
+            &SystemClauseType::GetInterruptBacktrace => clause_name!("$get_interrupt_backtrace"),
+            &SystemClauseType::SetTrace => clause_name!("$set_trace"),
             //          &SystemClauseType::GetModuleClause => clause_name!("$get_module_clause"),
             &SystemClauseType::GetSCCCleaner => clause_name!("$get_scc_cleaner"),
             &SystemClauseType::Halt => clause_name!("$halt"),
             &SystemClauseType::HeadIsDynamic => clause_name!("$head_is_dynamic"),
             &SystemClauseType::Open => clause_name!("$open"),
+            &SystemClauseType::OpenCharsOutputStream => {
+                clause_name!("$open_chars_output_stream")
+            }
             &SystemClauseType::OpDeclaration => clause_name!("$op"),
             &SystemClauseType::InstallSCCCleaner => clause_name!("$install_scc_cleaner"),
             &SystemClauseType::InstallInferenceCounter => {
@@ -477,6 +539,12 @@ impl SystemClauseType {
             }
             &SystemClauseType::IsPartialString => clause_name!("$is_partial_string"),
             &SystemClauseType::PartialStringTail => clause_name!("$partial_string_tail"),
+            &SystemClauseType::PStrAffix => clause_name!("$pstr_affix"),
+            &SystemClauseType::PStrAppend => clause_name!("$pstr_append"),
+            &SystemClauseType::PStrAtom => clause_name!("$pstr_atom"),
+            &SystemClauseType::PStrLength => clause_name!("$pstr_length"),
+            &SystemClauseType::PStrSearch => clause_name!("$pstr_search"),
+            &SystemClauseType::PStrSplit => clause_name!("$pstr_split"),
             &SystemClauseType::PeekByte => clause_name!("$peek_byte"),
             &SystemClauseType::PeekChar => clause_name!("$peek_char"),
             &SystemClauseType::PeekCode => clause_name!("$peek_code"),
@@ -484,6 +552,16 @@ impl SystemClauseType {
             &SystemClauseType::Maybe => clause_name!("maybe"),
             &SystemClauseType::CpuNow => clause_name!("$cpu_now"),
             &SystemClauseType::CurrentTime => clause_name!("$current_time"),
+            &SystemClauseType::GetTime => clause_name!("$get_time"),
+            &SystemClauseType::Statistics => clause_name!("$statistics"),
+            &SystemClauseType::LocalTimeZone => clause_name!("$local_time_zone"),
+            &SystemClauseType::MonotonicTime => clause_name!("$monotonic_time"),
+            &SystemClauseType::NewAlarmId => clause_name!("$new_alarm_id"),
+            &SystemClauseType::ScheduleAlarm => clause_name!("$schedule_alarm"),
+            &SystemClauseType::CancelAlarm => clause_name!("$cancel_alarm"),
+            &SystemClauseType::FiredAlarms => clause_name!("$fired_alarms"),
+            &SystemClauseType::SetSignalAction => clause_name!("$set_signal_action"),
+            &SystemClauseType::PendingSignals => clause_name!("$pending_signals"),
             // &SystemClauseType::ModuleAssertDynamicPredicateToFront => {
             //     clause_name!("$module_asserta")
             // }
@@ -494,6 +572,7 @@ impl SystemClauseType {
             &SystemClauseType::ModuleExists => clause_name!("$module_exists"),
             &SystemClauseType::NextStream => clause_name!("$next_stream"),
             &SystemClauseType::NoSuchPredicate => clause_name!("$no_such_predicate"),
+            &SystemClauseType::PredicateDefined => clause_name!("$predicate_defined"),
             &SystemClauseType::NumberToChars => clause_name!("$number_to_chars"),
             &SystemClauseType::NumberToCodes => clause_name!("$number_to_codes"),
             &SystemClauseType::PointsToContinuationResetMarker => {
@@ -525,28 +604,40 @@ impl SystemClauseType {
             &SystemClauseType::StreamProperty => clause_name!("$stream_property"),
             &SystemClauseType::SetStreamPosition => clause_name!("$set_stream_position"),
             &SystemClauseType::StoreBacktrackableGlobalVar => {
-                clause_name!("$store_back_trackable_global_var")
+                clause_name!("$store_backtrackable_global_var")
             }
             &SystemClauseType::StoreGlobalVar => clause_name!("$store_global_var"),
             &SystemClauseType::InferenceLevel => clause_name!("$inference_level"),
             &SystemClauseType::CleanUpBlock => clause_name!("$clean_up_block"),
             &SystemClauseType::EraseBall => clause_name!("$erase_ball"),
             &SystemClauseType::Fail => clause_name!("$fail"),
+            &SystemClauseType::GetBacktrace => clause_name!("$get_backtrace"),
             &SystemClauseType::GetBall => clause_name!("$get_ball"),
+            &SystemClauseType::KeepBacktrace => clause_name!("$keep_backtrace"),
             &SystemClauseType::GetCutPoint => clause_name!("$get_cp"),
             &SystemClauseType::GetCurrentBlock => clause_name!("$get_current_block"),
             &SystemClauseType::InstallNewBlock => clause_name!("$install_new_block"),
             &SystemClauseType::NextEP => clause_name!("$nextEP"),
+            &SystemClauseType::QueryHistory => clause_name!("$query_history"),
             &SystemClauseType::ReadQueryTerm => clause_name!("$read_query_term"),
             &SystemClauseType::ReadTerm => clause_name!("$read_term"),
             &SystemClauseType::ReadTermFromChars => clause_name!("$read_term_from_chars"),
             &SystemClauseType::ResetBlock => clause_name!("$reset_block"),
             &SystemClauseType::ResetContinuationMarker => clause_name!("$reset_cont_marker"),
             &SystemClauseType::ReturnFromVerifyAttr => clause_name!("$return_from_verify_attr"),
+            &SystemClauseType::SetArg => clause_name!("$setarg"),
+            &SystemClauseType::NbSetArg => clause_name!("$nb_setarg"),
             &SystemClauseType::SetBall => clause_name!("$set_ball"),
             &SystemClauseType::SetCutPointByDefault(_) => clause_name!("$set_cp_by_default"),
+            &SystemClauseType::SetDebug => clause_name!("$set_debug"),
             &SystemClauseType::SetDoubleQuotes => clause_name!("$set_double_quotes"),
+            &SystemClauseType::SetFloatFlag => clause_name!("$set_float_flag"),
+            &SystemClauseType::SetMemoryLimit => clause_name!("$set_memory_limit"),
+            &SystemClauseType::SetFloatFormat => clause_name!("$set_float_format"),
+            &SystemClauseType::SetRandomState => clause_name!("$set_random_state"),
             &SystemClauseType::SkipMaxList => clause_name!("$skip_max_list"),
+            &SystemClauseType::SplitText => clause_name!("$split_text"),
+            &SystemClauseType::SubText => clause_name!("$sub_text"),
             &SystemClauseType::Sleep => clause_name!("$sleep"),
             &SystemClauseType::SocketClientOpen => clause_name!("$socket_client_open"),
             &SystemClauseType::SocketServerOpen => clause_name!("$socket_server_open"),
@@ -557,8 +648,16 @@ impl SystemClauseType {
                 clause_name!("$term_attributed_variables")
             }
             &SystemClauseType::TermVariables => clause_name!("$term_variables"),
+            &SystemClauseType::TextConcat => clause_name!("$text_concat"),
+            &SystemClauseType::TextLength => clause_name!("$text_length"),
+            &SystemClauseType::TextToString => clause_name!("$text_to_string"),
+            &SystemClauseType::TextToChars => clause_name!("$text_to_chars"),
+            &SystemClauseType::IsString => clause_name!("$is_string"),
             &SystemClauseType::TruncateLiftedHeapTo => clause_name!("$truncate_lh_to"),
             &SystemClauseType::UnifyWithOccursCheck => clause_name!("$unify_with_occurs_check"),
+            &SystemClauseType::UnifyWithOccursCheckWithError => {
+                clause_name!("$unify_with_occurs_check_with_error")
+            }
             &SystemClauseType::UnwindEnvironments => clause_name!("$unwind_environments"),
             &SystemClauseType::UnwindStack => clause_name!("$unwind_stack"),
             &SystemClauseType::Variant => clause_name!("$variant"),
@@ -582,6 +681,8 @@ impl SystemClauseType {
             &SystemClauseType::Curve25519ScalarMult => clause_name!("$curve25519_scalar_mult"),
             &SystemClauseType::LoadHTML => clause_name!("$load_html"),
             &SystemClauseType::LoadXML => clause_name!("$load_xml"),
+            &SystemClauseType::XMLStreamElement => clause_name!("$xml_stream_element"),
+            &SystemClauseType::XMLStreamRoot => clause_name!("$xml_stream_root"),
             &SystemClauseType::GetEnv => clause_name!("$getenv"),
             &SystemClauseType::SetEnv => clause_name!("$setenv"),
             &SystemClauseType::UnsetEnv => clause_name!("$unsetenv"),
@@ -593,6 +694,8 @@ impl SystemClauseType {
             &SystemClauseType::SetNSTOAsUnify => clause_name!("$set_nsto_as_unify"),
             &SystemClauseType::HomeDirectory => clause_name!("$home_directory"),
             &SystemClauseType::SetSTOWithErrorAsUnify => clause_name!("$set_sto_with_error_as_unify"),
+            &SystemClauseType::ModuleOccursCheck => clause_name!("$module_occurs_check"),
+            &SystemClauseType::SetModuleOccursCheck => clause_name!("$set_module_occurs_check"),
             &SystemClauseType::DebugHook => clause_name!("$debug_hook"),
         }
     }
@@ -623,6 +726,9 @@ impl SystemClauseType {
             ("$char_code", 2) => Some(SystemClauseType::CharCode),
             ("$char_type", 2) => Some(SystemClauseType::CharType),
             ("$chars_to_number", 2) => Some(SystemClauseType::CharsToNumber),
+            ("$chars_output_stream_contents", 2) => {
+                Some(SystemClauseType::CharsOutputStreamContents)
+            }
             ("$codes_to_number", 2) => Some(SystemClauseType::CodesToNumber),
             ("$copy_term_without_attr_vars", 2) => Some(SystemClauseType::CopyTermWithoutAttrVars),
             ("$create_partial_string", 3) => Some(SystemClauseType::CreatePartialString),
@@ -644,11 +750,18 @@ impl SystemClauseType {
             ("$module_call", _) => Some(SystemClauseType::DynamicModuleResolution(arity - 2)),
             ("$enqueue_attr_var", 1) => Some(SystemClauseType::EnqueueAttributedVar),
             ("$partial_string_tail", 2) => Some(SystemClauseType::PartialStringTail),
+            ("$pstr_affix", 3) => Some(SystemClauseType::PStrAffix),
+            ("$pstr_append", 3) => Some(SystemClauseType::PStrAppend),
+            ("$pstr_atom", 2) => Some(SystemClauseType::PStrAtom),
+            ("$pstr_length", 2) => Some(SystemClauseType::PStrLength),
+            ("$pstr_search", 4) => Some(SystemClauseType::PStrSearch),
+            ("$pstr_split", 3) => Some(SystemClauseType::PStrSplit),
             ("$peek_byte", 2) => Some(SystemClauseType::PeekByte),
             ("$peek_char", 2) => Some(SystemClauseType::PeekChar),
             ("$peek_code", 2) => Some(SystemClauseType::PeekCode),
             ("$is_partial_string", 1) => Some(SystemClauseType::IsPartialString),
             ("$fetch_global_var", 2) => Some(SystemClauseType::FetchGlobalVar),
+            ("$global_var_keys", 1) => Some(SystemClauseType::GlobalVarKeys),
             ("$get_byte", 2) => Some(SystemClauseType::GetByte),
             ("$get_char", 2) => Some(SystemClauseType::GetChar),
             ("$get_n_chars", 3) => Some(SystemClauseType::GetNChars),
@@ -672,7 +785,16 @@ impl SystemClauseType {
             ("$get_b_value", 1) => Some(SystemClauseType::GetBValue),
             ("$get_lh_from_offset", 2) => Some(SystemClauseType::GetLiftedHeapFromOffset),
             ("$get_lh_from_offset_diff", 3) => Some(SystemClauseType::GetLiftedHeapFromOffsetDiff),
+            ("$get_debug", 1) => Some(SystemClauseType::GetDebug),
             ("$get_double_quotes", 1) => Some(SystemClauseType::GetDoubleQuotes),
+            ("$get_float_flag", 2) => Some(SystemClauseType::GetFloatFlag),
+            ("$get_memory_limit", 2) => Some(SystemClauseType::GetMemoryLimit),
+            ("$format_number", 4) => Some(SystemClauseType::FormatNumber),
+            ("$get_float_format", 1) => Some(SystemClauseType::GetFloatFormat),
+            ("$get_random_state", 4) => Some(SystemClauseType::GetRandomState),
+            ("$get_raw_backtrace", 1) => Some(SystemClauseType::GetRawBacktrace),
+            ("$get_interrupt_backtrace", 1) => Some(SystemClauseType::GetInterruptBacktrace),
+            ("$set_trace", 1) => Some(SystemClauseType::SetTrace),
             ("$get_scc_cleaner", 1) => Some(SystemClauseType::GetSCCCleaner),
             ("$halt", 1) => Some(SystemClauseType::Halt),
             ("$head_is_dynamic", 2) => Some(SystemClauseType::HeadIsDynamic),
@@ -682,12 +804,24 @@ impl SystemClauseType {
             ("$maybe", 0) => Some(SystemClauseType::Maybe),
             ("$cpu_now", 1) => Some(SystemClauseType::CpuNow),
             ("$current_time", 1) => Some(SystemClauseType::CurrentTime),
+            ("$get_time", 1) => Some(SystemClauseType::GetTime),
+            ("$statistics", 2) => Some(SystemClauseType::Statistics),
+            ("$local_time_zone", 4) => Some(SystemClauseType::LocalTimeZone),
+            ("$monotonic_time", 1) => Some(SystemClauseType::MonotonicTime),
+            ("$new_alarm_id", 1) => Some(SystemClauseType::NewAlarmId),
+            ("$schedule_alarm", 2) => Some(SystemClauseType::ScheduleAlarm),
+            ("$cancel_alarm", 1) => Some(SystemClauseType::CancelAlarm),
+            ("$fired_alarms", 1) => Some(SystemClauseType::FiredAlarms),
+            ("$set_signal_action", 2) => Some(SystemClauseType::SetSignalAction),
+            ("$pending_signals", 1) => Some(SystemClauseType::PendingSignals),
             ("$module_exists", 1) => Some(SystemClauseType::ModuleExists),
             ("$no_such_predicate", 2) => Some(SystemClauseType::NoSuchPredicate),
+            ("$predicate_defined", 3) => Some(SystemClauseType::PredicateDefined),
             ("$number_to_chars", 2) => Some(SystemClauseType::NumberToChars),
             ("$number_to_codes", 2) => Some(SystemClauseType::NumberToCodes),
             ("$op", 3) => Some(SystemClauseType::OpDeclaration),
             ("$open", 7) => Some(SystemClauseType::Open),
+            ("$open_chars_output_stream", 1) => Some(SystemClauseType::OpenCharsOutputStream),
             ("$redo_attr_var_binding", 2) => Some(SystemClauseType::RedoAttrVarBinding),
             ("$remove_call_policy_check", 1) => Some(SystemClauseType::RemoveCallPolicyCheck),
             ("$remove_inference_counter", 2) => Some(SystemClauseType::RemoveInferenceCounter),
@@ -703,24 +837,36 @@ impl SystemClauseType {
             ("$fail", 0) => Some(SystemClauseType::Fail),
             ("$get_attr_var_queue_beyond", 2) => Some(SystemClauseType::GetAttrVarQueueBeyond),
             ("$get_attr_var_queue_delim", 1) => Some(SystemClauseType::GetAttrVarQueueDelimiter),
+            ("$get_backtrace", 1) => Some(SystemClauseType::GetBacktrace),
             ("$get_ball", 1) => Some(SystemClauseType::GetBall),
+            ("$keep_backtrace", 1) => Some(SystemClauseType::KeepBacktrace),
             ("$get_cont_chunk", 3) => Some(SystemClauseType::GetContinuationChunk),
             ("$get_current_block", 1) => Some(SystemClauseType::GetCurrentBlock),
             ("$get_cp", 1) => Some(SystemClauseType::GetCutPoint),
             ("$install_new_block", 1) => Some(SystemClauseType::InstallNewBlock),
             ("$quoted_token", 1) => Some(SystemClauseType::QuotedToken),
             ("$nextEP", 3) => Some(SystemClauseType::NextEP),
+            ("$query_history", 1) => Some(SystemClauseType::QueryHistory),
             ("$read_query_term", 5) => Some(SystemClauseType::ReadQueryTerm),
             ("$read_term", 5) => Some(SystemClauseType::ReadTerm),
             ("$read_term_from_chars", 2) => Some(SystemClauseType::ReadTermFromChars),
             ("$reset_block", 1) => Some(SystemClauseType::ResetBlock),
             ("$reset_cont_marker", 0) => Some(SystemClauseType::ResetContinuationMarker),
             ("$return_from_verify_attr", 0) => Some(SystemClauseType::ReturnFromVerifyAttr),
+            ("$setarg", 3) => Some(SystemClauseType::SetArg),
+            ("$nb_setarg", 3) => Some(SystemClauseType::NbSetArg),
             ("$set_ball", 1) => Some(SystemClauseType::SetBall),
             ("$set_cp_by_default", 1) => Some(SystemClauseType::SetCutPointByDefault(temp_v!(1))),
+            ("$set_debug", 1) => Some(SystemClauseType::SetDebug),
             ("$set_double_quotes", 1) => Some(SystemClauseType::SetDoubleQuotes),
+            ("$set_float_flag", 2) => Some(SystemClauseType::SetFloatFlag),
+            ("$set_memory_limit", 2) => Some(SystemClauseType::SetMemoryLimit),
+            ("$set_float_format", 1) => Some(SystemClauseType::SetFloatFormat),
+            ("$set_random_state", 4) => Some(SystemClauseType::SetRandomState),
             ("$set_seed", 1) => Some(SystemClauseType::SetSeed),
             ("$skip_max_list", 4) => Some(SystemClauseType::SkipMaxList),
+            ("$split_text", 4) => Some(SystemClauseType::SplitText),
+            ("$sub_text", 4) => Some(SystemClauseType::SubText),
             ("$sleep", 1) => Some(SystemClauseType::Sleep),
             ("$socket_client_open", 8) => Some(SystemClauseType::SocketClientOpen),
             ("$socket_server_open", 3) => Some(SystemClauseType::SocketServerOpen),
@@ -732,10 +878,18 @@ impl SystemClauseType {
             }
             ("$term_attributed_variables", 2) => Some(SystemClauseType::TermAttributedVariables),
             ("$term_variables", 2) => Some(SystemClauseType::TermVariables),
+            ("$text_concat", 3) => Some(SystemClauseType::TextConcat),
+            ("$text_length", 2) => Some(SystemClauseType::TextLength),
+            ("$text_to_string", 2) => Some(SystemClauseType::TextToString),
+            ("$text_to_chars", 2) => Some(SystemClauseType::TextToChars),
+            ("$is_string", 1) => Some(SystemClauseType::IsString),
             ("$truncate_lh_to", 1) => Some(SystemClauseType::TruncateLiftedHeapTo),
             ("$unwind_environments", 0) => Some(SystemClauseType::UnwindEnvironments),
             ("$unwind_stack", 0) => Some(SystemClauseType::UnwindStack),
             ("$unify_with_occurs_check", 2) => Some(SystemClauseType::UnifyWithOccursCheck),
+            ("$unify_with_occurs_check_with_error", 2) => {
+                Some(SystemClauseType::UnifyWithOccursCheckWithError)
+            }
             ("$directory_files", 2) => Some(SystemClauseType::DirectoryFiles),
             ("$file_size", 2) => Some(SystemClauseType::FileSize),
             ("$file_exists", 1) => Some(SystemClauseType::FileExists),
@@ -796,6 +950,8 @@ impl SystemClauseType {
             ("$curve25519_scalar_mult", 3) => Some(SystemClauseType::Curve25519ScalarMult),
             ("$load_html", 3) => Some(SystemClauseType::LoadHTML),
             ("$load_xml", 3) => Some(SystemClauseType::LoadXML),
+            ("$xml_stream_element", 5) => Some(SystemClauseType::XMLStreamElement),
+            ("$xml_stream_root", 2) => Some(SystemClauseType::XMLStreamRoot),
             ("$getenv", 2) => Some(SystemClauseType::GetEnv),
             ("$setenv", 2) => Some(SystemClauseType::SetEnv),
             ("$unsetenv", 1) => Some(SystemClauseType::UnsetEnv),
@@ -841,6 +997,8 @@ impl SystemClauseType {
             ("$set_sto_as_unify", 0) => Some(SystemClauseType::SetSTOAsUnify),
             ("$set_nsto_as_unify", 0) => Some(SystemClauseType::SetNSTOAsUnify),
             ("$set_sto_with_error_as_unify", 0) => Some(SystemClauseType::SetSTOWithErrorAsUnify),
+            ("$module_occurs_check", 2) => Some(SystemClauseType::ModuleOccursCheck),
+            ("$set_module_occurs_check", 2) => Some(SystemClauseType::SetModuleOccursCheck),
             ("$home_directory", 1) => Some(SystemClauseType::HomeDirectory),
             ("$debug_hook", 0) => Some(SystemClauseType::DebugHook),
             _ => None,
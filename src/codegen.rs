@@ -94,6 +94,7 @@ pub(crate) struct CodeGenSettings {
     pub global_clock_tick: Option<usize>,
     pub is_extensible: bool,
     pub non_counted_bt: bool,
+    pub occurs_check: Option<OccursCheck>,
 }
 
 impl CodeGenSettings {
@@ -439,15 +440,47 @@ impl<'a, TermMarker: Allocator<'a>> CodeGenerator<TermMarker> {
                 code.push(jmp_call!(vars.len(), 0, pvs));
             }
             &QueryTerm::Clause(_, ref ct, ref terms, true) => {
-                code.push(call_clause_by_default!(ct.clone(), terms.len(), pvs));
+                let ct = self.unify_with_occurs_check(ct, terms.len());
+                code.push(call_clause_by_default!(ct, terms.len(), pvs));
             }
             &QueryTerm::Clause(_, ref ct, ref terms, false) => {
-                code.push(call_clause!(ct.clone(), terms.len(), pvs));
+                let ct = self.unify_with_occurs_check(ct, terms.len());
+                code.push(call_clause!(ct, terms.len(), pvs));
             }
             _ => {}
         }
     }
 
+    // =/2 in the body of a clause of a module that sets the
+    // occurs_check flag to true or error unifies as the flag says.
+    fn unify_with_occurs_check(&self, ct: &ClauseType, arity: usize) -> ClauseType {
+        match ct {
+            ClauseType::Named(..) | ClauseType::Op(..)
+                if arity == 2 && ct.name().as_str() == "=" =>
+            {
+                match self.settings.occurs_check {
+                    Some(OccursCheck::True) => {
+                        ClauseType::System(SystemClauseType::UnifyWithOccursCheck)
+                    }
+                    Some(OccursCheck::Error) => {
+                        ClauseType::System(SystemClauseType::UnifyWithOccursCheckWithError)
+                    }
+                    _ => ct.clone(),
+                }
+            }
+            _ => ct.clone(),
+        }
+    }
+
+    // the head of a clause of a module that sets the occurs_check
+    // flag unifies as the flag says.
+    fn fact_instr_with_occurs_check(&self, fact_instr: FactInstruction) -> FactInstruction {
+        match self.settings.occurs_check {
+            Some(occurs_check) => fact_instr.with_occurs_check(occurs_check),
+            None => fact_instr,
+        }
+    }
+
     fn lco(code: &mut Code) -> usize {
         let mut dealloc_index = code.len() - 1;
 
@@ -863,7 +896,7 @@ impl<'a, TermMarker: Allocator<'a>> CodeGenerator<TermMarker> {
             unsafe_var_marker = self.mark_unsafe_fact_vars(&mut fact);
 
             for fact_instr in fact {
-                code.push(Line::Fact(fact_instr));
+                code.push(Line::Fact(self.fact_instr_with_occurs_check(fact_instr)));
             }
         }
 
@@ -918,7 +951,7 @@ impl<'a, TermMarker: Allocator<'a>> CodeGenerator<TermMarker> {
 
             if !compiled_fact.is_empty() {
                 for fact_instr in compiled_fact {
-                    code.push(Line::Fact(fact_instr));
+                    code.push(Line::Fact(self.fact_instr_with_occurs_check(fact_instr)));
                 }
             }
         }
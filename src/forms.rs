@@ -427,6 +427,33 @@ pub(crate) struct ModuleDecl {
     pub(crate) exports: Vec<ModuleExport>,
 }
 
+// a value of the occurs_check flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OccursCheck {
+    False,
+    True,
+    Error,
+}
+
+impl OccursCheck {
+    pub(crate) fn from_atom(name: &str) -> Option<Self> {
+        match name {
+            "false" => Some(OccursCheck::False),
+            "true" => Some(OccursCheck::True),
+            "error" => Some(OccursCheck::Error),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            OccursCheck::False => "false",
+            OccursCheck::True => "true",
+            OccursCheck::Error => "error",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct Module {
     pub(crate) module_decl: ModuleDecl,
@@ -437,6 +464,10 @@ pub(crate) struct Module {
     pub(crate) local_extensible_predicates: LocalExtensiblePredicates,
     pub(crate) is_impromptu_module: bool,
     pub(crate) listing_src: ListingSource,
+    // the occurs_check flag the clauses of the module are compiled
+    // with, if the module sets it. otherwise they unify as the global
+    // flag says.
+    pub(crate) occurs_check: Option<OccursCheck>,
 }
 
 // Module's and related types are defined in forms.
@@ -451,6 +482,7 @@ impl Module {
             extensible_predicates: ExtensiblePredicates::new(),
             local_extensible_predicates: LocalExtensiblePredicates::new(),
             listing_src,
+            occurs_check: None,
         }
     }
 
@@ -464,6 +496,7 @@ impl Module {
             extensible_predicates: ExtensiblePredicates::new(),
             local_extensible_predicates: LocalExtensiblePredicates::new(),
             listing_src: ListingSource::DynamicallyGenerated,
+            occurs_check: None,
         }
     }
 }
@@ -40,7 +40,8 @@ impl<'a> HCPreOrderIterator<'a> {
             HeapCellValue::Atom(..)
             | HeapCellValue::DBRef(_)
             | HeapCellValue::Integer(_)
-            | HeapCellValue::Rational(_) => Addr::Con(h),
+            | HeapCellValue::Rational(_)
+            | HeapCellValue::OpaqueString(_) => Addr::Con(h),
             HeapCellValue::LoadStatePayload(_) => Addr::LoadStatePayload(h),
             HeapCellValue::Stream(_) => Addr::Stream(h),
             HeapCellValue::TcpListener(_) => Addr::TcpListener(h),
@@ -445,6 +445,109 @@ pub(super) fn non_quoted_token<Iter: Iterator<Item = char>>(mut iter: Iter) -> b
     }
 }
 
+// the shortest decimal that reads back as f. rust leaves out the
+// fraction of mantissas in exponent notation, which prolog requires.
+pub(crate) fn shortest_float_str(f: f64) -> String {
+    let output_str = format!("{:?}", f);
+
+    match output_str.find('e') {
+        Some(e) if !output_str[..e].contains('.') => {
+            format!("{}.0{}", &output_str[..e], &output_str[e..])
+        }
+        _ => output_str,
+    }
+}
+
+// f in exponent notation with the given number of fraction digits,
+// and a signed exponent of at least two digits, as in C.
+pub(crate) fn exponential_float_str(f: f64, precision: usize) -> String {
+    let output_str = format!("{:.*e}", precision, f);
+    let e = output_str.find('e').unwrap();
+    let exponent: i32 = output_str[e + 1..].parse().unwrap();
+
+    format!(
+        "{}e{}{:02}",
+        &output_str[..e],
+        if exponent < 0 { '-' } else { '+' },
+        exponent.abs()
+    )
+}
+
+// f with the given number of significant digits, in fixed or exponent
+// notation depending on its magnitude and without trailing zeros, as
+// the %g conversion of C.
+pub(crate) fn general_float_str(f: f64, precision: usize) -> String {
+    let precision = std::cmp::max(precision, 1);
+
+    let rounded = format!("{:.*e}", precision - 1, f);
+    let e = rounded.find('e').unwrap();
+    let exponent: i32 = rounded[e + 1..].parse().unwrap();
+
+    let output_str = if exponent < -4 || exponent >= precision as i32 {
+        exponential_float_str(f, precision - 1)
+    } else {
+        format!("{:.*}", (precision as i32 - 1 - exponent) as usize, f)
+    };
+
+    let (mantissa, exponent) = match output_str.find('e') {
+        Some(e) => output_str.split_at(e),
+        None => (output_str.as_str(), ""),
+    };
+
+    if mantissa.contains('.') {
+        let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+        format!("{}{}", mantissa, exponent)
+    } else {
+        output_str.clone()
+    }
+}
+
+// infinities and NaN are written 1.0Inf, -1.0Inf and 1.5NaN in any
+// float format, so that they can be read back.
+pub(crate) fn float_str(f: f64, float_format: FloatFormat) -> String {
+    if f.is_nan() {
+        return "1.5NaN".to_string();
+    } else if f.is_infinite() {
+        return if f > 0.0 { "1.0Inf" } else { "-1.0Inf" }.to_string();
+    }
+
+    match float_format {
+        FloatFormat::Shortest => shortest_float_str(f),
+        FloatFormat::Exponential(precision) => exponential_float_str(f, precision),
+        FloatFormat::Fixed(precision) => format!("{:.*}", precision, f),
+        FloatFormat::General(precision) => general_float_str(f, precision),
+    }
+}
+
+// r in fixed notation with the given number of fraction digits,
+// rounded half away from zero. the digits are computed exactly, so
+// that rationals and big integers keep their precision.
+pub(crate) fn fixed_rational_str(r: &Rational, digits: usize) -> String {
+    let mut scaled = Integer::from(r.numer().abs_ref());
+
+    for _ in 0..digits {
+        scaled = scaled * 10u32;
+    }
+
+    let (mut quotient, remainder) = <(Integer, Integer)>::from(scaled.div_rem_ref(r.denom()));
+
+    if remainder * 2u32 >= *r.denom() {
+        quotient += 1i64;
+    }
+
+    let mut output_str = format!("{:0>width$}", quotient.to_string(), width = digits + 1);
+
+    if digits > 0 {
+        output_str.insert(output_str.len() - digits, '.');
+    }
+
+    if *r.numer() < 0 {
+        output_str.insert(0, '-');
+    }
+
+    output_str
+}
+
 #[inline]
 fn functor_location(addr: &Addr) -> Option<usize> {
     Some(match addr {
@@ -962,18 +1065,20 @@ impl<'a, Outputter: HCValueOutputter> HCPrinter<'a, Outputter> {
 
         match n {
             Number::Float(fl) => {
-                if &fl == &OrderedFloat(0f64) {
-                    push_space_if_amb!(self, "0.0", {
-                        self.append_str("0.0");
-                    });
+                let OrderedFloat(fl) = fl;
+                // quoted output must read back as the same float, so
+                // the float_format flag applies to write/1 alone.
+                let float_format = if self.quoted {
+                    FloatFormat::Shortest
                 } else {
-                    let OrderedFloat(fl) = fl;
-                    let output_str = format!("{0:<20?}", fl);
+                    self.machine_st.flags.float_format
+                };
 
-                    push_space_if_amb!(self, &output_str, {
-                        self.append_str(&output_str.trim());
-                    });
-                }
+                let output_str = float_str(fl, float_format);
+
+                push_space_if_amb!(self, &output_str, {
+                    self.append_str(&output_str);
+                });
             }
             Number::Rational(r) => {
                 self.print_rational(&r, add_brackets);
@@ -1331,7 +1436,7 @@ impl<'a, Outputter: HCValueOutputter> HCPrinter<'a, Outputter> {
             self.print_atom(alias);
         } else {
             if self.format_struct(iter, max_depth, 1, clause_name!("$stream")) {
-                let atom = if stream.is_stdout() || stream.is_stdin() {
+                let atom = if stream.is_stdout() || stream.is_stderr() || stream.is_stdin() {
                     TokenOrRedirect::Atom(clause_name!("user"))
                 } else {
                     TokenOrRedirect::RawPtr(stream.as_ptr())
@@ -1456,6 +1561,15 @@ impl<'a, Outputter: HCValueOutputter> HCPrinter<'a, Outputter> {
             &HeapCellValue::Rational(ref n) => {
                 self.print_number(Number::Rational(n.clone()), &op);
             }
+            &HeapCellValue::OpaqueString(ref s) => {
+                if self.quoted {
+                    self.print_proper_string(s.to_string(), max_depth);
+                } else {
+                    push_space_if_amb!(self, s.as_str(), {
+                        self.append_str(s.as_str());
+                    });
+                }
+            }
             &HeapCellValue::Stream(ref stream) => {
                 self.print_stream(iter, stream, max_depth);
             }
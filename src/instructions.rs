@@ -356,6 +356,9 @@ pub(crate) enum ArithmeticInstruction {
     Mod(ArithmeticTerm, ArithmeticTerm, usize),
     Rem(ArithmeticTerm, ArithmeticTerm, usize),
     Gcd(ArithmeticTerm, ArithmeticTerm, usize),
+    LogBase(ArithmeticTerm, ArithmeticTerm, usize),
+    CopySign(ArithmeticTerm, ArithmeticTerm, usize),
+    NextToward(ArithmeticTerm, ArithmeticTerm, usize),
     Sign(ArithmeticTerm, usize),
     Cos(ArithmeticTerm, usize),
     Sin(ArithmeticTerm, usize),
@@ -376,6 +379,65 @@ pub(crate) enum ArithmeticInstruction {
     Neg(ArithmeticTerm, usize),
     Plus(ArithmeticTerm, usize),
     BitwiseComplement(ArithmeticTerm, usize),
+    Cot(ArithmeticTerm, usize),
+    ACot(ArithmeticTerm, usize),
+    Sinh(ArithmeticTerm, usize),
+    Cosh(ArithmeticTerm, usize),
+    Tanh(ArithmeticTerm, usize),
+    ASinh(ArithmeticTerm, usize),
+    ACosh(ArithmeticTerm, usize),
+    ATanh(ArithmeticTerm, usize),
+    Log2(ArithmeticTerm, usize),
+    FloatIntegerPart(ArithmeticTerm, usize),
+    FloatFractionalPart(ArithmeticTerm, usize),
+    Msb(ArithmeticTerm, usize),
+    Lsb(ArithmeticTerm, usize),
+    PopCount(ArithmeticTerm, usize),
+    SuccOrZero(ArithmeticTerm, usize),
+    Random(ArithmeticTerm, usize),
+    Integer(ArithmeticTerm, usize),
+    Nullary(NullaryInstruction, usize),
+}
+
+// evaluable atoms with instructions of their own, rather than
+// constants folded in at compile time.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum NullaryInstruction {
+    CmpFlags,
+    Epsilon,
+    Inf,
+    MaxTaggedInteger,
+    NaN,
+    RandomFloat,
+}
+
+impl NullaryInstruction {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "cmpflags" => Some(NullaryInstruction::CmpFlags),
+            "epsilon" => Some(NullaryInstruction::Epsilon),
+            "inf" => Some(NullaryInstruction::Inf),
+            "max_tagged_integer" => Some(NullaryInstruction::MaxTaggedInteger),
+            "nan" => Some(NullaryInstruction::NaN),
+            "random_float" => Some(NullaryInstruction::RandomFloat),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            NullaryInstruction::CmpFlags => "cmpflags",
+            NullaryInstruction::Epsilon => "epsilon",
+            NullaryInstruction::Inf => "inf",
+            NullaryInstruction::MaxTaggedInteger => "max_tagged_integer",
+            NullaryInstruction::NaN => "nan",
+            NullaryInstruction::RandomFloat => "random_float",
+        }
+    }
+}
+
+fn arith_instr_nullary_functor(name: &'static str, t: usize) -> MachineStub {
+    functor!(name, [integer(t)])
 }
 
 fn arith_instr_unary_functor(
@@ -469,6 +531,15 @@ impl ArithmeticInstruction {
             &ArithmeticInstruction::Gcd(ref at_1, ref at_2, t) => {
                 arith_instr_bin_functor(h, "gcd", at_1, at_2, t)
             }
+            &ArithmeticInstruction::LogBase(ref at_1, ref at_2, t) => {
+                arith_instr_bin_functor(h, "log", at_1, at_2, t)
+            }
+            &ArithmeticInstruction::CopySign(ref at_1, ref at_2, t) => {
+                arith_instr_bin_functor(h, "copysign", at_1, at_2, t)
+            }
+            &ArithmeticInstruction::NextToward(ref at_1, ref at_2, t) => {
+                arith_instr_bin_functor(h, "nexttoward", at_1, at_2, t)
+            }
             &ArithmeticInstruction::Sign(ref at, t) => arith_instr_unary_functor(h, "sign", at, t),
             &ArithmeticInstruction::Cos(ref at, t) => arith_instr_unary_functor(h, "cos", at, t),
             &ArithmeticInstruction::Sin(ref at, t) => arith_instr_unary_functor(h, "sin", at, t),
@@ -500,6 +571,44 @@ impl ArithmeticInstruction {
             &ArithmeticInstruction::BitwiseComplement(ref at, t) => {
                 arith_instr_unary_functor(h, "\\", at, t)
             }
+            &ArithmeticInstruction::Cot(ref at, t) => arith_instr_unary_functor(h, "cot", at, t),
+            &ArithmeticInstruction::ACot(ref at, t) => arith_instr_unary_functor(h, "acot", at, t),
+            &ArithmeticInstruction::Sinh(ref at, t) => arith_instr_unary_functor(h, "sinh", at, t),
+            &ArithmeticInstruction::Cosh(ref at, t) => arith_instr_unary_functor(h, "cosh", at, t),
+            &ArithmeticInstruction::Tanh(ref at, t) => arith_instr_unary_functor(h, "tanh", at, t),
+            &ArithmeticInstruction::ASinh(ref at, t) => {
+                arith_instr_unary_functor(h, "asinh", at, t)
+            }
+            &ArithmeticInstruction::ACosh(ref at, t) => {
+                arith_instr_unary_functor(h, "acosh", at, t)
+            }
+            &ArithmeticInstruction::ATanh(ref at, t) => {
+                arith_instr_unary_functor(h, "atanh", at, t)
+            }
+            &ArithmeticInstruction::Log2(ref at, t) => arith_instr_unary_functor(h, "log2", at, t),
+            &ArithmeticInstruction::FloatIntegerPart(ref at, t) => {
+                arith_instr_unary_functor(h, "float_integer_part", at, t)
+            }
+            &ArithmeticInstruction::FloatFractionalPart(ref at, t) => {
+                arith_instr_unary_functor(h, "float_fractional_part", at, t)
+            }
+            &ArithmeticInstruction::Msb(ref at, t) => arith_instr_unary_functor(h, "msb", at, t),
+            &ArithmeticInstruction::Lsb(ref at, t) => arith_instr_unary_functor(h, "lsb", at, t),
+            &ArithmeticInstruction::PopCount(ref at, t) => {
+                arith_instr_unary_functor(h, "popcount", at, t)
+            }
+            &ArithmeticInstruction::SuccOrZero(ref at, t) => {
+                arith_instr_unary_functor(h, "succ_or_zero", at, t)
+            }
+            &ArithmeticInstruction::Random(ref at, t) => {
+                arith_instr_unary_functor(h, "random", at, t)
+            }
+            &ArithmeticInstruction::Integer(ref at, t) => {
+                arith_instr_unary_functor(h, "integer", at, t)
+            }
+            &ArithmeticInstruction::Nullary(instr, t) => {
+                arith_instr_nullary_functor(instr.name(), t)
+            }
         }
     }
 }
@@ -663,15 +772,36 @@ pub(crate) enum FactInstruction {
     GetPartialString(Level, String, RegType, bool),
     GetStructure(ClauseType, usize, RegType),
     GetValue(RegType, usize),
+    GetValueWithOccursCheck(RegType, usize, OccursCheck),
     GetVariable(RegType, usize),
     UnifyConstant(Constant),
     UnifyLocalValue(RegType),
+    UnifyLocalValueWithOccursCheck(RegType, OccursCheck),
     UnifyVariable(RegType),
     UnifyValue(RegType),
+    UnifyValueWithOccursCheck(RegType, OccursCheck),
     UnifyVoid(usize),
 }
 
 impl FactInstruction {
+    // the instruction with which a clause of a module that sets the
+    // occurs_check flag unifies, in place of one that would unify as
+    // the global flag says.
+    pub(crate) fn with_occurs_check(self, occurs_check: OccursCheck) -> Self {
+        match self {
+            FactInstruction::GetValue(r, arg) => {
+                FactInstruction::GetValueWithOccursCheck(r, arg, occurs_check)
+            }
+            FactInstruction::UnifyLocalValue(r) => {
+                FactInstruction::UnifyLocalValueWithOccursCheck(r, occurs_check)
+            }
+            FactInstruction::UnifyValue(r) => {
+                FactInstruction::UnifyValueWithOccursCheck(r, occurs_check)
+            }
+            fact_instr => fact_instr,
+        }
+    }
+
     pub(crate) fn to_functor(&self, h: usize) -> MachineStub {
         match self {
             &FactInstruction::GetConstant(lvl, ref c, r) => {
@@ -714,6 +844,15 @@ impl FactInstruction {
 
                 functor!("get_value", [aux(h, 0), integer(arg)], [rt_stub])
             }
+            &FactInstruction::GetValueWithOccursCheck(r, arg, occurs_check) => {
+                let rt_stub = reg_type_into_functor(r);
+
+                functor!(
+                    "get_value",
+                    [aux(h, 0), integer(arg), atom(occurs_check.as_str())],
+                    [rt_stub]
+                )
+            }
             &FactInstruction::GetVariable(r, arg) => {
                 let rt_stub = reg_type_into_functor(r);
 
@@ -727,6 +866,15 @@ impl FactInstruction {
 
                 functor!("unify_local_value", [aux(h, 0)], [rt_stub])
             }
+            &FactInstruction::UnifyLocalValueWithOccursCheck(r, occurs_check) => {
+                let rt_stub = reg_type_into_functor(r);
+
+                functor!(
+                    "unify_local_value",
+                    [aux(h, 0), atom(occurs_check.as_str())],
+                    [rt_stub]
+                )
+            }
             &FactInstruction::UnifyVariable(r) => {
                 let rt_stub = reg_type_into_functor(r);
 
@@ -737,6 +885,15 @@ impl FactInstruction {
 
                 functor!("unify_value", [aux(h, 0)], [rt_stub])
             }
+            &FactInstruction::UnifyValueWithOccursCheck(r, occurs_check) => {
+                let rt_stub = reg_type_into_functor(r);
+
+                functor!(
+                    "unify_value",
+                    [aux(h, 0), atom(occurs_check.as_str())],
+                    [rt_stub]
+                )
+            }
             &FactInstruction::UnifyVoid(vars) => {
                 functor!("unify_void", [integer(vars)])
             }
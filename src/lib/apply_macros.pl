@@ -0,0 +1,11 @@
+/* Loading this library into a module makes the loader inline the
+   calls to maplist/2..9, foldl/4..6 and call/2..N in the clauses of
+   the module whose closures are known at compile time. The calls to
+   maplist/N and foldl/N are replaced by calls to auxiliary
+   predicates applying the closure to each element directly, and
+   call/N by the goal it would construct.
+
+   The clauses loaded before the library are left as they are.
+*/
+
+:- module(apply_macros, []).
@@ -6,7 +6,7 @@
                      atom_chars/2, atom_codes/2, atom_concat/3,
                      atom_length/2, bagof/3, call/1, call/2, call/3,
                      call/4, call/5, call/6, call/7, call/8, call/9,
-                     catch/3, char_code/2, clause/2, close/1, close/2,
+                     catch/3, catch_with_backtrace/3, char_code/2, clause/2, close/1, close/2,
                      current_input/1, current_output/1, current_op/3,
                      current_predicate/1, current_prolog_flag/2,
                      fail/0, false/0, findall/3, findall/4,
@@ -18,7 +18,7 @@
                      peek_char/1, peek_char/2, peek_code/1,
                      peek_code/2, put_byte/1, put_byte/2, put_code/1,
                      put_code/2, put_char/1, put_char/2, read_term/2,
-                     read_term/3, repeat/0, retract/1,
+                     read_term/3, repeat/0, retract/1, retractall/1,
                      set_prolog_flag/2, set_input/1,
                      set_stream_position/2, set_output/1, setof/3,
                      stream_property/2, sub_atom/5, subsumes_term/2,
@@ -131,12 +131,35 @@ current_prolog_flag(Flag, toward_zero) :- Flag == integer_rounding_function, !.
 current_prolog_flag(integer_rounding_function, toward_zero).
 current_prolog_flag(Flag, Value) :- Flag == double_quotes, !, '$get_double_quotes'(Value).
 current_prolog_flag(double_quotes, Value) :- '$get_double_quotes'(Value).
+current_prolog_flag(Flag, Value) :- Flag == debug, !, '$get_debug'(Value).
+current_prolog_flag(debug, Value) :- '$get_debug'(Value).
+current_prolog_flag(Flag, Value) :- Flag == float_overflow, !, '$get_float_flag'(float_overflow, Value).
+current_prolog_flag(float_overflow, Value) :- '$get_float_flag'(float_overflow, Value).
+current_prolog_flag(Flag, Value) :- Flag == float_zero_div, !, '$get_float_flag'(float_zero_div, Value).
+current_prolog_flag(float_zero_div, Value) :- '$get_float_flag'(float_zero_div, Value).
+current_prolog_flag(Flag, Value) :- Flag == float_undefined, !, '$get_float_flag'(float_undefined, Value).
+current_prolog_flag(float_undefined, Value) :- '$get_float_flag'(float_undefined, Value).
+current_prolog_flag(Flag, Value) :- Flag == float_underflow, !, '$get_float_flag'(float_underflow, Value).
+current_prolog_flag(float_underflow, Value) :- '$get_float_flag'(float_underflow, Value).
+current_prolog_flag(Flag, Value) :- Flag == float_format, !, '$get_float_format'(Value).
+current_prolog_flag(float_format, Value) :- '$get_float_format'(Value).
+current_prolog_flag(Flag, Value) :- Flag == heap_limit, !, '$get_memory_limit'(heap_limit, Value).
+current_prolog_flag(heap_limit, Value) :- '$get_memory_limit'(heap_limit, Value).
+current_prolog_flag(Flag, Value) :- Flag == stack_limit, !, '$get_memory_limit'(stack_limit, Value).
+current_prolog_flag(stack_limit, Value) :- '$get_memory_limit'(stack_limit, Value).
+current_prolog_flag(Flag, Value) :- Flag == trail_limit, !, '$get_memory_limit'(trail_limit, Value).
+current_prolog_flag(trail_limit, Value) :- '$get_memory_limit'(trail_limit, Value).
+current_prolog_flag(Flag, Options) :- Flag == answer_write_options, !, answer_write_options(Options).
+current_prolog_flag(answer_write_options, Options) :- answer_write_options(Options).
 current_prolog_flag(Flag, _) :- Flag == max_integer, !, '$fail'.
 current_prolog_flag(Flag, _) :- Flag == min_integer, !, '$fail'.
 current_prolog_flag(Flag, OccursCheckEnabled) :-
     Flag == occurs_check,
     !,
-    '$is_sto_enabled'(OccursCheckEnabled).
+    (  loader:load_context_occurs_check(OccursCheckEnabled0) ->
+       OccursCheckEnabled = OccursCheckEnabled0
+    ;  '$is_sto_enabled'(OccursCheckEnabled)
+    ).
 current_prolog_flag(Flag, _) :-
     atom(Flag),
     throw(error(domain_error(prolog_flag, Flag), current_prolog_flag/2)). % 8.17.2.3 b
@@ -176,12 +199,103 @@ set_prolog_flag(occurs_check, error) :-
 set_prolog_flag(double_quotes, Value) :-
     throw(error(domain_error(flag_value, double_quotes + Value),
 		        set_prolog_flag/2)). % 8.17.1.3 e
+set_prolog_flag(float_overflow, Value) :-
+    !,
+    (  '$set_float_flag'(float_overflow, Value) ->
+       true
+    ;  throw(error(domain_error(flag_value, float_overflow + Value), set_prolog_flag/2))
+    ).
+set_prolog_flag(float_zero_div, Value) :-
+    !,
+    (  '$set_float_flag'(float_zero_div, Value) ->
+       true
+    ;  throw(error(domain_error(flag_value, float_zero_div + Value), set_prolog_flag/2))
+    ).
+set_prolog_flag(float_undefined, Value) :-
+    !,
+    (  '$set_float_flag'(float_undefined, Value) ->
+       true
+    ;  throw(error(domain_error(flag_value, float_undefined + Value), set_prolog_flag/2))
+    ).
+set_prolog_flag(float_underflow, Value) :-
+    !,
+    (  '$set_float_flag'(float_underflow, Value) ->
+       true
+    ;  throw(error(domain_error(flag_value, float_underflow + Value), set_prolog_flag/2))
+    ).
+set_prolog_flag(float_format, Value) :-
+    !,
+    (  '$set_float_format'(Value) ->
+       true
+    ;  throw(error(domain_error(flag_value, float_format + Value), set_prolog_flag/2))
+    ).
+% the memory limits are checked after each instruction, so the
+% instruction or builtin that allocates past one throws
+% resource_error(memory) once it is done.
+set_prolog_flag(heap_limit, Value) :-
+    !,
+    (  '$set_memory_limit'(heap_limit, Value) ->
+       true
+    ;  throw(error(domain_error(flag_value, heap_limit + Value), set_prolog_flag/2))
+    ).
+set_prolog_flag(stack_limit, Value) :-
+    !,
+    (  '$set_memory_limit'(stack_limit, Value) ->
+       true
+    ;  throw(error(domain_error(flag_value, stack_limit + Value), set_prolog_flag/2))
+    ).
+set_prolog_flag(trail_limit, Value) :-
+    !,
+    (  '$set_memory_limit'(trail_limit, Value) ->
+       true
+    ;  throw(error(domain_error(flag_value, trail_limit + Value), set_prolog_flag/2))
+    ).
+set_prolog_flag(answer_write_options, Options) :-
+    !,
+    (  answer_write_options_valid(Options) ->
+       '$store_global_var'('$answer_write_options', Options)
+    ;  throw(error(domain_error(flag_value, answer_write_options + Options), set_prolog_flag/2))
+    ).
+set_prolog_flag(debug, true) :-
+    !, '$set_debug'(true).
+set_prolog_flag(debug, false) :-
+    !, '$set_debug'(false).
+set_prolog_flag(debug, Value) :-
+    throw(error(domain_error(flag_value, debug + Value),
+		        set_prolog_flag/2)).
 set_prolog_flag(Flag, _) :-
     atom(Flag),
     throw(error(domain_error(prolog_flag, Flag), set_prolog_flag/2)). % 8.17.1.3 d
 set_prolog_flag(Flag, _) :-
     throw(error(type_error(atom, Flag), set_prolog_flag/2)). % 8.17.1.3 c
 
+% the options the toplevel writes answers with.
+
+answer_write_options(Options) :-
+    (  '$fetch_global_var'('$answer_write_options', Options0) ->
+       Options = Options0
+    ;  Options = [quoted(true), portray(true), max_depth(20)]
+    ).
+
+answer_write_options_valid(Options) :-
+    Options == [],
+    !.
+answer_write_options_valid([Option|Options]) :-
+    nonvar(Option),
+    answer_write_option_valid(Option),
+    answer_write_options_valid(Options).
+
+answer_write_option_valid(max_depth(MaxDepth)) :-
+    integer(MaxDepth),
+    MaxDepth >= 0.
+answer_write_option_valid(ignore_ops(Bool)) :- bool_flag_value(Bool).
+answer_write_option_valid(numbervars(Bool)) :- bool_flag_value(Bool).
+answer_write_option_valid(portray(Bool)) :- bool_flag_value(Bool).
+answer_write_option_valid(quoted(Bool)) :- bool_flag_value(Bool).
+
+bool_flag_value(Bool) :- Bool == true.
+bool_flag_value(Bool) :- Bool == false.
+
 % control operators.
 
 fail :- '$fail'.
@@ -611,6 +725,54 @@ handle_ball(_, _, _) :-
 
 throw(Ball) :- '$set_ball'(Ball), '$unwind_stack'.
 
+% like catch/3, but if the debug flag was on when an error was
+% thrown, its context is extended with the backtrace captured at that
+% point, i.e. error(E, C) is caught as
+% error(E, context(prolog_stack(Frames), C)). C is further given the
+% source location of the innermost frame whose clause was loaded from
+% a file while the debug flag was on, as context(C, File:Line:Col),
+% unless C is a context/2 term already.
+
+:- meta_predicate catch_with_backtrace(0, ?, 0).
+
+catch_with_backtrace(G,C,R) :-
+    '$get_current_block'(Bb),
+    '$call_with_default_policy'(catch_with_backtrace(G,C,R,Bb)).
+
+:- meta_predicate catch_with_backtrace(0, ?, 0, +).
+
+:- non_counted_backtracking catch_with_backtrace/4.
+catch_with_backtrace(G,C,R,Bb) :-
+    '$install_new_block'(NBb),
+    call(G),
+    '$call_with_default_policy'(end_block(Bb, NBb)).
+catch_with_backtrace(G,C,R,Bb) :-
+    '$reset_block'(Bb),
+    '$get_ball'(Ball0),
+    '$get_backtrace'(Frames),
+    '$call_with_default_policy'(add_backtrace(Ball0, Frames, Ball)),
+    '$call_with_default_policy'(handle_ball(Ball, C, R)).
+
+:- non_counted_backtracking add_backtrace/3.
+add_backtrace(error(E, Ctx0), Frames, error(E, context(prolog_stack(Frames), Ctx))) :-
+    Frames \== [],
+    !,
+    (  nonvar(Ctx0),
+       Ctx0 = context(_, _) ->
+       Ctx = Ctx0
+    ;  '$call_with_default_policy'(frames_location(Frames, Position)) ->
+       Ctx = context(Ctx0, Position)
+    ;  Ctx = Ctx0
+    ).
+add_backtrace(Ball, _, Ball).
+
+:- non_counted_backtracking frames_location/2.
+frames_location([frame(PI, ClauseNum) | Frames], Position) :-
+    (  '$call'(loader:clause_location(PI, ClauseNum, Position)) ->
+       true
+    ;  '$call_with_default_policy'(frames_location(Frames, Position))
+    ).
+
 
 % :- meta_predicate '$iterate_find_all'(?, 0, ?, ?).
 
@@ -997,6 +1159,12 @@ retract(Clause) :-
        retract_clause(Head, Body)
     ).
 
+retractall(Head) :-
+    (  retract((Head :- _)),
+       '$fail'
+    ;  true
+    ).
+
 
 module_abolish(Pred, Module) :-
     (  var(Pred) ->
@@ -1176,6 +1344,13 @@ atom_length(Atom, Length) :-
     ;  throw(error(type_error(atom, Atom), atom_length/2)) % 8.16.1.3 b)
     ).
 
+% a complete partial string is made an atom from its packed text,
+% without looking at its characters one by one.
+atom_chars(Atom, List) :-
+    var(Atom),
+    '$pstr_atom'(List, Atom0),
+    !,
+    Atom = Atom0.
 atom_chars(Atom, List) :-
     '$skip_max_list'(_, -1, List, Tail),
     (  ( Tail == [] ; var(Tail) ) ->
@@ -1539,6 +1714,8 @@ check_stream_property(alias(A), alias, A) :-
     ( var(A) -> true ; atom(A) ).
 check_stream_property(position(P), position, P) :-
     ( var(P) -> true ; is_stream_position(P)).
+check_stream_property(line_position(C), line_position, C) :-
+    ( var(C) -> true ; integer(C), C >= 0 ).
 check_stream_property(end_of_stream(E), end_of_stream, E) :-
     ( var(E) -> true ; lists:member(E, [not, at, past]) ).
 check_stream_property(eof_action(A), eof_action, A) :-
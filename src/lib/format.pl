@@ -22,12 +22,24 @@
      ~s    use the next argument here, which must be a string
      ~d    use the next argument here, which must be an integer
      ~f    use the next argument here, a floating point number
-     ~Nf   where N is an integer: format the float argument
-           using N digits after the decimal point
+     ~Nf   where N is an integer: format the number argument
+           using N digits after the decimal point. Integers and
+           rationals are rounded exactly, without going through floats.
+     ~e    use the next argument here, a number in exponential
+           notation with 6 digits after the decimal point
+     ~Ne   like ~e, using N digits after the decimal point
+     ~g    use the next argument here, a number in the shorter
+           of fixed and exponential notation, with 6 significant digits
+     ~Ng   like ~g, using N significant digits
+     ~c    use the next argument here, which must be a character code
+     ~Nc   like ~c, emitting the character N times
+     ~p    use the next argument here, printed by the user-defined
+           hook user:portray/1 if it succeeds, and as by writeq/1 otherwise
      ~Nd   like ~d, placing the last N digits after a decimal point;
            if N is 0 or omitted, no decimal point is used.
      ~ND   like ~Nd, separating digits to the left of the decimal point
            in groups of three, using the character "," (comma)
+     ~N:d  the same as ~ND
      ~Nr   where N is an integer between 2 and 36: format the
            next argument, which must be an integer, in radix N.
            The characters "a" to "z" are used for radices 10 to 36.
@@ -74,7 +86,8 @@
                    format/3,
                    portray_clause/1,
                    portray_clause/2,
-                   listing/1
+                   listing/1,
+                   portray_output_chars/2
                   ]).
 
 :- use_module(library(dcgs)).
@@ -192,6 +205,12 @@ cells([~|Fs0], Args0, Tab, Es, VNs) -->
               ) }
         ),
         cells(Fs, Args, Tab, [chars(Cs)|Es], VNs).
+cells([~|Fs0], Args0, Tab, Es, VNs) -->
+        { numeric_argument(Fs0, Num, [:,d|Fs], Args0, Args) },
+        !,
+        { number_chars(Num, NCs),
+          append(NCs, ['D'|Fs], Fs1) },
+        cells([~|Fs1], Args, Tab, Es, VNs).
 cells([~|Fs0], Args0, Tab, Es, VNs) -->
         { numeric_argument(Fs0, Num, ['D'|Fs], Args0, [Arg|Args]) },
         !,
@@ -222,32 +241,39 @@ cells([~,f|Fs], [Arg|Args], Tab, Es, VNs) --> !,
         { format_number_chars(Arg, Chars) },
         cells(Fs, Args, Tab, [chars(Chars)|Es], VNs).
 cells([~|Fs0], Args0, Tab, Es, VNs) -->
-        { numeric_argument(Fs0, Num, [f|Fs], Args0, [Arg|Args]) },
+        { numeric_argument(Fs0, Num, [f|Fs], Args0, [Arg0|Args]) },
         !,
-        { format_number_chars(Arg, Cs0),
-          phrase(upto_what(Bs, .), Cs0, Cs),
-          (   Num =:= 0 -> Chars = Bs
-          ;   (   Cs = ['.'|Rest] ->
-                  length(Rest, L),
-                  (   Num < L ->
-                      length(Ds, Num),
-                      append(Ds, _, Rest)
-                  ;   Num =:= L ->
-                      Ds = Rest
-                  ;   Num > L,
-                      Delta is Num - L,
-                      % we should look into the float with
-                      % greater accuracy here, and use the
-                      % actual digits instead of 0.
-                      length(Zs, Delta),
-                      maplist(=('0'), Zs),
-                      append(Rest, Zs, Ds)
-                  )
-              ;   length(Ds, Num),
-                  maplist(=('0'), Ds)
-              ),
-              append(Bs, ['.'|Ds], Chars)
-          ) },
+        { Arg is Arg0, % evaluate compound expression
+          '$format_number'(f, Num, Arg, Chars) },
+        cells(Fs, Args, Tab, [chars(Chars)|Es], VNs).
+cells([~,e|Fs], Args, Tab, Es, VNs) --> !,
+        cells([~,'6',e|Fs], Args, Tab, Es, VNs).
+cells([~|Fs0], Args0, Tab, Es, VNs) -->
+        { numeric_argument(Fs0, Num, [e|Fs], Args0, [Arg0|Args]) },
+        !,
+        { Arg is Arg0,
+          '$format_number'(e, Num, Arg, Chars) },
+        cells(Fs, Args, Tab, [chars(Chars)|Es], VNs).
+cells([~,g|Fs], Args, Tab, Es, VNs) --> !,
+        cells([~,'6',g|Fs], Args, Tab, Es, VNs).
+cells([~|Fs0], Args0, Tab, Es, VNs) -->
+        { numeric_argument(Fs0, Num, [g|Fs], Args0, [Arg0|Args]) },
+        !,
+        { Arg is Arg0,
+          '$format_number'(g, Num, Arg, Chars) },
+        cells(Fs, Args, Tab, [chars(Chars)|Es], VNs).
+cells([~,c|Fs], Args, Tab, Es, VNs) --> !,
+        cells([~,'1',c|Fs], Args, Tab, Es, VNs).
+cells([~|Fs0], Args0, Tab, Es, VNs) -->
+        { numeric_argument(Fs0, Num, [c|Fs], Args0, [Arg|Args]) },
+        !,
+        { must_be(integer, Arg),
+          char_code(Char, Arg),
+          length(Chars, Num),
+          maplist(=(Char), Chars) },
+        cells(Fs, Args, Tab, [chars(Chars)|Es], VNs).
+cells([~,p|Fs], [Arg|Args], Tab, Es, VNs) --> !,
+        { portray_chars(Arg, VNs, Chars) },
         cells(Fs, Args, Tab, [chars(Chars)|Es], VNs).
 cells([~|Fs0], Args0, Tab, Es, VNs) -->
         { numeric_argument(Fs0, Num, [r|Fs], Args0, [Arg|Args]) },
@@ -291,6 +317,29 @@ format_number_chars(N0, Chars) :-
         N is N0, % evaluate compound expression
         number_chars(N, Chars).
 
+portray_chars(Term, VNs, Chars) :-
+        (   '$predicate_defined'(user, portray, 1),
+            portray_output_chars(Term, Chars0) ->
+            Chars = Chars0
+        ;   write_term_to_chars(Term, [quoted(true),variable_names(VNs)], Chars)
+        ).
+
+% portray_output_chars(+Term, -Chars): Chars is what user:portray/1
+% writes for Term. Fails if portray/1 fails.
+
+portray_output_chars(Term, Chars) :-
+        '$open_chars_output_stream'(S),
+        current_output(Out),
+        set_output(S),
+        catch((   user:portray(Term) -> Success = true
+              ;   Success = false
+              ), E, true),
+        set_output(Out),
+        (   nonvar(E) -> throw(E)
+        ;   Success == true
+        ),
+        '$chars_output_stream_contents'(S, Chars).
+
 n_newlines(0) --> !.
 n_newlines(N0) --> { N0 > 0, N is N0 - 1 }, [newline], n_newlines(N).
 
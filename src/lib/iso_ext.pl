@@ -6,6 +6,8 @@
 :- module(iso_ext, [bb_b_put/2,
                     bb_get/2,
                     bb_put/2,
+                    b_getval/2,
+                    b_setval/2,
                     call_cleanup/2,
                     call_with_inference_limit/3,
                     forall/2,
@@ -15,9 +17,15 @@
                     setup_call_cleanup/3,
                     call_nth/2,
                     variant/2,
-                    copy_term_nat/2]).
+                    copy_term_nat/2,
+                    nb_current/2,
+                    nb_getval/2,
+                    nb_setarg/3,
+                    nb_setval/2,
+                    setarg/3]).
 
-:- use_module(library(error), [can_be/2, domain_error/3, type_error/3]).
+:- use_module(library(error), [can_be/2, domain_error/3, instantiation_error/1,
+                                type_error/3]).
 
 
 :- meta_predicate call_cleanup(0, 0).
@@ -50,6 +58,84 @@ bb_get(Key, Value) :-
     ;  type_error(atom, Key, bb_get/2)
     ).
 
+%% global variables with the interface of SWI-Prolog. they share
+%% their keys with the blackboard predicates above.
+%%
+%% nb_setval/2 stores a copy of Value, attributes included, which
+%% survives backtracking. b_setval/2 stores Value itself, so later
+%% bindings of its variables are seen by b_getval/2, and the
+%% assignment is undone on backtracking.
+
+b_setval(Key, Value) :-
+    (  atom(Key) ->
+       '$store_backtrackable_global_var'(Key, Value)
+    ;  type_error(atom, Key, b_setval/2)
+    ).
+
+nb_setval(Key, Value) :-
+    (  atom(Key) ->
+       '$store_global_var'(Key, Value)
+    ;  type_error(atom, Key, nb_setval/2)
+    ).
+
+b_getval(Key, Value) :-
+    global_var_value(Key, Value, b_getval/2).
+
+nb_getval(Key, Value) :-
+    global_var_value(Key, Value, nb_getval/2).
+
+global_var_value(Key, Value, PI) :-
+    (  atom(Key) ->
+       (  '$fetch_global_var'(Key, Value0) ->
+          Value = Value0
+       ;  throw(error(existence_error(variable, Key), PI))
+       )
+    ;  type_error(atom, Key, PI)
+    ).
+
+nb_current(Key, Value) :-
+    (  var(Key) ->
+       '$global_var_keys'(Keys),
+       global_var_member(Keys, Key, Value)
+    ;  atom(Key) ->
+       '$fetch_global_var'(Key, Value)
+    ;  type_error(atom, Key, nb_current/2)
+    ).
+
+global_var_member([K|Ks], Key, Value) :-
+    (  '$fetch_global_var'(K, Value0),
+       Key = K,
+       Value = Value0
+    ;  global_var_member(Ks, Key, Value)
+    ).
+
+%% destructive assignment to an argument of a compound term.
+%%
+%% setarg/3 is undone on backtracking, and Value is not copied.
+%% nb_setarg/3 stores a copy of Value, attributes included, and the
+%% assignment survives backtracking. Both fail if N is not the index
+%% of an argument of Term. A value read from Term, with arg/3 or by
+%% unification, keeps its value after the assignment, e.g.
+%%
+%%   ?- T = f(a), arg(1, T, Old), setarg(1, T, b).
+%%   T = f(b), Old = a.
+
+setarg(N, Term, Value) :-
+    must_be_setarg(N, Term, setarg/3),
+    '$setarg'(N, Term, Value).
+
+nb_setarg(N, Term, Value) :-
+    must_be_setarg(N, Term, nb_setarg/3),
+    '$nb_setarg'(N, Term, Value).
+
+must_be_setarg(N, Term, PI) :-
+    (  var(N) -> instantiation_error(PI)
+    ;  \+ integer(N) -> type_error(integer, N, PI)
+    ;  var(Term) -> instantiation_error(PI)
+    ;  \+ compound(Term) -> type_error(compound, Term, PI)
+    ;  true
+    ).
+
 
 call_cleanup(G, C) :- setup_call_cleanup(true, G, C).
 
@@ -78,8 +164,10 @@ scc_helper(C, G, Bb) :-
 scc_helper(_, _, Bb) :-
     '$reset_block'(Bb),
     '$get_ball'(Ball),
+    '$get_raw_backtrace'(Backtrace),
     '$call_with_default_policy'(run_cleaners_with_handling),
     '$erase_ball',
+    '$keep_backtrace'(Backtrace),
     '$call_with_default_policy'(throw(Ball)).
 scc_helper(_, _, _) :-
     '$get_cp'(Cp),
@@ -121,6 +209,8 @@ end_block(B, _Bb, NBb, L) :-
 handle_ile(B, inference_limit_exceeded(B), inference_limit_exceeded) :- !.
 handle_ile(B, E, _) :-
     '$remove_call_policy_check'(B),
+    '$get_raw_backtrace'(Backtrace),
+    '$keep_backtrace'(Backtrace),
     '$call_with_default_policy'(throw(E)).
 
 :- meta_predicate call_with_inference_limit(0, ?, ?).
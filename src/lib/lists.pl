@@ -1,4 +1,4 @@
-:- module(lists, [member/2, select/3, append/2, append/3, foldl/4, foldl/5,
+:- module(lists, [member/2, select/3, append/2, append/3, foldl/4, foldl/5, foldl/6,
 		          memberchk/2, reverse/2, length/2, maplist/2,
 		          maplist/3, maplist/4, maplist/5, maplist/6,
 		          maplist/7, maplist/8, maplist/9, same_length/2, nth0/3,
@@ -19,6 +19,7 @@
 
 :- meta_predicate foldl(3, ?, ?, ?).
 :- meta_predicate foldl(4, ?, ?, ?, ?).
+:- meta_predicate foldl(5, ?, ?, ?, ?, ?).
 
 
 length(Xs, N) :-
@@ -64,8 +65,19 @@ append([L0|Ls0], Ls) :-
     append(Ls0, Rest).
 
 
+% the rest of a complete partial string is copied in one go, rather
+% than a character at a time. '$pstr_append'/3 fails at once on
+% anything else, which is then appended by append_/3.
 append([], R, R).
-append([X|L], R, [X|S]) :- append(L, R, S).
+append([X|L], R, [X|S]) :-
+    (   '$pstr_append'(L, R0, S0) ->
+        R = R0,
+        S = S0
+    ;   append_(L, R, S)
+    ).
+
+append_([], R, R).
+append_([X|L], R, [X|S]) :- append_(L, R, S).
 
 
 memberchk(X, Xs) :- member(X, Xs), !.
@@ -155,6 +167,14 @@ foldl_([X|Xs], [Y|Ys], G_4, A0, A) :-
         call(G_4, X, Y, A0, A1),
         foldl_(Xs, Ys, G_4, A1, A).
 
+foldl(Goal_5, Xs, Ys, Zs, A0, A) :-
+        foldl_(Xs, Ys, Zs, Goal_5, A0, A).
+
+foldl_([], [], [], _, A, A).
+foldl_([X|Xs], [Y|Ys], [Z|Zs], G_5, A0, A) :-
+        call(G_5, X, Y, Z, A0, A1),
+        foldl_(Xs, Ys, Zs, G_5, A1, A).
+
 transpose(Ls, Ts) :-
         lists_transpose(Ls, Ts).
 
@@ -201,7 +221,15 @@ nth0(N, Es, E) :-
         can_be(integer, N),
         can_be(list, Es),
         (   integer(N) ->
-            nth0_index(N, Es, E)
+            N >= 0,
+            % skips over partial strings a segment at a time.
+            '$skip_max_list'(M, N, Es, Es0),
+            (   M =:= N ->
+                Es0 = [E|_]
+            ;   var(Es0) ->
+                R is N - M,
+                nth0_index(R, Es0, E)
+            )
         ;   nth0_search(N, Es, E)
         ).
 
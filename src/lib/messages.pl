@@ -0,0 +1,241 @@
+:- module(messages, [print_message_lines/3,
+                     translate_message//1]).
+
+/* - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+   Messages are terms translated into lines of text by the nonterminal
+   message//1. The lines are a list of elements:
+
+     Format-Args   text formatted as by format_//2
+     nl            a line break
+     Atomic        text written as by write/1
+
+   The translation is first looked up in user:message//1, so messages
+   can be defined, localised or overridden by the user. Otherwise the
+   rules of this module are used, which cover the ISO error terms and
+   the messages of the loader and toplevel.
+
+   print_message(Kind, Message) prints the translation of Message,
+   prefixed according to Kind, which is one of informational, warning,
+   error and silent. Errors and warnings are printed to user_error,
+   informational messages to the current output. Before printing, the
+   message is offered to user:message_hook(Message, Kind, Lines). If
+   the hook succeeds, the message is considered handled. Silent
+   messages are passed to the hook but never printed. Both hooks are
+   declared below, so they fail until the user defines them, and can
+   be defined in any file.
+
+   print_message/2 and message_to_codes/3 are exported by the loader,
+   which calls '$print_message'/2 and '$message_to_codes'/3 below.
+- - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
+
+:- use_module(library(dcgs)).
+:- use_module(library(error)).
+:- use_module(library(format)).
+:- use_module(library(lists)).
+
+:- dynamic(user:message/3).
+:- multifile(user:message/3).
+:- dynamic(user:message_hook/3).
+:- multifile(user:message_hook/3).
+
+'$print_message'(Kind, Message) :-
+    must_be_message_kind(Kind, print_message/2),
+    phrase(translate_message(Message), Lines),
+    (  user:message_hook(Message, Kind, Lines) ->
+       true
+    ;  Kind == silent ->
+       true
+    ;  print_message_lines(Kind, Lines, Cs),
+       message_stream(Kind, Stream),
+       format(Stream, "~s~n", [Cs])
+    ).
+
+message_stream(informational, Stream) :-
+    current_output(Stream).
+message_stream(warning, user_error).
+message_stream(error, user_error).
+
+'$message_to_codes'(Message, Kind, Codes) :-
+    must_be_message_kind(Kind, message_to_codes/3),
+    phrase(translate_message(Message), Lines),
+    print_message_lines(Kind, Lines, Cs),
+    maplist(char_code, Cs, Codes).
+
+%% print_message_lines(+Kind, +Lines, -Chars)
+%
+% Chars is the text of Lines, each line starting with the prefix of
+% messages of Kind.
+
+print_message_lines(Kind, Lines, Cs) :-
+    must_be_message_kind(Kind, print_message_lines/3),
+    message_prefix(Kind, Prefix),
+    phrase(message_lines(Lines, Prefix), Cs).
+
+must_be_message_kind(Kind, PI) :-
+    (  var(Kind) ->
+       instantiation_error(PI)
+    ;  message_prefix(Kind, _) ->
+       true
+    ;  domain_error(message_kind, Kind, PI)
+    ).
+
+message_prefix(informational, "% ").
+message_prefix(warning, "Warning: ").
+message_prefix(error, "error: ").
+message_prefix(silent, "").
+
+message_lines([], _) --> [].
+message_lines([Line|Lines], Prefix) -->
+    prefix_chars(Prefix),
+    message_line(Line, Prefix),
+    message_lines_(Lines, Prefix).
+
+message_lines_([], _) --> [].
+message_lines_([Line|Lines], Prefix) -->
+    message_line(Line, Prefix),
+    message_lines_(Lines, Prefix).
+
+message_line(nl, Prefix) -->
+    !,
+    "\n",
+    prefix_chars(Prefix).
+message_line(Format-Args, _) -->
+    !,
+    { format_chars(Format, Fs) },
+    format_(Fs, Args).
+message_line(Line, _) -->
+    format_("~w", [Line]).
+
+prefix_chars([]) --> [].
+prefix_chars([C|Cs]) --> [C], prefix_chars(Cs).
+
+format_chars(Format, Fs) :-
+    (  atom(Format) ->
+       atom_chars(Format, Fs)
+    ;  Fs = Format
+    ).
+
+%% translate_message(+Message)//
+%
+% The lines of Message, as defined by user:message//1 or else by the
+% rules of this module.
+
+translate_message(Message) -->
+    (  { phrase(user:message(Message), Lines) } ->
+       Lines
+    ;  message(Message) ->
+       []
+    ;  ["Unknown message: ~q"-[Message]]
+    ).
+
+message(format(Format, Args)) -->
+    [Format-Args].
+message(unhandled_exception(error('$interrupt_thrown', repl))) -->
+    !,
+    ["Execution interrupted"-[]].
+message(unhandled_exception(error(E, context(prolog_stack(Frames), Context)))) -->
+    !,
+    message(error(E, Context)),
+    backtrace_frames(Frames, 0).
+message(unhandled_exception(error(E, Context))) -->
+    !,
+    message(error(E, Context)).
+message(unhandled_exception(Ball)) -->
+    ["caught: ~q"-[Ball]].
+message(load_error(error(E, Context))) -->
+    !,
+    message(error(E, Context)).
+message(load_error(Error)) -->
+    ["caught: ~q"-[Error]].
+message(singletons(File:Line:Col, Names)) -->
+    ["~w:~w:~w: singleton variables "-[File, Line, Col]],
+    variable_names(Names).
+message(error(E, Context)) -->
+    error_context(Context),
+    iso_error(E),
+    error_context_message(Context).
+
+backtrace_frames([], _) --> [].
+backtrace_frames([frame(PI, ClauseNum)|Frames], N) -->
+    [nl, "   [~w] ~q (clause ~w)"-[N, PI, ClauseNum]],
+    { N1 is N + 1 },
+    backtrace_frames(Frames, N1).
+
+variable_names([Name]) -->
+    !,
+    ["~w"-[Name]].
+variable_names([Name|Names]) -->
+    ["~w, "-[Name]],
+    variable_names(Names).
+
+error_context(Context) -->
+    { var(Context) },
+    !.
+error_context(context(prolog_stack(_), Context)) -->
+    !,
+    error_context(Context).
+error_context(context(Context, Position)) -->
+    { nonvar(Position),
+      Position = File:Line:Col },
+    !,
+    ["~w:~w:~w: "-[File, Line, Col]],
+    error_context(Context).
+error_context(context(PI, _)) -->
+    !,
+    error_context(PI).
+error_context(Context) -->
+    ["~q: "-[Context]].
+
+error_context_message(Context) -->
+    { nonvar(Context),
+      Context = context(Context0, Message) },
+    !,
+    (  { nonvar(Message),
+         Message \= _:_:_ } ->
+       [" (~w)"-[Message]]
+    ;  error_context_message(Context0)
+    ).
+error_context_message(_) --> [].
+
+iso_error(E) -->
+    { var(E) },
+    !,
+    ["Unknown error term: ~q"-[E]].
+iso_error(instantiation_error) -->
+    !,
+    ["Arguments are not sufficiently instantiated"-[]].
+iso_error(uninstantiation_error(Culprit)) -->
+    !,
+    ["Expected an unbound variable, found ~q"-[Culprit]].
+iso_error(type_error(Type, Culprit)) -->
+    !,
+    ["Type error: expected ~w, found ~q"-[Type, Culprit]].
+iso_error(domain_error(Domain, Culprit)) -->
+    !,
+    ["Domain error: expected ~w, found ~q"-[Domain, Culprit]].
+iso_error(existence_error(procedure, PI)) -->
+    !,
+    ["Unknown procedure: ~q"-[PI]].
+iso_error(existence_error(Kind, Culprit)) -->
+    !,
+    ["Existence error: ~w ~q does not exist"-[Kind, Culprit]].
+iso_error(permission_error(Action, Type, Culprit)) -->
+    !,
+    ["Permission error: no permission to ~w ~w ~q"-[Action, Type, Culprit]].
+iso_error(representation_error(Flag)) -->
+    !,
+    ["Representation error: cannot represent ~w"-[Flag]].
+iso_error(evaluation_error(Error)) -->
+    !,
+    ["Evaluation error: ~w"-[Error]].
+iso_error(resource_error(Resource)) -->
+    !,
+    ["Resource error: not enough ~w"-[Resource]].
+iso_error(syntax_error(Error)) -->
+    !,
+    ["Syntax error: ~w"-[Error]].
+iso_error(system_error) -->
+    !,
+    ["System error"-[]].
+iso_error(E) -->
+    ["Unknown error term: ~q"-[E]].
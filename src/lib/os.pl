@@ -14,7 +14,8 @@
 
 :- module(os, [getenv/2,
                setenv/2,
-               unsetenv/1]).
+               unsetenv/1,
+               on_signal/3]).
 
 :- use_module(library(error)).
 :- use_module(library(charsio)).
@@ -55,3 +56,82 @@ permitted('_').
 must_be_chars(Cs) :-
         must_be(list, Cs),
         maplist(must_be(character), Cs).
+
+/* - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+   on_signal(Signal, Old, New) unifies Old with the current handler of
+   Signal and installs New as its handler. If New is a variable, it
+   is unified with Old and the handler is left unchanged.
+
+   Signal is one of hup, term, usr1, usr2 and pipe. A handler is one
+   of:
+
+     default    the default action of the operating system
+     ignore     the signal is ignored
+     throw      the exception signal(Signal) is thrown
+     Closure    call(Closure, Signal) is called
+
+   Handlers run at the next call after the signal is received, and
+   the interrupted computation resumes afterwards unless the handler
+   throws an exception.
+
+   Example:
+
+       ?- on_signal(term, _, throw).
+          true.
+- - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
+
+:- dynamic(signal_handler/2).
+
+:- meta_predicate on_signal(?, ?, 1).
+
+on_signal(Signal, Old, M:New) :-
+        must_be(atom, Signal),
+        (   signal(Signal, Initial) -> true
+        ;   domain_error(signal, Signal, on_signal/3)
+        ),
+        (   signal_handler(Signal, Old0) -> Old = Old0
+        ;   Old = Initial
+        ),
+        (   var(New) -> New = Old
+        ;   memberchk(New, [default, ignore, throw]) ->
+            set_signal_handler(Signal, New)
+        ;   (   atom(New)
+            ;   compound(New)
+            ) ->
+            set_signal_handler(Signal, M:New)
+        ;   type_error(callable, New, on_signal/3)
+        ).
+
+%% The handlers in effect at startup. Rust ignores SIGPIPE.
+
+signal(hup, default).
+signal(term, default).
+signal(usr1, default).
+signal(usr2, default).
+signal(pipe, ignore).
+
+set_signal_handler(Signal, Handler) :-
+        (   memberchk(Handler, [default, ignore]) -> Action = Handler
+        ;   Action = handle
+        ),
+        '$set_signal_action'(Signal, Action),
+        retractall(os:signal_handler(Signal, _)),
+        assertz(os:signal_handler(Signal, Handler)).
+
+%% The machine calls handle_signals/0 when signals are pending, and
+%% retries the interrupted call after it.
+
+handle_signals :-
+        '$pending_signals'(Signals),
+        maplist(handle_signal, Signals),
+        '$return_from_verify_attr'.
+
+handle_signal(Signal) :-
+        (   signal_handler(Signal, Handler) -> true
+        ;   Handler = default
+        ),
+        (   Handler == throw -> throw(signal(Signal))
+        ;   memberchk(Handler, [default, ignore]) -> true
+        ;   call(Handler, Signal) -> true
+        ;   true
+        ).
@@ -0,0 +1,100 @@
+/* Operations on the packed prefix of partial strings.
+
+   A partial string stores its characters as UTF-8 in a single heap
+   buffer, followed by a tail. The predicates of this library work
+   on that buffer directly, rather than on the list cells that
+   unification presents, and leave the tail alone. For a string
+   read under double_quotes set to chars, the tail is [].
+
+   Sub, Prefix, Suffix and Sep may be given as any text: atoms,
+   strings, or lists of characters or codes.
+
+   ?- partial_string("hello", Ls, _), partial_string_search(Ls, "l", I).
+   %@    Ls = "hello"||_A, I = 2
+   %@ ;  Ls = "hello"||_A, I = 3
+   %@ ;  false.
+*/
+
+:- module(pstrings, [partial_string_length/2,
+                     partial_string_prefix/2,
+                     partial_string_search/3,
+                     partial_string_split/3,
+                     partial_string_suffix/2]).
+
+:- use_module(library(error)).
+
+must_be_packed(PStr, PI) :-
+    (   var(PStr) ->
+        instantiation_error(PI)
+    ;   '$pstr_affix'(prefix, PStr, []) ->
+        true
+    ;   type_error(partial_string, PStr, PI)
+    ).
+
+must_be_text(Text, PI) :-
+    (   var(Text) ->
+        instantiation_error(PI)
+    ;   true
+    ).
+
+%% partial_string_length(+PStr, -N)
+%
+% N is the number of characters in the packed prefix of PStr.
+
+partial_string_length(PStr, N) :-
+    must_be_packed(PStr, partial_string_length/2),
+    can_be(integer, N),
+    '$pstr_length'(PStr, N).
+
+%% partial_string_prefix(+PStr, +Prefix)
+%
+% The packed prefix of PStr starts with Prefix.
+
+partial_string_prefix(PStr, Prefix) :-
+    must_be_packed(PStr, partial_string_prefix/2),
+    must_be_text(Prefix, partial_string_prefix/2),
+    '$pstr_affix'(prefix, PStr, Prefix).
+
+%% partial_string_suffix(+PStr, +Suffix)
+%
+% The packed prefix of PStr ends with Suffix.
+
+partial_string_suffix(PStr, Suffix) :-
+    must_be_packed(PStr, partial_string_suffix/2),
+    must_be_text(Suffix, partial_string_suffix/2),
+    '$pstr_affix'(suffix, PStr, Suffix).
+
+%% partial_string_search(+PStr, +Sub, ?Index)
+%
+% Sub occurs in the packed prefix of PStr after Index characters.
+% Occurrences are enumerated from left to right on backtracking.
+
+partial_string_search(PStr, Sub, Index) :-
+    must_be_packed(PStr, partial_string_search/3),
+    must_be_text(Sub, partial_string_search/3),
+    can_be(integer, Index),
+    (   integer(Index) ->
+        Index >= 0,
+        '$pstr_search'(PStr, Sub, Index, Index)
+    ;   search_from(PStr, Sub, 0, Index)
+    ).
+
+search_from(PStr, Sub, From, Index) :-
+    '$pstr_search'(PStr, Sub, From, Index0),
+    (   Index = Index0
+    ;   From1 is Index0 + 1,
+        search_from(PStr, Sub, From1, Index)
+    ).
+
+%% partial_string_split(+PStr, +Sep, -Parts)
+%
+% Parts are the strings between the occurrences of the non-empty
+% separator Sep in the packed prefix of PStr.
+
+partial_string_split(PStr, Sep, Parts) :-
+    must_be_packed(PStr, partial_string_split/3),
+    must_be_text(Sep, partial_string_split/3),
+    (   '$pstr_split'(PStr, Sep, Parts0) ->
+        Parts = Parts0
+    ;   domain_error(non_empty_separator, Sep, partial_string_split/3)
+    ).
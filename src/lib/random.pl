@@ -1,4 +1,7 @@
-:- module(random, [maybe/0, random/1, random_integer/3, set_random/1]).
+:- module(random, [maybe/0, random/1, random_integer/3, random_between/3,
+                   random_member/2, random_select/3, random_permutation/2,
+                   random_subseq/3, random_gaussian/3, random_exponential/2,
+                   set_random/1, getrand/1, setrand/1]).
 
 /* - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
    To retain desirable declarative properties, predicates that internally
@@ -7,17 +10,16 @@
 - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
 
 :- use_module(library(error)).
+:- use_module(library(lists), [length/2, nth0/3]).
+:- use_module(library(pairs), [pairs_values/2]).
 
 % succeeds with probability 0.5.
 maybe :- '$maybe'.
 
-% The higher the precision, the slower it gets.
-random_number_precision(64).
-
+% R is a float uniformly distributed in the open interval (0, 1).
 random(R) :-
     var(R),
-    random_number_precision(N),
-    rnd(N, R).
+    R is random_float.
 
 random_integer(Lower, Upper, R) :-
     var(R),
@@ -28,23 +30,87 @@ random_integer(Lower, Upper, R) :-
     ;   \+ integer(Upper) ->
             domain_error(integer, Upper, random_integer/3)
     ;   Upper > Lower,
-        random(R0),
-        R is floor((Upper - Lower) * R0 + Lower)
+        R is Lower + random(Upper - Lower)
+    ).
+
+% R is uniformly distributed over Lower..Upper, both bounds included.
+random_between(Lower, Upper, R) :-
+    must_be(integer, Lower),
+    must_be(integer, Upper),
+    Upper >= Lower,
+    R is Lower + random(Upper - Lower + 1).
+
+random_member(X, List) :-
+    must_be(list, List),
+    length(List, N),
+    N > 0,
+    I is random(N),
+    nth0(I, List, X).
+
+% removes a random element X from List, leaving Rest. if List is
+% unbound, X is instead inserted at a random position of Rest.
+random_select(X, List, Rest) :-
+    (   nonvar(List) ->
+        must_be(list, List),
+        length(List, N),
+        N > 0,
+        I is random(N)
+    ;   must_be(list, Rest),
+        length(Rest, N),
+        I is random(N + 1)
+    ),
+    nth0_select(I, List, X, Rest).
+
+nth0_select(0, [X|Xs], X, Xs) :- !.
+nth0_select(I, [Y|Xs], X, [Y|Ys]) :-
+    I1 is I - 1,
+    nth0_select(I1, Xs, X, Ys).
+
+random_permutation(List, Perm) :-
+    (   nonvar(List) ->
+        must_be(list, List),
+        random_permutation_(List, Perm)
+    ;   must_be(list, Perm),
+        random_permutation_(Perm, List)
     ).
 
-rnd(N, R) :-
-    rnd_(N, 0, R).
+random_permutation_(List, Perm) :-
+    random_keys(List, Pairs0),
+    keysort(Pairs0, Pairs),
+    pairs_values(Pairs, Perm).
 
-rnd_(0, R, R) :- !.
-rnd_(N, R0, R) :-
-    maybe,
-    !,
-    N1 is N - 1,
-    rnd_(N1, R0, R).
-rnd_(N, R0, R) :-
-    N1 is N - 1,
-    R1 is R0 + 1.0 / 2.0 ^ N,
-    rnd_(N1, R1, R).
+random_keys([], []).
+random_keys([X|Xs], [K-X|KXs]) :-
+    K is random_float,
+    random_keys(Xs, KXs).
+
+% each element of List goes to either Subseq or Complement with
+% probability 0.5, keeping the order of List in both.
+random_subseq(List, Subseq, Complement) :-
+    must_be(list, List),
+    random_subseq_(List, Subseq, Complement).
+
+random_subseq_([], [], []).
+random_subseq_([X|Xs], Subseq, Complement) :-
+    (   maybe ->
+        Subseq = [X|Subseq0],
+        Complement = Complement0
+    ;   Subseq = Subseq0,
+        Complement = [X|Complement0]
+    ),
+    random_subseq_(Xs, Subseq0, Complement0).
+
+% X is drawn from the normal distribution with the given mean and
+% standard deviation, by the Box-Muller transform.
+random_gaussian(Mean, StdDev, X) :-
+    U1 is random_float,
+    U2 is random_float,
+    X is Mean + StdDev * sqrt(-2 * log(U1)) * cos(2 * pi * U2).
+
+% X is drawn from the exponential distribution with rate Lambda.
+random_exponential(Lambda, X) :-
+    U is random_float,
+    X is -log(U) / Lambda.
 
 set_random(Seed) :-
     (   nonvar(Seed) ->
@@ -57,3 +123,18 @@ set_random(Seed) :-
     ;   instantiation_error(set_random/1)
     ).
 
+% the generator state is the term rand(S0, S1, S2, S3) of four 64-bit
+% words. restoring it with setrand/1 repeats the numbers drawn since
+% getrand/1 was called.
+getrand(State) :-
+    '$get_random_state'(S0, S1, S2, S3),
+    State = rand(S0, S1, S2, S3).
+
+setrand(State) :-
+    (   var(State) ->
+        instantiation_error(setrand/1)
+    ;   State = rand(S0, S1, S2, S3),
+        '$set_random_state'(S0, S1, S2, S3) ->
+        true
+    ;   domain_error(rand_state, State, setrand/1)
+    ).
@@ -3,12 +3,18 @@
    Written June 2020 by Markus Triska (triska@metalevel.at)
    Part of Scryer Prolog.
 
-   Currently, two predicates are provided:
+   The following predicates are provided:
 
    -  load_html(+Source, -Es, +Options)
    -  load_xml(+Source, -Es, +Options)
+   -  load_xml_stream(+Source, :Goal, +Options)
+   -  html_write(+Stream, +Es, +Options)
+   -  xml_write(+Stream, +Es, +Options)
+   -  html(+Spec)//
 
-   These predicates parse HTML and XML documents, respectively.
+   load_html/3 and load_xml/3 parse HTML and XML documents,
+   respectively. html_write/3 and xml_write/3 write documents in the
+   same representation to a stream.
 
    Source must be a stream, specified as stream(S), or a file,
    specified as file(Name), where Name is a list of characters, or a
@@ -24,8 +30,37 @@
        Key is an atom, and Value is a list of characters
      - Children is a list of elements as specified here.
 
-   Currently, Options are ignored. In the future, more options may be
-   provided to control parsing.
+   Comments and processing instructions are omitted. The following
+   options are supported:
+
+   * space(Space)
+     How white space in text is treated. With preserve (the default),
+     all text is kept as it is. With sgml, a newline directly after
+     a start tag and directly before an end tag is removed. With
+     remove, leading and trailing white space is removed from each
+     text. Text that becomes empty is omitted.
+   * dialect(Dialect)
+     With xmlns, element and attribute names in a namespace are
+     represented as URI:Local, and namespace declarations are
+     omitted from the attributes. With xml (the default for
+     load_xml/3), names are written as in the document, such as
+     prefix:local.
+   * attribute_value(Type)
+     Whether attribute values are represented as lists of
+     characters (string, the default) or atoms (atom).
+   * line_numbers(Bool)
+     If true, each element parsed by load_xml/3 gets an additional
+     first attribute '$line'=Line, the line on which it starts.
+     xml_write/3 and html_write/3 omit these attributes.
+
+   load_xml_stream(Source, Goal, Options) reads the XML document
+   from Source, which is stream(S) or file(Name), and calls
+   call(Goal, E) for each child E of the root element as soon as it
+   has been read. The document is therefore never held in memory as
+   a whole. The children are those load_xml/3 yields for the root,
+   texts included, with the entities declared in the DOCTYPE
+   resolved. The options are as for load_xml/3. If Goal fails,
+   load_xml_stream/3 stops reading and fails.
 
    Example:
 
@@ -49,16 +84,70 @@
 
    Use http_open/3 from library(http/http_open) to read answers from
    web servers via streams.
+
+   xml_write/3 and html_write/3 write a single element or a list of
+   elements and text. Special characters in text and attribute
+   values are escaped. Attribute values may also be atoms or numbers.
+   An element or attribute name can be of the form URI:Local to place
+   it in a namespace; the namespaces used are declared on the
+   outermost elements. The following options are supported:
+
+   * header(Bool)
+     Whether to write the XML declaration (default true). For
+     html_write/3, whether to write the HTML5 doctype instead, if no
+     other doctype is given.
+   * doctype(DocType), public(PubId), system(SysId)
+     Write a document type declaration for DocType, with the given
+     public and system identifiers.
+   * layout(Bool)
+     Whether to indent elements that contain only elements (default
+     true). The contents of pre, script, style and textarea elements
+     are never indented by html_write/3.
+   * indent(N)
+     The number of spaces per nesting level (default 2).
+   * nsmap(Map)
+     Map is a list of Prefix=URI pairs that determine the prefixes of
+     namespaces. The prefix xmlns makes URI the default namespace.
+     Other namespaces get the prefixes ns1, ns2, and so on.
+
+   For example:
+
+      ?- xml_write(user_output, element(item, [id=1], ["a < b"]), [header(false)]).
+      <item id="1">a &lt; b</item>
+
+   html(Spec) is a nonterminal that describes the characters of an
+   HTML fragment, for use in templates. Spec is one of:
+
+   * a list of specifications
+   * an atom, number or list of characters: escaped text
+   * Tag(Content): an element without attributes
+   * Tag(Attrs, Content): an element with attributes Name=Value
+   * &(Entity): the entity reference of an atom or character code
+   * \Rule: the nonterminal Rule, for example a list of raw characters
+
+   For example:
+
+      ?- phrase(html(p([class=note], ['Hello, ', b(world)])), Cs).
+         Cs = "<p class=\"note\">Hello, <b>world</b></p>".
 - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
 
 :- module(sgml, [load_html/3,
-                 load_xml/3]).
+                 load_xml/3,
+                 load_xml_stream/3,
+                 html_write/3,
+                 xml_write/3,
+                 html//1]).
 
 :- use_module(library(iso_ext)).
 :- use_module(library(error)).
 :- use_module(library(dcgs)).
+:- use_module(library(format)).
+:- use_module(library(lists)).
 :- use_module(library(pio)).
 
+:- meta_predicate html(2, ?, ?).
+:- meta_predicate load_xml_stream(?, 1, ?).
+
 load_html(Source, Es, Options) :-
         load_structure_(Source, Es, Options, html).
 load_xml(Source, Es, Options) :-
@@ -69,20 +158,76 @@ list([L|Ls]) --> [L], list(Ls).
 
 load_structure_([], [], _, _).
 load_structure_([C|Cs], [E], Options, What) :-
-        load_(What, [C|Cs], E, Options).
+        load_options(Options, What, Opts),
+        load_(What, [C|Cs], E, Opts).
 load_structure_(file(Fs), [E], Options, What) :-
-        must_be(list, Options),
+        load_options(Options, What, Opts),
         must_be(list, Fs),
         atom_chars(File, Fs),
         once(phrase_from_file(list(Cs), File)),
-        load_(What, Cs, E, Options).
+        load_(What, Cs, E, Opts).
 load_structure_(stream(Stream), [E], Options, What) :-
-        must_be(list, Options),
+        load_options(Options, What, Opts),
         read_to_end(Stream, Cs),
-        load_(What, Cs, E, Options).
+        load_(What, Cs, E, Opts).
+
+load_(html, Cs, E, Opts) :- '$load_html'(Cs, E, Opts).
+load_(xml, Cs, E, Opts) :- '$load_xml'(Cs, E, Opts).
+
+%% The options are validated here and passed to the parser as
+%% options(Space, Dialect, AttributeValue, LineNumbers).
+
+load_options(Options, What, options(Space, Dialect, Value, Lines)) :-
+        must_be(list, Options),
+        load_option(space(Space), Options, preserve, [preserve,sgml,remove]),
+        load_option(dialect(Dialect), Options, What, [html,xml,xmlns]),
+        load_option(attribute_value(Value), Options, string, [string,atom]),
+        load_option(line_numbers(Lines), Options, false, [true,false]).
+
+load_option(Option, Options, Default, Domain) :-
+        option(Option, Options, Default),
+        arg(1, Option, Value),
+        must_be(atom, Value),
+        (   memberchk(Value, Domain) -> true
+        ;   functor(Option, Name, _),
+            domain_error(Name, Value, load_xml/3)
+        ).
 
-load_(html, Cs, E, Options) :- '$load_html'(Cs, E, Options).
-load_(xml, Cs, E, Options) :- '$load_xml'(Cs, E, Options).
+load_xml_stream(Source, Goal, Options) :-
+        load_options(Options, xml, Opts),
+        (   var(Source) ->
+            instantiation_error(load_xml_stream/3)
+        ;   Source = stream(Stream) ->
+            load_stream_(Stream, Goal, Opts)
+        ;   Source = file(Fs) ->
+            must_be(list, Fs),
+            atom_chars(File, Fs),
+            setup_call_cleanup(open(File, read, Stream),
+                               load_stream_(Stream, Goal, Opts),
+                               close(Stream))
+        ;   domain_error(source, Source, load_xml_stream/3)
+        ).
+
+%% Each child is read in a failure-driven loop, so that the memory
+%% it occupies is reclaimed before the next one is read. The stream
+%% counts the lines read so far. The first child read is marked as
+%% such, since space(sgml) treats the text that opens the root apart.
+
+load_stream_(Stream, Goal, Opts) :-
+        (   '$xml_stream_root'(Stream, Root) ->
+            first_child_(First),
+            (   '$xml_stream_element'(Stream, Root, Opts, First, Es) ->
+                (   maplist(Goal, Es) -> fail
+                ;   !, fail
+                )
+            ;   !
+            )
+        ;   true
+        ).
+
+first_child_(true).
+first_child_(false) :-
+        repeat.
 
 read_to_end(Stream, Cs) :-
         '$get_n_chars'(Stream, 4096, Cs0),
@@ -90,3 +235,314 @@ read_to_end(Stream, Cs) :-
         ;   partial_string(Cs0, Cs, Rest),
             read_to_end(Stream, Rest)
         ).
+
+%% Writing documents.
+
+xml_write(Stream, Es, Options) :-
+        write_structure_(Stream, Es, Options, xml).
+html_write(Stream, Es, Options) :-
+        write_structure_(Stream, Es, Options, html).
+
+write_structure_(Stream, Es0, Options, Dialect) :-
+        must_be(list, Options),
+        (   Es0 = element(_,_,_) -> Es = [Es0]
+        ;   must_be(list, Es0),
+            Es = Es0
+        ),
+        option(layout(Layout), Options, true),
+        must_be(boolean, Layout),
+        option(indent(Step), Options, 2),
+        must_be(integer, Step),
+        option(nsmap(NSMap), Options, []),
+        must_be(list, NSMap),
+        namespaces(Es, NSMap, NSs),
+        phrase(document(Dialect, Es, NSs, Options, Layout-Step), Cs),
+        format(Stream, "~s", [Cs]).
+
+option(Option, Options, Default) :-
+        (   memberchk(Option, Options) -> true
+        ;   arg(1, Option, Default)
+        ).
+
+document(Dialect, Es, NSs, Options, Layout) -->
+        prologue(Dialect, Options),
+        top_contents(Es, Dialect, NSs, Layout),
+        "\n".
+
+prologue(Dialect, Options) -->
+        { option(header(Header), Options, true),
+          must_be(boolean, Header) },
+        (   { Dialect == xml, Header == true } ->
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"
+        ;   []
+        ),
+        (   { memberchk(doctype(DocType), Options) } ->
+            "<!DOCTYPE ", text(DocType),
+            (   { memberchk(public(PubId), Options) } ->
+                " PUBLIC \"", text(PubId), "\"",
+                (   { memberchk(system(SysId), Options) } ->
+                    " \"", text(SysId), "\""
+                ;   []
+                )
+            ;   { memberchk(system(SysId), Options) } ->
+                " SYSTEM \"", text(SysId), "\""
+            ;   []
+            ),
+            ">\n"
+        ;   { Dialect == html, Header == true } ->
+            "<!DOCTYPE html>\n"
+        ;   []
+        ).
+
+top_contents([], _, _, _) --> [].
+top_contents([E|Es], Dialect, NSs, Layout) -->
+        (   { E = element(Name, Attrs0, Children) } ->
+            { ns_declarations(NSs, Attrs0, Attrs) },
+            element(element(Name, Attrs, Children), Dialect, NSs, Layout, 0)
+        ;   content(E, Dialect, NSs, Layout, 0)
+        ),
+        (   { Es == [] } -> []
+        ;   "\n",
+            top_contents(Es, Dialect, NSs, Layout)
+        ).
+
+element(element(Name, Attrs, Children), Dialect, NSs, Layout, Depth) -->
+        { must_be(list, Attrs),
+          must_be(list, Children) },
+        "<", name(Name, NSs), attributes(Attrs, NSs),
+        (   { Children == [] } ->
+            (   { Dialect == xml } -> "/>"
+            ;   { void_element(Name) } -> ">"
+            ;   "></", name(Name, NSs), ">"
+            )
+        ;   ">",
+            (   { raw_element(Dialect, Name) } ->
+                raw_contents(Children)
+            ;   { Layout = true-Step,
+                  \+ preserve_element(Dialect, Name),
+                  maplist(is_element, Children) } ->
+                { Depth1 is Depth + 1 },
+                indented_contents(Children, Dialect, NSs, Layout, Depth1),
+                "\n", indent(Depth, Step)
+            ;   { Layout = _-Step,
+                  Layout1 = false-Step },
+                contents(Children, Dialect, NSs, Layout1, Depth)
+            ),
+            "</", name(Name, NSs), ">"
+        ).
+
+is_element(element(_,_,_)).
+
+indented_contents([], _, _, _, _) --> [].
+indented_contents([E|Es], Dialect, NSs, Layout, Depth) -->
+        { Layout = _-Step },
+        "\n", indent(Depth, Step),
+        element(E, Dialect, NSs, Layout, Depth),
+        indented_contents(Es, Dialect, NSs, Layout, Depth).
+
+contents([], _, _, _, _) --> [].
+contents([C|Cs], Dialect, NSs, Layout, Depth) -->
+        content(C, Dialect, NSs, Layout, Depth),
+        contents(Cs, Dialect, NSs, Layout, Depth).
+
+content(C, Dialect, NSs, Layout, Depth) -->
+        (   { var(C) } ->
+            { instantiation_error(xml_write/3) }
+        ;   { C = element(_,_,_) } ->
+            element(C, Dialect, NSs, Layout, Depth)
+        ;   escaped_text(C, text_char)
+        ).
+
+raw_contents([]) --> [].
+raw_contents([C|Cs]) -->
+        (   { C = element(_,_,_) } ->
+            { domain_error(text, C, html_write/3) }
+        ;   text(C)
+        ),
+        raw_contents(Cs).
+
+indent(Depth, Step) -->
+        { N is Depth * Step },
+        spaces(N).
+
+spaces(N) -->
+        (   { N > 0 } ->
+            " ",
+            { N1 is N - 1 },
+            spaces(N1)
+        ;   []
+        ).
+
+void_element(Name) :-
+        memberchk(Name, [area,base,br,col,embed,hr,img,input,link,meta,
+                         param,source,track,wbr]).
+
+raw_element(html, Name) :-
+        memberchk(Name, [script,style]).
+
+preserve_element(html, Name) :-
+        memberchk(Name, [pre,script,style,textarea]).
+
+attributes([], _) --> [].
+attributes([A|As], NSs) -->
+        (   { A = ('$line'=_) } ->
+            []
+        ;   { A = (Name=Value) } ->
+            " ", name(Name, NSs), "=\"",
+            escaped_text(Value, attribute_char),
+            "\""
+        ;   { domain_error(attribute, A, xml_write/3) }
+        ),
+        attributes(As, NSs).
+
+%% Text is given as an atom, a number or a list of characters.
+
+text_chars(Text, Cs) :-
+        (   var(Text) -> instantiation_error(xml_write/3)
+        ;   atom(Text) -> atom_chars(Text, Cs)
+        ;   number(Text) -> number_chars(Text, Cs)
+        ;   '$text_to_chars'(Text, Cs) -> true
+        ;   domain_error(text, Text, xml_write/3)
+        ).
+
+text(Text) -->
+        { text_chars(Text, Cs) },
+        list(Cs).
+
+escaped_text(Text, Escape) -->
+        { text_chars(Text, Cs) },
+        escaped_chars(Cs, Escape).
+
+escaped_chars([], _) --> [].
+escaped_chars([C|Cs], Escape) -->
+        (   { call(Escape, C, Es) } -> list(Es)
+        ;   [C]
+        ),
+        escaped_chars(Cs, Escape).
+
+text_char(<, "&lt;").
+text_char(>, "&gt;").
+text_char(&, "&amp;").
+
+attribute_char(<, "&lt;").
+attribute_char(&, "&amp;").
+attribute_char('"', "&quot;").
+
+%% Namespaces. NSs is a list of URI-Prefix pairs, where the prefix
+%% xmlns denotes the default namespace.
+
+namespaces(Es, NSMap, NSs) :-
+        phrase(content_uris(Es), URIs0),
+        list_to_set(URIs0, URIs),
+        namespace_prefixes(URIs, NSMap, 1, NSs).
+
+content_uris([]) --> [].
+content_uris([E|Es]) -->
+        (   { nonvar(E), E = element(Name, Attrs, Children) } ->
+            name_uri(Name),
+            attribute_uris(Attrs),
+            content_uris(Children)
+        ;   []
+        ),
+        content_uris(Es).
+
+attribute_uris([]) --> [].
+attribute_uris([A|As]) -->
+        (   { nonvar(A), A = (Name=_) } -> name_uri(Name)
+        ;   []
+        ),
+        attribute_uris(As).
+
+name_uri(Name) -->
+        (   { nonvar(Name), Name = URI:_ } -> [URI]
+        ;   []
+        ).
+
+namespace_prefixes([], _, _, []).
+namespace_prefixes([URI|URIs], NSMap, N0, [URI-Prefix|NSs]) :-
+        (   memberchk(Prefix=URI, NSMap) ->
+            N = N0
+        ;   number_chars(N0, Ds),
+            atom_chars(Prefix, [n,s|Ds]),
+            N is N0 + 1
+        ),
+        namespace_prefixes(URIs, NSMap, N, NSs).
+
+ns_declarations(NSs, Attrs0, Attrs) :-
+        findall(Name=URI,
+                (   member(URI-Prefix, NSs),
+                    (   Prefix == xmlns -> Name = xmlns
+                    ;   Name = xmlns:Prefix
+                    )
+                ),
+                Decls),
+        append(Decls, Attrs0, Attrs).
+
+name(Name, NSs) -->
+        (   { var(Name) } ->
+            { instantiation_error(xml_write/3) }
+        ;   { Name = xmlns:Prefix } ->
+            "xmlns:", text(Prefix)
+        ;   { Name = URI:Local } ->
+            { memberchk(URI-Prefix, NSs) },
+            (   { Prefix == xmlns } -> []
+            ;   text(Prefix), ":"
+            ),
+            text(Local)
+        ;   { atom(Name) } ->
+            text(Name)
+        ;   { domain_error(name, Name, xml_write/3) }
+        ).
+
+%% HTML templates.
+
+html(M:Spec) -->
+        (   { var(Spec) } ->
+            { instantiation_error(html//1) }
+        ;   { Spec = [] } ->
+            []
+        ;   { Spec = [_|_] } ->
+            html_list(Spec, M)
+        ;   { Spec = \Rule } ->
+            phrase(M:Rule)
+        ;   { Spec = &(Entity) } ->
+            "&",
+            (   { integer(Entity) } -> "#", text(Entity)
+            ;   text(Entity)
+            ),
+            ";"
+        ;   { atomic(Spec) } ->
+            escaped_text(Spec, text_char)
+        ;   { Spec =.. [Tag, Content] } ->
+            html_element(Tag, [], Content, M)
+        ;   { Spec =.. [Tag, Attrs, Content] } ->
+            html_element(Tag, Attrs, Content, M)
+        ;   { domain_error(html_spec, Spec, html//1) }
+        ).
+
+html_list([], _) --> [].
+html_list([S|Ss], M) -->
+        html(M:S),
+        html_list(Ss, M).
+
+html_element(Tag, Attrs0, Content, M) -->
+        { (   Attrs0 = [] -> Attrs = []
+          ;   Attrs0 = [_|_] -> Attrs = Attrs0
+          ;   Attrs = [Attrs0]
+          ) },
+        "<", text(Tag), html_attributes(Attrs),
+        (   { Content == [], void_element(Tag) } ->
+            ">"
+        ;   ">", html(M:Content), "</", text(Tag), ">"
+        ).
+
+html_attributes([]) --> [].
+html_attributes([A|As]) -->
+        (   { A = (Name=Value) } -> []
+        ;   { A =.. [Name, Value] } -> []
+        ;   { domain_error(html_attribute, A, html//1) }
+        ),
+        " ", text(Name), "=\"",
+        escaped_text(Value, attribute_char),
+        "\"",
+        html_attributes(As).
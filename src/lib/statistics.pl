@@ -0,0 +1,107 @@
+/* - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+   Predicates for inspecting the resource usage of the system.
+   Part of Scryer Prolog.
+
+   statistics(Key, Value) yields the value of Key, which is one of:
+
+     runtime        [Total, SinceLast]: CPU time in milliseconds,
+                    in total and since the last query of runtime
+     walltime       [Total, SinceLast]: elapsed real time in
+                    milliseconds since the system was started, in
+                    total and since the last query of walltime
+     cputime        CPU time in seconds, a floating point number
+     inferences     the number of predicate calls made so far
+     heapused       bytes used by the heap
+     stack          bytes used by the stack of frames
+     trail          bytes used by the trail
+     choicepoints   the number of choicepoints
+     atoms          the number of atoms in the atom table
+     clauses        the number of clauses of all predicates
+     modules        the number of loaded modules
+     codes          the number of instructions of compiled code
+     epoch          the time stamp of when the system was started
+
+   If Key is a variable, all keys are enumerated on backtracking.
+
+   statistics/0 prints a summary of these values.
+
+   Example:
+
+       ?- statistics(runtime, [T, _]).
+          T = 120.
+- - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
+
+:- module(statistics, [statistics/0,
+                       statistics/2]).
+
+:- use_module(library(dcgs)).
+:- use_module(library(error)).
+:- use_module(library(format)).
+:- use_module(library(iso_ext)).
+:- use_module(library(lists)).
+:- use_module(library(time)).
+
+statistics_key(runtime).
+statistics_key(walltime).
+statistics_key(cputime).
+statistics_key(inferences).
+statistics_key(heapused).
+statistics_key(stack).
+statistics_key(trail).
+statistics_key(choicepoints).
+statistics_key(atoms).
+statistics_key(clauses).
+statistics_key(modules).
+statistics_key(codes).
+statistics_key(epoch).
+
+statistics(Key, Value) :-
+        (   var(Key) ->
+            statistics_key(Key),
+            statistics_(Key, Value)
+        ;   must_be(atom, Key),
+            (   statistics_key(Key) ->
+                statistics_(Key, Value)
+            ;   domain_error(statistics_key, Key, statistics/2)
+            )
+        ).
+
+%% runtime and walltime report the time since they were last queried
+%% as their second element.
+
+statistics_(Key, Value) :-
+        (   memberchk(Key, [runtime, walltime]) ->
+            '$statistics'(Key, Total),
+            atom_concat('$statistics_last_', Key, LastKey),
+            (   bb_get(LastKey, Last) -> true
+            ;   Last = 0
+            ),
+            bb_put(LastKey, Total),
+            SinceLast is Total - Last,
+            Value = [Total, SinceLast]
+        ;   '$statistics'(Key, Value)
+        ).
+
+statistics :-
+        '$statistics'(epoch, Epoch),
+        '$statistics'(cputime, CPU),
+        '$statistics'(inferences, Inferences),
+        '$statistics'(atoms, Atoms),
+        '$statistics'(modules, Modules),
+        '$statistics'(clauses, Clauses),
+        '$statistics'(codes, Codes),
+        '$statistics'(heapused, Heap),
+        '$statistics'(stack, Stack),
+        '$statistics'(trail, Trail),
+        '$statistics'(choicepoints, Choicepoints),
+        phrase(format_time("%c", Epoch), Started),
+        format("% Started at ~s~n", [Started]),
+        format("% ~3f seconds cpu time for ~D inferences~n", [CPU, Inferences]),
+        format("% ~D atoms, ~D modules, ~D clauses, ~D codes~n",
+               [Atoms, Modules, Clauses, Codes]),
+        format("~n", []),
+        format("%~t~20|~tBytes~30|~n", []),
+        format("% Heap used~t~20|~t~D~30|~n", [Heap]),
+        format("% Stack used~t~20|~t~D~30|~n", [Stack]),
+        format("% Trail used~t~20|~t~D~30|~n", [Trail]),
+        format("% ~D choicepoints~n", [Choicepoints]).
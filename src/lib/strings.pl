@@ -0,0 +1,144 @@
+/* Strings as compact, immutable text.
+
+   A string is an atomic object whose text is stored packed as UTF-8
+   in a single heap cell. It is not a list, so a string and the list
+   of its characters are different terms. string_chars/2 converts a
+   string to a list of characters for use in DCGs and with
+   library(lists), and text_to_string/2 converts text back. The list
+   is a complete partial string, so its characters stay packed.
+
+   The predicates accept text in the form of atoms, numbers, strings
+   and lists of characters or character codes, and always yield
+   strings.
+
+   ?- split_string("SWI-Prolog, 7.0", ",", " ", Ss).
+   %@    Ss = ["SWI-Prolog","7.0"].
+*/
+
+:- module(strings, [split_string/4,
+                    string/1,
+                    string_chars/2,
+                    string_code/3,
+                    string_concat/3,
+                    string_length/2,
+                    sub_string/5,
+                    text_to_string/2]).
+
+:- use_module(library(between)).
+:- use_module(library(error)).
+
+text_string(Text, String, PI) :-
+    (   var(Text) ->
+        instantiation_error(PI)
+    ;   '$text_to_string'(Text, String0) ->
+        String = String0
+    ;   type_error(string, Text, PI)
+    ).
+
+string(String) :-
+    '$is_string'(String).
+
+text_to_string(Text, String) :-
+    text_string(Text, S, text_to_string/2),
+    String = S.
+
+string_chars(String, Chars) :-
+    (   var(String) ->
+        must_be(list, Chars),
+        text_string(Chars, String, string_chars/2)
+    ;   text_string(String, S, string_chars/2),
+        '$text_to_chars'(S, Chars)
+    ).
+
+string_length(String, Length) :-
+    can_be(integer, Length),
+    text_string(String, S, string_length/2),
+    '$text_length'(S, Length).
+
+string_code(Index, String, Code) :-
+    must_be(integer, Index),
+    can_be(integer, Code),
+    text_string(String, S, string_code/3),
+    Index >= 1,
+    Before is Index - 1,
+    '$sub_text'(S, Before, 1, Sub),
+    '$text_to_chars'(Sub, [Char]),
+    char_code(Char, Code).
+
+string_concat(Prefix, Suffix, String) :-
+    (   nonvar(Prefix), nonvar(Suffix) ->
+        text_string(Prefix, P, string_concat/3),
+        text_string(Suffix, Q, string_concat/3),
+        '$text_concat'(P, Q, S),
+        (   var(String) ->
+            String = S
+        ;   text_string(String, S, string_concat/3)
+        )
+    ;   text_string(String, S, string_concat/3),
+        '$text_length'(S, Length),
+        (   nonvar(Prefix) ->
+            text_string(Prefix, P, string_concat/3),
+            Q = Suffix,
+            '$text_length'(P, PrefixLength),
+            SuffixLength is Length - PrefixLength
+        ;   nonvar(Suffix) ->
+            P = Prefix,
+            text_string(Suffix, Q, string_concat/3),
+            '$text_length'(Q, SuffixLength),
+            PrefixLength is Length - SuffixLength
+        ;   P = Prefix,
+            Q = Suffix,
+            between(0, Length, PrefixLength),
+            SuffixLength is Length - PrefixLength
+        ),
+        '$sub_text'(S, 0, PrefixLength, P),
+        '$sub_text'(S, PrefixLength, SuffixLength, Q)
+    ).
+
+sub_string(String, Before, Length, After, Sub) :-
+    can_be(integer, Before),
+    can_be(integer, Length),
+    can_be(integer, After),
+    text_string(String, S, sub_string/5),
+    '$text_length'(S, StringLength),
+    (   nonvar(Sub) ->
+        text_string(Sub, SubS, sub_string/5),
+        '$text_length'(SubS, Length),
+        Max is StringLength - Length,
+        between(0, Max, Before),
+        '$sub_text'(S, Before, Length, SubS),
+        After is Max - Before
+    ;   sub_string_bounds(Before, Length, After, StringLength),
+        '$sub_text'(S, Before, Length, Sub)
+    ).
+
+sub_string_bounds(Before, Length, After, StringLength) :-
+    (   integer(Before), integer(Length) ->
+        After is StringLength - Before - Length
+    ;   integer(Before), integer(After) ->
+        Length is StringLength - Before - After
+    ;   integer(Length), integer(After) ->
+        Before is StringLength - Length - After
+    ;   integer(Before) ->
+        Max is StringLength - Before,
+        between(0, Max, Length),
+        After is Max - Length
+    ;   integer(Length) ->
+        Max is StringLength - Length,
+        between(0, Max, Before),
+        After is Max - Before
+    ;   integer(After) ->
+        Max is StringLength - After,
+        between(0, Max, Before),
+        Length is Max - Before
+    ;   between(0, StringLength, Before),
+        Max is StringLength - Before,
+        between(0, Max, Length),
+        After is Max - Length
+    ).
+
+split_string(String, SepChars, Pad, Substrings) :-
+    text_string(String, S, split_string/4),
+    text_string(SepChars, Seps, split_string/4),
+    text_string(Pad, P, split_string/4),
+    '$split_text'(S, Seps, P, Substrings).
@@ -8,27 +8,114 @@
    called a time stamp. Use format_time//2 to describe strings that
    contain attributes of the time stamp.
 
+   get_time(T) yields the current system time as a floating point
+   number of seconds since 1970-01-01 00:00 UTC. Such numbers are
+   also called time stamps below. monotonic_time(T) yields the
+   number of seconds elapsed since an arbitrary fixed moment, from a
+   clock that is not affected by changes of the system time. Use it
+   to measure durations.
+
+   Dates are represented as terms of the form:
+
+     date(Y,M,D)                      a calendar day
+     date(Y,M,D,H,Mn,S,Off,TZ,DST)    a moment of a calendar day
+
+   S is the number of seconds, possibly a floating point number. Off
+   is the offset of the time zone in seconds west of UTC, so that
+   UTC = local time + Off. TZ is the name of the time zone, or '-'
+   if it is unknown. DST is true if daylight saving time is in
+   effect, false if it is not, and '-' if this is unknown.
+
+   stamp_date_time(Stamp, Date, TimeZone) converts a time stamp to
+   a date/9 term. TimeZone is 'UTC', local for the local time zone
+   of the system, or an integer offset in seconds west of UTC. The
+   name of the local time zone is not known.
+
+   date_time_stamp(Date, Stamp) converts a date term to a time stamp.
+   The fields of Date may be out of range; for example, day 0 is the
+   last day of the previous month. If Off is a variable, Date is in
+   the local time zone. date(Y,M,D) denotes 00:00 UTC of that day.
+
+   day_of_the_week(Date, DoW) yields the day of the week of Date,
+   from 1 (Monday) to 7 (Sunday).
+
+   date_add(Date0, Durations, Date) adds a duration, or a list of
+   durations, to Date0. The durations are years(N), months(N),
+   weeks(N), days(N), hours(N), minutes(N) and seconds(N), where N
+   may be negative. Years and months are added first, keeping the
+   day of the month if it exists and using the last day of the month
+   otherwise. Only years, months, weeks and days can be added to a
+   date/3 term. The result of adding to a date/9 term has the same
+   time zone as Date0.
+
+   date_difference(Date1, Date2, Diff) yields Date1 - Date2, as
+   days(N) if both are date/3 terms, and as seconds(S) otherwise.
+
+   parse_time(Text, Format, Stamp) and parse_date_time(Text, Format,
+   Date) parse a date in the given Format, which is iso_8601 or
+   rfc_2822. If Format is a variable, both formats are tried.
+   parse_time(Text, Stamp) is parse_time(Text, _, Stamp). ISO 8601
+   dates without a time yield date/3 terms, and times without a time
+   zone are in the local time zone. Examples of supported forms:
+
+     2020-06-11                      2020-06-11T14:30:00Z
+     2020-W24-4                      2020-06-11 14:30:00.25+02:00
+     2020-163                        20200611T143000-0500
+     Thu, 11 Jun 2020 14:30:00 +0200
+
    The nonterminal format_time//2 describes a list of characters that
    are formatted according to a format string. Usage:
 
-     phrase(format_time(FormatString, TimeStamp), Cs)
+     phrase(format_time(FormatString, Time), Cs)
 
-   TimeStamp represents a moment in time in an opaque form, as for
-   example obtained by current_time/1.
+   Time is a time stamp, as obtained by current_time/1 or get_time/1,
+   or a date term. Time stamps are formatted in the local time zone.
 
    FormatString is a list of characters that are interpreted literally,
-   except for the following specifiers (and possibly more in the future):
+   except for the following specifiers:
 
-     %Y    year of the time stamp. Example: 2020.
-     %m    month number (01-12), zero-padded to 2 digits
-     %d    day number (01-31), zero-padded to 2 digits
-     %H    hour number (00-24), zero-padded to 2 digits
-     %M    minute number (00-59), zero-padded to 2 digits
-     %S    second number (00-60), zero-padded to 2 digits
-     %b    abbreviated month name, always 3 letters
      %a    abbreviated weekday name, always 3 letters
      %A    full weekday name
+     %b    abbreviated month name, always 3 letters
+     %B    full month name
+     %c    date and time, same as %a %b %e %H:%M:%S %Y
+     %C    century (00-99), zero-padded to 2 digits
+     %d    day number (01-31), zero-padded to 2 digits
+     %D    same as %m/%d/%y
+     %e    day number (1-31), space-padded to 2 characters
+     %f    microseconds (000000-999999), zero-padded to 6 digits
+     %F    same as %Y-%m-%d
+     %g    year of the ISO week (00-99), zero-padded to 2 digits
+     %G    year of the ISO week
+     %h    same as %b
+     %H    hour number (00-23), zero-padded to 2 digits
+     %I    hour number (01-12), zero-padded to 2 digits
      %j    day of the year (001-366), zero-padded to 3 digits
+     %k    hour number (0-23), space-padded to 2 characters
+     %l    hour number (1-12), space-padded to 2 characters
+     %m    month number (01-12), zero-padded to 2 digits
+     %M    minute number (00-59), zero-padded to 2 digits
+     %n    a newline
+     %p    AM or PM
+     %P    am or pm
+     %r    same as %I:%M:%S %p
+     %R    same as %H:%M
+     %s    seconds since 1970-01-01 00:00 UTC
+     %S    second number (00-60), zero-padded to 2 digits
+     %t    a tab
+     %T    same as %H:%M:%S
+     %u    day of the week (1-7), where 1 is Monday
+     %U    week of the year (00-53), starting on Sunday
+     %v    same as %e-%b-%Y
+     %V    ISO week of the year (01-53), zero-padded to 2 digits
+     %w    day of the week (0-6), where 0 is Sunday
+     %W    week of the year (00-53), starting on Monday
+     %x    same as %m/%d/%y
+     %X    same as %H:%M:%S
+     %y    year (00-99), zero-padded to 2 digits
+     %Y    year of the time stamp. Example: 2020.
+     %z    time zone as an offset from UTC. Example: +0200.
+     %Z    name of the time zone, if it is known
      %%    the literal %
 
    Example:
@@ -36,13 +123,34 @@
      ?- current_time(T), phrase(format_time("%d.%m.%Y (%H:%M:%S)", T), Cs).
         T = [...], Cs = "11.06.2020 (00:24:32)".
 
+     ?- phrase(format_time("%A, %e %B %Y", date(2020,6,11)), Cs).
+        Cs = "Thursday, 11 June 2020".
+
    sleep(S) sleeps for S seconds (a floating point number).
 
    time(Goal) reports the execution time of Goal.
 
+   alarm(Time, Goal, Id) schedules Goal to be called once, after Time
+   seconds. Goal interrupts the running computation at its next call,
+   which resumes afterwards unless Goal throws an exception. Id is
+   used with remove_alarm(Id) to remove the alarm before it fires.
+
+   call_with_time_limit(Time, Goal) calls once(Goal), throwing
+   time_limit_exceeded if Goal does not complete within Time seconds.
+
+   Example:
+
+     ?- catch(call_with_time_limit(0.5, repeat), E, true).
+        E = time_limit_exceeded.
+
 - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - */
 
-:- module(time, [max_sleep_time/1, sleep/1, time/1, current_time/1, format_time//2]).
+:- module(time, [max_sleep_time/1, sleep/1, time/1, current_time/1, format_time//2,
+                 get_time/1, monotonic_time/1, stamp_date_time/3,
+                 date_time_stamp/2, day_of_the_week/2, date_add/3,
+                 date_difference/3, parse_time/2, parse_time/3,
+                 parse_date_time/3, alarm/3, remove_alarm/1,
+                 call_with_time_limit/2]).
 
 :- use_module(library(format)).
 :- use_module(library(iso_ext)).
@@ -55,15 +163,601 @@ current_time(T) :-
         '$current_time'(T0),
         read_term_from_chars(T0, T).
 
-format_time([], _) --> [].
-format_time(['%','%'|Fs], T) --> !, "%", format_time(Fs, T).
-format_time(['%',Spec|Fs], T) --> !,
+get_time(T) :-
+        '$get_time'(T).
+
+monotonic_time(T) :-
+        '$monotonic_time'(T).
+
+%% Dates and time stamps.
+
+stamp_date_time(Stamp, Date, TimeZone) :-
+        must_be_number(Stamp, stamp_date_time/3),
+        time_zone(TimeZone, Stamp, Off, TZ, DST),
+        Local is Stamp - Off,
+        local_date(Local, Date0),
+        Date0 = date(Y,M,D,H,Mn,S,_,_,_),
+        Date = date(Y,M,D,H,Mn,S,Off,TZ,DST).
+
+time_zone(TimeZone, Stamp, Off, TZ, DST) :-
+        (   var(TimeZone) -> instantiation_error(stamp_date_time/3)
+        ;   TimeZone == 'UTC' -> Off = 0, TZ = 'UTC', DST = ('-')
+        ;   TimeZone == local ->
+            Secs is floor(Stamp),
+            '$local_time_zone'(Secs, Off, TZ, DST)
+        ;   integer(TimeZone) -> Off = TimeZone, TZ = ('-'), DST = ('-')
+        ;   domain_error(time_zone, TimeZone, stamp_date_time/3)
+        ).
+
+%% The date/9 term in UTC of a number of seconds since the epoch.
+%% Seconds are integers if Local is an integer.
+
+local_date(Local, date(Y,M,D,H,Mn,S,0,'UTC','-')) :-
+        Secs is floor(Local),
+        Days is Secs div 86400,
+        Rem is Secs mod 86400,
+        civil_date(Days, Y, M, D),
+        H is Rem // 3600,
+        Mn is Rem mod 3600 // 60,
+        S0 is Rem mod 60,
+        (   integer(Local) -> S = S0
+        ;   S is S0 + (Local - Secs)
+        ).
+
+date_time_stamp(Date, Stamp) :-
+        (   var(Date) -> instantiation_error(date_time_stamp/2)
+        ;   Date = date(_,_,_) ->
+            local_seconds(Date, Stamp)
+        ;   Date = date(_,_,_,_,_,_,Off,_,_) ->
+            local_seconds(Date, Local),
+            (   var(Off) -> local_offset(Local, Off0)
+            ;   must_be(integer, Off),
+                Off0 = Off
+            ),
+            Stamp is Local + Off0
+        ;   domain_error(date, Date, date_time_stamp/2)
+        ).
+
+%% The seconds since the epoch of the fields of a date, as if it
+%% were in UTC.
+
+local_seconds(date(Y,M,D), Secs) :-
+        maplist(must_be(integer), [Y,M,D]),
+        date_days(Y, M, D, Days),
+        Secs is Days * 86400.
+local_seconds(date(Y,M,D,H,Mn,S,_,_,_), Secs) :-
+        maplist(must_be(integer), [Y,M,D,H,Mn]),
+        must_be_number(S, date_time_stamp/2),
+        date_days(Y, M, D, Days),
+        Secs is Days * 86400 + H * 3600 + Mn * 60 + S.
+
+%% The offset of the local time zone at local time Local. The offset
+%% at Local taken as UTC is close enough to find the actual moment,
+%% except across a change of the offset.
+
+local_offset(Local, Off) :-
+        Secs is floor(Local),
+        '$local_time_zone'(Secs, Off0, _, _),
+        Secs1 is Secs + Off0,
+        '$local_time_zone'(Secs1, Off, _, _).
+
+proper_list(Ls) :-
+        '$skip_max_list'(_, -1, Ls, Tail),
+        Tail == [].
+
+must_be_number(N, Context) :-
+        (   var(N) -> instantiation_error(Context)
+        ;   number(N) -> true
+        ;   type_error(number, N, Context)
+        ).
+
+%% Days since 1970-01-01 of a date whose month and day may be out
+%% of range.
+
+date_days(Y, M, D, Days) :-
+        Y1 is Y + (M - 1) div 12,
+        M1 is (M - 1) mod 12 + 1,
+        civil_days(Y1, M1, 1, Days0),
+        Days is Days0 + D - 1.
+
+%% Conversion between proleptic Gregorian dates and days since
+%% 1970-01-01, using eras of 400 years that start on March 1.
+
+civil_days(Y, M, D, Days) :-
+        (   M =< 2 -> Y0 is Y - 1 ; Y0 = Y ),
+        Era is Y0 div 400,
+        YoE is Y0 - Era * 400,
+        DoY is (153 * ((M + 9) mod 12) + 2) // 5 + D - 1,
+        DoE is YoE * 365 + YoE // 4 - YoE // 100 + DoY,
+        Days is Era * 146097 + DoE - 719468.
+
+civil_date(Days, Y, M, D) :-
+        Z is Days + 719468,
+        Era is Z div 146097,
+        DoE is Z - Era * 146097,
+        YoE is (DoE - DoE // 1460 + DoE // 36524 - DoE // 146096) // 365,
+        DoY is DoE - (365 * YoE + YoE // 4 - YoE // 100),
+        MP is (5 * DoY + 2) // 153,
+        D is DoY - (153 * MP + 2) // 5 + 1,
+        (   MP < 10 -> M is MP + 3 ; M is MP - 9 ),
+        (   M =< 2 -> Y is YoE + Era * 400 + 1 ; Y is YoE + Era * 400 ).
+
+leap_year(Y) :-
+        Y mod 4 =:= 0,
+        (   Y mod 100 =\= 0 -> true
+        ;   Y mod 400 =:= 0
+        ).
+
+days_in_month(Y, M, N) :-
+        (   M =:= 2 ->
+            (   leap_year(Y) -> N = 29 ; N = 28 )
+        ;   M0 is M - 1,
+            nth0(M0, [31,28,31,30,31,30,31,31,30,31,30,31], N)
+        ).
+
+day_of_the_week(Date, DoW) :-
+        (   var(Date) -> instantiation_error(day_of_the_week/2)
+        ;   Date = date(_,_,_) -> true
+        ;   Date = date(_,_,_,_,_,_,_,_,_) -> true
+        ;   domain_error(date, Date, day_of_the_week/2)
+        ),
+        local_seconds(Date, Secs),
+        DoW is (floor(Secs) div 86400 + 3) mod 7 + 1.
+
+date_add(Date0, Durations0, Date) :-
+        (   proper_list(Durations0) -> Durations = Durations0
+        ;   Durations = [Durations0]
+        ),
+        foldl(duration(Date0), Durations, 0-0-0, Months-Days-Secs),
+        (   var(Date0) -> instantiation_error(date_add/3)
+        ;   Date0 = date(Y0,M0,D0) ->
+            add_months(Y0, M0, D0, Months, Y1, M1, D1),
+            date_days(Y1, M1, D1, Days1),
+            Days2 is Days1 + Days,
+            civil_date(Days2, Y, M, D),
+            Date = date(Y,M,D)
+        ;   Date0 = date(Y0,M0,D0,H,Mn,S,Off,TZ,DST) ->
+            add_months(Y0, M0, D0, Months, Y1, M1, D1),
+            local_seconds(date(Y1,M1,D1,H,Mn,S,Off,TZ,DST), Local0),
+            Local is Local0 + Days * 86400 + Secs,
+            local_date(Local, date(Y,M,D,H1,Mn1,S1,_,_,_)),
+            Date = date(Y,M,D,H1,Mn1,S1,Off,TZ,DST)
+        ;   domain_error(date, Date0, date_add/3)
+        ).
+
+duration(Date, Duration, Months0-Days0-Secs0, Months-Days-Secs) :-
+        (   var(Duration) -> instantiation_error(date_add/3)
+        ;   Duration =.. [Unit, N],
+            memberchk(Unit-Factor, [years-12, months-1, weeks-7, days-1,
+                                    hours-3600, minutes-60, seconds-1]) ->
+            (   Unit == seconds -> must_be_number(N, date_add/3)
+            ;   must_be(integer, N)
+            ),
+            (   memberchk(Unit, [years, months]) ->
+                Months is Months0 + N * Factor, Days = Days0, Secs = Secs0
+            ;   memberchk(Unit, [weeks, days]) ->
+                Months = Months0, Days is Days0 + N * Factor, Secs = Secs0
+            ;   Date = date(_,_,_) ->
+                domain_error(date_duration, Duration, date_add/3)
+            ;   Months = Months0, Days = Days0, Secs is Secs0 + N * Factor
+            )
+        ;   domain_error(date_duration, Duration, date_add/3)
+        ).
+
+add_months(Y0, M0, D0, Months, Y, M, D) :-
+        maplist(must_be(integer), [Y0,M0,D0]),
+        T is Y0 * 12 + M0 - 1 + Months,
+        Y is T div 12,
+        M is T mod 12 + 1,
+        days_in_month(Y, M, N),
+        D is min(D0, N).
+
+date_difference(Date1, Date2, Diff) :-
+        (   nonvar(Date1), Date1 = date(Y1,M1,D1),
+            nonvar(Date2), Date2 = date(Y2,M2,D2) ->
+            maplist(must_be(integer), [Y1,M1,D1,Y2,M2,D2]),
+            date_days(Y1, M1, D1, Days1),
+            date_days(Y2, M2, D2, Days2),
+            N is Days1 - Days2,
+            Diff = days(N)
+        ;   date_time_stamp(Date1, Stamp1),
+            date_time_stamp(Date2, Stamp2),
+            S is Stamp1 - Stamp2,
+            Diff = seconds(S)
+        ).
+
+%% Parsing dates.
+
+parse_time(Text, Stamp) :-
+        parse_time(Text, _, Stamp).
+
+parse_time(Text, Format, Stamp) :-
+        parse_date_time(Text, Format, Date),
+        date_time_stamp(Date, Stamp).
+
+parse_date_time(Text, Format, Date) :-
+        (   var(Text) -> instantiation_error(parse_date_time/3)
+        ;   atom(Text) -> atom_chars(Text, Cs)
+        ;   must_be(list, Text),
+            Cs = Text
+        ),
+        (   var(Format) -> true
+        ;   memberchk(Format, [iso_8601, rfc_2822]) -> true
+        ;   domain_error(time_format, Format, parse_date_time/3)
+        ),
+        member(Format, [iso_8601, rfc_2822]),
+        phrase(date_time(Format, Date), Cs),
+        !.
+
+date_time(iso_8601, Date) -->
+        iso_date(Y, M, D),
+        (   time_separator ->
+            iso_time(H, Mn, S),
+            iso_zone(Off, TZ, DST),
+            { Date = date(Y,M,D,H,Mn,S,Off,TZ,DST) }
+        ;   { Date = date(Y,M,D) }
+        ).
+date_time(rfc_2822, date(Y,M,D,H,Mn,S,Off,TZ,'-')) -->
+        blanks,
+        (   rfc_weekday, blanks, ",", blanks -> []
+        ;   []
+        ),
+        digits(1, 2, D),
+        blanks1,
+        rfc_month(M),
+        blanks1,
+        digits(2, 4, Y0),
+        { rfc_year(Y0, Y) },
+        blanks1,
+        digits(2, 2, H),
+        ":",
+        digits(2, 2, Mn),
+        (   ":" -> digits(2, 2, S)
+        ;   { S = 0 }
+        ),
+        blanks1,
+        rfc_zone(Off, TZ),
+        blanks,
+        { valid_date(Y, M, D), valid_time(H, Mn, S) }.
+
+time_separator --> "T".
+time_separator --> " ".
+
+iso_date(Y, M, D) -->
+        digits(4, 4, Y0),
+        (   "-" ->
+            (   "W" ->
+                digits(2, 2, W),
+                (   "-" -> digits(1, 1, WD)
+                ;   { WD = 1 }
+                ),
+                { week_date(Y0, W, WD, Y, M, D) }
+            ;   digits(2, 2, M), "-", digits(2, 2, D) -> { Y = Y0 }
+            ;   digits(3, 3, J) -> { ordinal_date(Y0, J, M, D), Y = Y0 }
+            ;   digits(2, 2, M), { Y = Y0, D = 1 }
+            )
+        ;   "W" ->
+            digits(2, 2, W),
+            digits(1, 1, WD),
+            { week_date(Y0, W, WD, Y, M, D) }
+        ;   digits(2, 2, M), digits(2, 2, D) -> { Y = Y0 }
+        ;   digits(3, 3, J), { ordinal_date(Y0, J, M, D), Y = Y0 }
+        ),
+        { valid_date(Y, M, D) }.
+
+%% Week 1 of an ISO week-numbering year is the week that contains
+%% January 4, so its days may belong to the previous year.
+
+week_date(YW, W, WD, Y, M, D) :-
+        W >= 1, W =< 53, WD >= 1, WD =< 7,
+        iso_weeks(YW, N),
+        W =< N,
+        day_of_the_week(date(YW,1,4), W4),
+        civil_days(YW, 1, 4, Days4),
+        Days is Days4 - W4 + (W - 1) * 7 + WD,
+        civil_date(Days, Y, M, D).
+
+ordinal_date(Y, J, M, D) :-
+        J >= 1,
+        (   leap_year(Y) -> J =< 366 ; J =< 365 ),
+        civil_days(Y, 1, 1, Days0),
+        Days is Days0 + J - 1,
+        civil_date(Days, _, M, D).
+
+iso_time(H, Mn, S) -->
+        digits(2, 2, H),
+        (   ":" ->
+            digits(2, 2, Mn),
+            (   ":" -> iso_seconds(S)
+            ;   { S = 0 }
+            )
+        ;   digits(2, 2, Mn) ->
+            (   iso_seconds(S) -> []
+            ;   { S = 0 }
+            )
+        ;   { Mn = 0, S = 0 }
+        ),
+        { valid_time(H, Mn, S) }.
+
+iso_seconds(S) -->
+        digits(2, 2, S0),
+        (   ( "." ; "," ) ->
+            digit(F), digits0(Fs),
+            { number_chars(Frac, ['0','.',F|Fs]),
+              S is S0 + Frac }
+        ;   { S = S0 }
+        ).
+
+iso_zone(0, 'UTC', '-') --> "Z", !.
+iso_zone(Off, '-', '-') -->
+        sign(Sign),
+        !,
+        digits(2, 2, H),
+        (   ":" -> digits(2, 2, Mn)
+        ;   digits(2, 2, Mn) -> []
+        ;   { Mn = 0 }
+        ),
+        { Off is -Sign * (H * 3600 + Mn * 60) }.
+iso_zone(_, _, _) --> [].
+
+sign(1) --> "+".
+sign(-1) --> "-".
+
+rfc_weekday -->
+        [A,B,C],
+        { atom_chars(Day, [A,B,C]),
+          memberchk(Day, ['Mon','Tue','Wed','Thu','Fri','Sat','Sun']) }.
+
+rfc_month(M) -->
+        [A,B,C],
+        { atom_chars(Month, [A,B,C]),
+          nth0(M0, ['Jan','Feb','Mar','Apr','May','Jun','Jul','Aug','Sep',
+                    'Oct','Nov','Dec'], Month),
+          M is M0 + 1 }.
+
+rfc_year(Y0, Y) :-
+        (   Y0 < 50 -> Y is Y0 + 2000
+        ;   Y0 < 1000 -> Y is Y0 + 1900
+        ;   Y = Y0
+        ).
+
+rfc_zone(Off, '-') -->
+        sign(Sign),
+        !,
+        digits(4, 4, HM),
+        { Off is -Sign * (HM // 100 * 3600 + HM mod 100 * 60) }.
+rfc_zone(Off, TZ) -->
+        letters(Cs),
+        { Cs \== [],
+          atom_chars(TZ, Cs),
+          (   memberchk(TZ-H, ['UT'-0, 'GMT'-0, 'EST'-(-5), 'EDT'-(-4),
+                               'CST'-(-6), 'CDT'-(-5), 'MST'-(-7),
+                               'MDT'-(-6), 'PST'-(-8), 'PDT'-(-7)]) ->
+              Off is -H * 3600
+          ;   Cs = [_],
+              Off = 0
+          ) }.
+
+letters([C|Cs]) -->
+        [C],
+        { char_code(C, Code),
+          (   Code >= 0'A, Code =< 0'Z -> true
+          ;   Code >= 0'a, Code =< 0'z
+          ) },
+        !,
+        letters(Cs).
+letters([]) --> [].
+
+valid_date(Y, M, D) :-
+        M >= 1, M =< 12,
+        days_in_month(Y, M, N),
+        D >= 1, D =< N.
+
+valid_time(H, Mn, S) :-
+        H >= 0, H =< 24,
+        Mn >= 0, Mn =< 59,
+        S >= 0, S < 61.
+
+digits(Min, Max, N) -->
+        digits_(Max, Ds),
+        { length(Ds, L),
+          L >= Min,
+          foldl(digit_value, Ds, 0, N) }.
+
+digits_(Max, [D|Ds]) -->
+        { Max > 0 },
+        digit(D),
+        !,
+        { Max1 is Max - 1 },
+        digits_(Max1, Ds).
+digits_(_, []) --> [].
+
+digit_value(D, N0, N) :-
+        number_chars(V, [D]),
+        N is N0 * 10 + V.
+
+digit(D) -->
+        [D],
+        { char_code(D, Code),
+          Code >= 0'0, Code =< 0'9 }.
+
+digits0([D|Ds]) --> digit(D), !, digits0(Ds).
+digits0([]) --> [].
+
+blanks --> [C], { C == ' ' ; C == '\t' }, !, blanks.
+blanks --> [].
+
+blanks1 --> [' '], blanks.
+
+%% Time stamps of current_time/1 that lack the seconds since the
+%% epoch support only the specifiers they contain.
+
+format_time(Fs, T) -->
+        (   { proper_list(T), \+ memberchk(s=_, T) } ->
+            format_stamp(Fs, T)
+        ;   { time_date(T, Date) },
+            format_date(Fs, Date)
+        ).
+
+format_stamp([], _) --> [].
+format_stamp(['%','%'|Fs], T) --> !, "%", format_stamp(Fs, T).
+format_stamp(['%',Spec|Fs], T) --> !,
         (   { member(Spec=Value, T) } ->
             list(Value)
         ;   { domain_error(time_specifier, Spec, format_time//2) }
         ),
-        format_time(Fs, T).
-format_time([F|Fs], T) --> [F], format_time(Fs, T).
+        format_stamp(Fs, T).
+format_stamp([F|Fs], T) --> [F], format_stamp(Fs, T).
+
+format_date([], _) --> [].
+format_date(['%',Spec|Fs], Date) --> !,
+        (   specifier(Spec, Date) -> []
+        ;   { domain_error(time_specifier, Spec, format_time//2) }
+        ),
+        format_date(Fs, Date).
+format_date([F|Fs], Date) --> [F], format_date(Fs, Date).
+
+time_date(T, Date) :-
+        (   var(T) -> instantiation_error(format_time//2)
+        ;   number(T) -> stamp_date_time(T, Date, local)
+        ;   proper_list(T), memberchk(s=Cs, T) ->
+            number_chars(Stamp, Cs),
+            stamp_date_time(Stamp, Date, local)
+        ;   T = date(_,_,_) ->
+            date_time_stamp(T, Stamp),
+            stamp_date_time(Stamp, Date, 'UTC')
+        ;   T = date(_,_,_,_,_,_,Off,TZ,DST) ->
+            (   var(Off) ->
+                date_time_stamp(T, Stamp),
+                stamp_date_time(Stamp, Date, local)
+            ;   local_seconds(T, Local),
+                local_date(Local, date(Y,M,D,H,Mn,S,_,_,_)),
+                Date = date(Y,M,D,H,Mn,S,Off,TZ0,DST0),
+                (   var(TZ) -> TZ0 = ('-') ; TZ0 = TZ ),
+                (   var(DST) -> DST0 = ('-') ; DST0 = DST )
+            )
+        ;   domain_error(time_stamp, T, format_time//2)
+        ).
+
+specifier(a, Date) --> { weekday(Date, W), weekday_name(W, A0), sub_atom(A0, 0, 3, _, A) }, atom(A).
+specifier('A', Date) --> { weekday(Date, W), weekday_name(W, A) }, atom(A).
+specifier(b, Date) --> { Date = date(_,M,_,_,_,_,_,_,_), month_name(M, A0), sub_atom(A0, 0, 3, _, A) }, atom(A).
+specifier('B', Date) --> { Date = date(_,M,_,_,_,_,_,_,_), month_name(M, A) }, atom(A).
+specifier(c, Date) --> format_date("%a %b %e %H:%M:%S %Y", Date).
+specifier('C', Date) --> { Date = date(Y,_,_,_,_,_,_,_,_), C is Y // 100 }, padded(C, 2, '0').
+specifier(d, Date) --> { Date = date(_,_,D,_,_,_,_,_,_) }, padded(D, 2, '0').
+specifier('D', Date) --> format_date("%m/%d/%y", Date).
+specifier(e, Date) --> { Date = date(_,_,D,_,_,_,_,_,_) }, padded(D, 2, ' ').
+specifier(f, Date) --> { Date = date(_,_,_,_,_,S,_,_,_), U is floor((S - floor(S)) * 1000000) }, padded(U, 6, '0').
+specifier('F', Date) --> format_date("%Y-%m-%d", Date).
+specifier(g, Date) --> { iso_week(Date, G, _), G2 is G mod 100 }, padded(G2, 2, '0').
+specifier('G', Date) --> { iso_week(Date, G, _) }, padded(G, 4, '0').
+specifier(h, Date) --> specifier(b, Date).
+specifier('H', Date) --> { Date = date(_,_,_,H,_,_,_,_,_) }, padded(H, 2, '0').
+specifier('I', Date) --> { hour12(Date, H) }, padded(H, 2, '0').
+specifier(j, Date) --> { year_day(Date, J) }, padded(J, 3, '0').
+specifier(k, Date) --> { Date = date(_,_,_,H,_,_,_,_,_) }, padded(H, 2, ' ').
+specifier(l, Date) --> { hour12(Date, H) }, padded(H, 2, ' ').
+specifier(m, Date) --> { Date = date(_,M,_,_,_,_,_,_,_) }, padded(M, 2, '0').
+specifier('M', Date) --> { Date = date(_,_,_,_,Mn,_,_,_,_) }, padded(Mn, 2, '0').
+specifier(n, _) --> "\n".
+specifier(p, Date) --> { Date = date(_,_,_,H,_,_,_,_,_) }, ( { H < 12 } -> "AM" ; "PM" ).
+specifier('P', Date) --> { Date = date(_,_,_,H,_,_,_,_,_) }, ( { H < 12 } -> "am" ; "pm" ).
+specifier(r, Date) --> format_date("%I:%M:%S %p", Date).
+specifier('R', Date) --> format_date("%H:%M", Date).
+specifier(s, Date) --> { date_time_stamp(Date, Stamp), S is floor(Stamp) }, padded(S, 1, '0').
+specifier('S', Date) --> { Date = date(_,_,_,_,_,S,_,_,_), S0 is floor(S) }, padded(S0, 2, '0').
+specifier(t, _) --> "\t".
+specifier('T', Date) --> format_date("%H:%M:%S", Date).
+specifier(u, Date) --> { weekday(Date, W) }, padded(W, 1, '0').
+specifier('U', Date) -->
+        { weekday(Date, W), year_day(Date, J), U is (J + 6 - W mod 7) // 7 },
+        padded(U, 2, '0').
+specifier(v, Date) --> format_date("%e-%b-%Y", Date).
+specifier('V', Date) --> { iso_week(Date, _, V) }, padded(V, 2, '0').
+specifier(w, Date) --> { weekday(Date, W), W0 is W mod 7 }, padded(W0, 1, '0').
+specifier('W', Date) -->
+        { weekday(Date, W), year_day(Date, J), U is (J + 7 - W) // 7 },
+        padded(U, 2, '0').
+specifier(x, Date) --> format_date("%m/%d/%y", Date).
+specifier('X', Date) --> format_date("%H:%M:%S", Date).
+specifier(y, Date) --> { Date = date(Y,_,_,_,_,_,_,_,_), Y2 is Y mod 100 }, padded(Y2, 2, '0').
+specifier('Y', Date) --> { Date = date(Y,_,_,_,_,_,_,_,_) }, padded(Y, 4, '0').
+specifier(z, Date) -->
+        { Date = date(_,_,_,_,_,_,Off,_,_),
+          (   Off > 0 -> Sign = ('-') ; Sign = ('+') ),
+          A is abs(Off),
+          H is A // 3600,
+          Mn is A mod 3600 // 60 },
+        [Sign],
+        padded(H, 2, '0'),
+        padded(Mn, 2, '0').
+specifier('Z', Date) -->
+        { Date = date(_,_,_,_,_,_,_,TZ,_) },
+        (   { TZ == ('-') } -> []
+        ;   atom(TZ)
+        ).
+specifier('%', _) --> "%".
+
+atom(A) -->
+        { atom_chars(A, Cs) },
+        list(Cs).
+
+padded(N, Width, Pad) -->
+        { number_chars(N, Cs),
+          length(Cs, L),
+          P is max(0, Width - L),
+          length(Ps, P),
+          maplist(=(Pad), Ps) },
+        list(Ps),
+        list(Cs).
+
+weekday_name(W, A) :-
+        W0 is W - 1,
+        nth0(W0, ['Monday','Tuesday','Wednesday','Thursday','Friday',
+                  'Saturday','Sunday'], A).
+
+month_name(M, A) :-
+        M0 is M - 1,
+        nth0(M0, ['January','February','March','April','May','June','July',
+                  'August','September','October','November','December'], A).
+
+weekday(date(Y,M,D,_,_,_,_,_,_), W) :-
+        day_of_the_week(date(Y,M,D), W).
+
+year_day(date(Y,M,D,_,_,_,_,_,_), J) :-
+        civil_days(Y, M, D, Days),
+        civil_days(Y, 1, 1, Days0),
+        J is Days - Days0 + 1.
+
+hour12(date(_,_,_,H,_,_,_,_,_), H12) :-
+        H0 is H mod 12,
+        (   H0 =:= 0 -> H12 = 12 ; H12 = H0 ).
+
+%% The ISO week-numbering year G and week V of a date. Week 1 is the
+%% week with the year's first Thursday.
+
+iso_week(Date, G, V) :-
+        Date = date(Y,_,_,_,_,_,_,_,_),
+        weekday(Date, W),
+        year_day(Date, J),
+        V0 is (J - W + 10) // 7,
+        (   V0 < 1 ->
+            G is Y - 1,
+            iso_weeks(G, V)
+        ;   iso_weeks(Y, N), V0 > N ->
+            G is Y + 1,
+            V = 1
+        ;   G = Y,
+            V = V0
+        ).
+
+iso_weeks(Y, N) :-
+        day_of_the_week(date(Y,1,1), W),
+        (   W =:= 4 -> N = 53
+        ;   W =:= 3, leap_year(Y) -> N = 53
+        ;   N = 52
+        ).
 
 list([]) --> [].
 list([L|Ls]) --> [L], list(Ls).
@@ -76,16 +770,75 @@ sleep(T) :-
         domain_error(not_less_than_zero, T, sleep/1)
     ;   max_sleep_time(N), T > N ->
         throw(error(representation_error(max_sleep_time), sleep/1))
-    ;   '$sleep'(T)
+    ;   monotonic_time(T0),
+        End is T0 + T,
+        sleep_until(End)
+    ).
+
+%% '$sleep'/1 returns early if an alarm fires, so that the alarm is
+%% handled by the next call.
+
+sleep_until(End) :-
+    monotonic_time(T),
+    Rest is End - T,
+    (   Rest > 0 ->
+        '$sleep'(Rest),
+        sleep_until(End)
+    ;   true
     ).
 
+%% Alarms.
+
+:- dynamic(alarm_goal/2).
+
+:- meta_predicate alarm(?, 0, ?).
+
+alarm(Time, Goal, '$alarm'(N)) :-
+        must_be_number(Time, alarm/3),
+        '$new_alarm_id'(N),
+        assertz(time:alarm_goal(N, Goal)),
+        '$schedule_alarm'(Time, N).
+
+remove_alarm(Id) :-
+        (   var(Id) -> instantiation_error(remove_alarm/1)
+        ;   Id = '$alarm'(N), integer(N) ->
+            '$cancel_alarm'(N),
+            retractall(time:alarm_goal(N, _))
+        ;   domain_error(alarm, Id, remove_alarm/1)
+        ).
+
+%% The machine calls fire_alarms/0 when alarms are due, and retries
+%% the interrupted call after it.
+
+fire_alarms :-
+        '$fired_alarms'(Ns),
+        maplist(fire_alarm, Ns),
+        '$return_from_verify_attr'.
+
+fire_alarm(N) :-
+        (   retract(time:alarm_goal(N, Goal)) ->
+            (   call(Goal) -> true
+            ;   true
+            )
+        ;   true
+        ).
+
+:- meta_predicate call_with_time_limit(?, 0).
+
+call_with_time_limit(Time, Goal) :-
+        must_be_number(Time, call_with_time_limit/2),
+        (   Time > 0 -> true
+        ;   domain_error(positive_number, Time, call_with_time_limit/2)
+        ),
+        setup_call_cleanup(alarm(Time, throw(time_limit_exceeded), Id),
+                           once(Goal),
+                           remove_alarm(Id)).
 
-% '$cpu_now' can be replaced by statistics/2 once that is implemented.
 
 :- meta_predicate time(0).
 
 time(Goal) :-
-        '$cpu_now'(T0),
+        '$statistics'(cputime, T0),
         setup_call_cleanup(true,
                            (   Goal,
                                report_time(T0)
@@ -93,7 +846,7 @@ time(Goal) :-
                            report_time(T0)).
 
 report_time(T0) :-
-        '$cpu_now'(T),
+        '$statistics'(cputime, T),
         Time is T - T0,
         (   bb_get('$first_answer', true) ->
             format("   % CPU time: ~3f seconds~n", [Time])
@@ -0,0 +1,207 @@
+/* Conditional coroutining.
+
+   when(Condition, Goal) delays Goal until Condition holds. A
+   condition is one of:
+
+     nonvar(X)    X is bound to a non-variable term
+     ground(X)    X is ground
+     ?=(X, Y)     X and Y are either identical or cannot unify
+     (C1, C2)     both C1 and C2 hold
+     (C1 ; C2)    C1 or C2 holds
+
+   If Condition already holds, Goal is called immediately.
+   Otherwise it is suspended on the variables of Condition and
+   called at most once, as soon as Condition is satisfied.
+
+   frozen(Term, Goal) unifies Goal with the conjunction of goals
+   that are delayed by freeze/2 and when/2 on the variables of Term.
+
+   ?- when(?=(X, Y), write(done)), X = f(A), Y = f(b).
+   %@    X = f(A), Y = f(b), when:when(?=(f(A),f(b)),user:write(done)).
+   ?- when(?=(X, Y), write(done)), X = f(A), Y = f(b), A = c.
+   %@ done   X = f(c), Y = f(b), A = c.
+*/
+
+:- module(when, [when/2, frozen/2]).
+
+:- use_module(library(atts)).
+:- use_module(library(dcgs)).
+:- use_module(library(error)).
+:- use_module(library(freeze)).
+:- use_module(library(iso_ext)).
+
+:- meta_predicate when(+, 0).
+
+:- attribute when/1.
+
+when(Cond, Goal) :-
+    must_be_condition(Cond),
+    trigger(Cond, Goal).
+
+must_be_condition(Cond) :-
+    (   var(Cond) ->
+        instantiation_error(when/2)
+    ;   Cond = nonvar(_) ->
+        true
+    ;   Cond = ground(_) ->
+        true
+    ;   Cond = ?=(_, _) ->
+        true
+    ;   Cond = (C1, C2) ->
+        must_be_condition(C1),
+        must_be_condition(C2)
+    ;   Cond = (C1 ; C2) ->
+        must_be_condition(C1),
+        must_be_condition(C2)
+    ;   domain_error(when_condition, Cond, when/2)
+    ).
+
+trigger(nonvar(X), Goal) :-
+    trigger_nonvar(X, Goal).
+trigger(ground(X), Goal) :-
+    trigger_ground(X, Goal).
+trigger(?=(X, Y), Goal) :-
+    trigger_determined(X, Y, Goal).
+trigger((C1, C2), Goal) :-
+    trigger(C1, trigger(C2, Goal)).
+trigger((C1 ; C2), Goal) :-
+    trigger(C1, check_once(Done, (C1 ; C2), Goal)),
+    trigger(C2, check_once(Done, (C1 ; C2), Goal)).
+
+trigger_nonvar(X, Goal) :-
+    (   nonvar(X) ->
+        call(Goal)
+    ;   suspend(X, trigger_nonvar(X, Goal))
+    ).
+
+trigger_ground(X, Goal) :-
+    term_variables(X, Vs),
+    (   Vs = [V|_] ->
+        suspend(V, trigger_ground(X, Goal))
+    ;   call(Goal)
+    ).
+
+trigger_determined(X, Y, Goal) :-
+    (   determined(X, Y) ->
+        call(Goal)
+    ;   term_variables(X-Y, Vs),
+        suspend_all(Vs, check_determined(_, X, Y, Goal))
+    ).
+
+%  X and Y are determined if they are identical or cannot unify.
+%  Unifiability is tested on a copy without attributes, so that no
+%  constraints are woken by the test.
+
+determined(X, Y) :-
+    (   X == Y ->
+        true
+    ;   copy_term_nat(X-Y, X0-Y0),
+        X0 \= Y0
+    ).
+
+check_determined(Done, X, Y, Goal) :-
+    (   var(Done) ->
+        Done = true,
+        trigger_determined(X, Y, Goal)
+    ;   true
+    ).
+
+check_once(Done, _, Goal) :-
+    (   var(Done) ->
+        Done = true,
+        call(Goal)
+    ;   true
+    ).
+
+suspend_all([], _).
+suspend_all([V|Vs], Goal) :-
+    suspend(V, Goal),
+    suspend_all(Vs, Goal).
+
+suspend(V, Goal) :-
+    (   get_atts(V, when(Goals)) ->
+        put_atts(V, when((Goals, Goal)))
+    ;   put_atts(V, when(Goal))
+    ).
+
+%  Whether V was bound or aliased to another variable, the triggers
+%  are rechecked. Those whose condition does not hold yet suspend
+%  themselves again on the variables that remain.
+
+verify_attributes(Var, _, Goals) :-
+    (   get_atts(Var, when(Goal)) ->
+        phrase(wake_goals(Goal), Goals)
+    ;   Goals = []
+    ).
+
+wake_goals((G1, G2)) -->
+    !,
+    wake_goals(G1),
+    wake_goals(G2).
+wake_goals(G) -->
+    [G].
+
+attribute_goals(Var) -->
+    { get_atts(Var, when(Goals)),
+      put_atts(Var, -when(_)) },
+    residual_goals(Goals).
+
+residual_goals((G1, G2)) -->
+    !,
+    residual_goals(G1),
+    residual_goals(G2).
+residual_goals(trigger_nonvar(X, Goal)) -->
+    residual_goal(nonvar(X), Goal).
+residual_goals(trigger_ground(X, Goal)) -->
+    residual_goal(ground(X), Goal).
+residual_goals(check_determined(Done, X, Y, Goal)) -->
+    (   { var(Done) } ->
+        residual_goal(?=(X, Y), Goal)
+    ;   []
+    ).
+
+%  Internal continuations are folded back into the condition they
+%  came from. A disjunction that has already fired shows nothing.
+
+residual_goal(Cond, trigger(C, Goal)) -->
+    !,
+    residual_goal((Cond, C), Goal).
+residual_goal(_, check_once(Done, Cond, Goal)) -->
+    !,
+    (   { var(Done) } ->
+        residual_goal(Cond, Goal)
+    ;   []
+    ).
+residual_goal(Cond, Goal) -->
+    [when(Cond, Goal)].
+
+%% frozen(@Term, -Goal)
+%
+% Goal is the conjunction of goals delayed on the variables of Term,
+% or true if there are none.
+
+frozen(Term, Goal) :-
+    term_variables(Term, Vs),
+    phrase(frozen_goals(Vs), Goals0),
+    sort(Goals0, Goals),
+    goals_conjunction(Goals, Goal).
+
+frozen_goals([]) --> [].
+frozen_goals([V|Vs]) -->
+    (   { freeze:get_atts(V, frozen(Fs)) } ->
+        [freeze(V, Fs)]
+    ;   []
+    ),
+    (   { get_atts(V, when(Ws)) } ->
+        residual_goals(Ws)
+    ;   []
+    ),
+    frozen_goals(Vs).
+
+goals_conjunction([], true).
+goals_conjunction([G|Gs], Goal) :-
+    (   Gs == [] ->
+        Goal = G
+    ;   Goal = (G, Goal0),
+        goals_conjunction(Gs, Goal0)
+    ).
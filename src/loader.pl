@@ -4,7 +4,9 @@
                    expand_term/2,
                    file_load/2,
                    load/1,
+                   message_to_codes/3,
                    predicate_property/2,
+                   print_message/2,
                    prolog_load_context/2,
                    strip_module/3,
                    use_module/1,
@@ -25,16 +27,22 @@
           ;  Expansion = term_expansion/2
           )  ->
           true
-       ;  write('caught: '),
-          writeq(Error),
-          nl
+       ;  print_message(error, load_error(Error))
        )
-    ;  write('caught: '),
-       writeq(Error),
-       nl
+    ;  print_message(error, load_error(Error))
     ),
     '$fail'.
 
+
+%% Messages are translated and printed by library(messages), which is
+%% loaded by the toplevel once the loader is in place.
+
+print_message(Kind, Message) :-
+    '$call'(messages:'$print_message'(Kind, Message)).
+
+message_to_codes(Message, Kind, Codes) :-
+    '$call'(messages:'$message_to_codes'(Message, Kind, Codes)).
+
 expand_term(Term, ExpandedTerm) :-
     (  catch('$call'(user:term_expansion(Term, ExpandedTerm0)),
              E,
@@ -110,6 +118,7 @@ file_load(Stream, Path, Evacuable) :-
     % local predicate clauses, etc. from a previous load of the file
     % at Path.
     '$add_in_situ_filename_module'(Evacuable),
+    forget_clause_locations,
     catch((loader:load_loop(Stream, Evacuable),
            loader:run_initialization_goals),
           E,
@@ -132,15 +141,6 @@ load(Stream) :-
 load(_).
 
 
-print_comma_separated_list([VN=_]) :-
-    write(VN),
-    !.
-print_comma_separated_list([VN=_, VNEq | VNEqs]) :-
-    write(VN),
-    write(', '),
-    print_comma_separated_list([VNEq | VNEqs]).
-
-
 filter_anonymous_vars([], []).
 filter_anonymous_vars([VN=V | VNEqs0], VNEqs) :-
     (  atom_concat('_', _, VN) ->
@@ -150,35 +150,79 @@ filter_anonymous_vars([VN=V | VNEqs0], VNEqs) :-
     ).
 
 warn_about_singletons([], _).
-warn_about_singletons([Singleton|Singletons], LinesRead) :-
+warn_about_singletons([Singleton|Singletons], Position) :-
     (  filter_anonymous_vars([Singleton|Singletons], VarEqs),
        VarEqs \== [] ->
-       write('Warning: singleton variables '),
-       print_comma_separated_list(VarEqs),
-       write(' at line '),
-       write(LinesRead),
-       write(' of '),
-       prolog_load_context(file, File),
-       write(File),
-       nl
+       maplist(loader:var_eq_name, VarEqs, VarNames),
+       print_message(warning, singletons(Position, VarNames))
     ;  true
     ).
 
+var_eq_name(VN=_, Name) :-
+    (  atom(VN) ->
+       Name = VN
+    ;  atom_chars(Name, VN)
+    ).
+
+
+%% The source location of the next term of Stream, as File:Line:Col
+%% with lines and columns counted from 1.
+
+term_start_position(Stream, File:Line:Col) :-
+    stream_property(Stream, position(position_and_lines_read(_, LinesRead))),
+    stream_property(Stream, line_position(LinePos)),
+    (  prolog_load_context(source, File),
+       File \== '' ->
+       true
+    ;  File = user
+    ),
+    Line is LinesRead + 1,
+    Col is LinePos + 1.
+
+
+%% Errors raised while reading or compiling a term are given the
+%% source location of the term as context(Context, File:Line:Col),
+%% unless they already carry a location.
+
+throw_with_position(E, Position) :-
+    (  E = error(Error, Context),
+       \+ (  nonvar(Context),
+             Context = context(_, _)
+          ) ->
+       throw(error(Error, context(Context, Position)))
+    ;  throw(E)
+    ).
+
 
 load_loop(Stream, Evacuable) :-
     (  '$devour_whitespace'(Stream) ->
-       stream_property(Stream, position(position_and_lines_read(_, LinesRead))),
-       read_term(Stream, Term, [singletons(Singletons)])
+       term_start_position(Stream, Position),
+       catch(loader:load_term(Stream, Evacuable, Position, Term),
+             E,
+             loader:throw_with_position(E, Position))
     ;  Term = end_of_file
     ),
     (  Term == end_of_file ->
        close(Stream),
-       '$conclude_load'(Evacuable)
+       '$store_backtrackable_global_var'('$term_position', []),
+       flush_term_queue(Evacuable),
+       '$conclude_load'(Evacuable),
+       abolish_aux_clauses
+    ;  load_loop(Stream, Evacuable)
+    ).
+
+load_term(Stream, Evacuable, Position, Term) :-
+    (  stream_property(Stream, file_name(_)) ->
+       '$store_backtrackable_global_var'('$term_position', Position)
+    ;  '$store_backtrackable_global_var'('$term_position', [])
+    ),
+    read_term(Stream, Term, [singletons(Singletons)]),
+    (  Term == end_of_file ->
+       true
     ;  var(Term) ->
        instantiation_error(load/1)
-    ;  warn_about_singletons(Singletons, LinesRead),
-       compile_term(Term, Evacuable),
-       load_loop(Stream, Evacuable)
+    ;  warn_about_singletons(Singletons, Position),
+       compile_term(Term, Evacuable)
     ).
 
 
@@ -276,8 +320,9 @@ compile_dispatch_or_clause(Term, Evacuable) :-
     (  var(Term) ->
        instantiation_error(load/1)
     ;  compile_dispatch(Term, Evacuable) ->
-       '$flush_term_queue'(Evacuable)
-    ;  compile_clause(Term, Evacuable)
+       flush_term_queue(Evacuable)
+    ;  inline_clause_meta_calls(Term, Clause, Evacuable),
+       compile_clause(Clause, Evacuable)
     ).
 
 
@@ -367,8 +412,13 @@ compile_declaration(initialization(Goal), Evacuable) :-
     prolog_load_context(module, Module),
     '$add_dynamic_predicate'(Module, '$initialization_goals', 1, Evacuable),
     assertz(Module:'$initialization_goals'(Goal)).
-compile_declaration(set_prolog_flag(Flag, Value), _) :-
-    set_prolog_flag(Flag, Value).
+compile_declaration(set_prolog_flag(Flag, Value), Evacuable) :-
+    (  Flag == occurs_check,
+       prolog_load_context(module, Module),
+       Module \== user ->
+       set_module_occurs_check(Module, Value)
+    ;  set_prolog_flag(Flag, Value)
+    ).
 compile_declaration(non_counted_backtracking(Name/Arity), Evacuable) :-
     must_be(atom, Name),
     must_be(integer, Arity),
@@ -382,16 +432,18 @@ compile_clause((Target:Head :- Body), Evacuable) :-
     !,
     functor(Head, Name, Arity),
     (  '$is_consistent_with_term_queue'(Target, Name, Arity, Evacuable) ->
-       '$scoped_clause_to_evacuable'(Target, (Head :- Body), Evacuable)
-    ;  '$flush_term_queue'(Evacuable),
+       '$scoped_clause_to_evacuable'(Target, (Head :- Body), Evacuable),
+       record_clause_location(Target, Name, Arity)
+    ;  flush_term_queue(Evacuable),
        compile_term((Target:Head :- Body), Evacuable)
     ).
 compile_clause(Target:Head, Evacuable) :-
     !,
     functor(Head, Name, Arity),
     (  '$is_consistent_with_term_queue'(Target, Name, Arity, Evacuable) ->
-       '$scoped_clause_to_evacuable'(Target, Head, Evacuable)
-    ;  '$flush_term_queue'(Evacuable),
+       '$scoped_clause_to_evacuable'(Target, Head, Evacuable),
+       record_clause_location(Target, Name, Arity)
+    ;  flush_term_queue(Evacuable),
        compile_term(Target:Head, Evacuable)
     ).
 compile_clause((Head :- Body), Evacuable) :-
@@ -399,20 +451,65 @@ compile_clause((Head :- Body), Evacuable) :-
     prolog_load_context(module, Target),
     functor(Head, Name, Arity),
     (  '$is_consistent_with_term_queue'(Target, Name, Arity, Evacuable) ->
-       '$clause_to_evacuable'((Head :- Body), Evacuable)
-    ;  '$flush_term_queue'(Evacuable),
+       '$clause_to_evacuable'((Head :- Body), Evacuable),
+       record_clause_location(Target, Name, Arity)
+    ;  flush_term_queue(Evacuable),
        compile_term((Head :- Body), Evacuable)
     ).
 compile_clause(Head, Evacuable) :-
     prolog_load_context(module, Target),
     functor(Head, Name, Arity),
     (  '$is_consistent_with_term_queue'(Target, Name, Arity, Evacuable) ->
-       '$clause_to_evacuable'(Head, Evacuable)
-    ;  '$flush_term_queue'(Evacuable),
+       '$clause_to_evacuable'(Head, Evacuable),
+       record_clause_location(Target, Name, Arity)
+    ;  flush_term_queue(Evacuable),
        compile_term(Head, Evacuable)
     ).
 
 
+%% The source locations of the clauses loaded from source files while
+%% the debug flag is on, as
+%% clause_location(Module:Name/Arity, ClauseNum, File:Line:Col).
+%% Clauses are numbered from 1 in the order they are loaded, as in
+%% the frames of backtraces. The number of clauses recorded for each
+%% predicate is kept in clause_location_count/4, indexed by its name.
+
+:- dynamic(clause_location/3).
+:- dynamic(clause_location_count/4).
+
+record_clause_location(Module, Name, Arity) :-
+    (  current_prolog_flag(debug, true),
+       '$fetch_global_var'('$term_position', Position),
+       Position = _:_:_ ->
+       (  '$call'(builtins:retract(loader:clause_location_count(Name, Module, Arity, N0))) ->
+          true
+       ;  N0 = 0
+       ),
+       N is N0 + 1,
+       assertz(loader:clause_location_count(Name, Module, Arity, N)),
+       assertz(loader:clause_location(Module:Name/Arity, N, Position))
+    ;  true
+    ).
+
+%% The clauses of a file being reloaded replace those of its previous
+%% load, so their locations are forgotten and no longer counted.
+%% retract/1 can't be called while the toplevel is bootstrapped, hence
+%% the check before it.
+
+forget_clause_locations :-
+    prolog_load_context(source, File),
+    (  loader:clause_location(_, _, File:_:_) ->
+       (  '$call'(builtins:retract(loader:clause_location(Module:Name/Arity, _, File:_:_))),
+          '$call'(builtins:retract(loader:clause_location_count(Name, Module, Arity, N0))),
+          N is N0 - 1,
+          assertz(loader:clause_location_count(Name, Module, Arity, N)),
+          false
+       ;  true
+       )
+    ;  true
+    ).
+
+
 prolog_load_context(source, Source) :-
     %% The absolute path name of the file being compiled. During
     %% loading of a PO file, the corresponding source file name is
@@ -502,6 +599,11 @@ use_module(Module, Exports, Evacuable) :-
           ;  '$load_library_as_stream'(LibraryPath, Stream, Path),
              file_load(Stream, Path, Subevacuable),
              '$use_module'(Evacuable, Subevacuable, Exports)
+          ),
+          (  Library == apply_macros ->
+             load_context(Target),
+             enable_inline_meta_calls(Target)
+          ;  true
           )
        ;  var(Library) ->
           instantiation_error(load/1)
@@ -705,6 +807,285 @@ thread_goals(Goals0, Goals1, Hole, Functor) :-
     ).
 
 
+%% In the modules that load library(apply_macros), calls to
+%% maplist/2..9, foldl/4..6 and call/2..N in clause bodies whose
+%% closure is known at compile time are inlined. call/N is
+%% replaced by the goal it would construct, the others by calls to
+%% auxiliary predicates that apply the closure to each element
+%% directly. The arguments of the closure are passed to the auxiliary
+%% predicate, so it depends only on the name and arity of the closure
+%% and can be shared by all its uses in a module. Its clauses are
+%% collected in Module:'$aux_clauses'/2 until they are compiled.
+%%
+%% The modules are kept with the source file loading them, as
+%% Source-Module pairs, in the '$inline_meta_calls' global variable,
+%% which is looked up once per clause. It is stored backtrackably, so
+%% it is forgotten once the load is done, and another load into the
+%% same module doesn't inline its calls unless it loads
+%% library(apply_macros) itself.
+
+enable_inline_meta_calls(Module) :-
+    load_context_source(Source),
+    (  '$fetch_global_var'('$inline_meta_calls', Contexts) ->
+       true
+    ;  Contexts = []
+    ),
+    (  memberchk(Source-Module, Contexts) ->
+       true
+    ;  '$store_backtrackable_global_var'('$inline_meta_calls', [Source-Module | Contexts])
+    ).
+
+inline_meta_calls_enabled(Module) :-
+    '$fetch_global_var'('$inline_meta_calls', Contexts),
+    load_context_source(Source),
+    memberchk(Source-Module, Contexts).
+
+load_context_source(Source) :-
+    (  prolog_load_context(source, Source) ->
+       true
+    ;  Source = user
+    ).
+
+inline_clause_meta_calls(Clause0, Clause, Evacuable) :-
+    (  Clause0 = (Head :- Body0),
+       load_context(Module),
+       inline_meta_calls_enabled(Module) ->
+       inline_meta_calls(Body0, Module, Body, Evacuable),
+       Clause = (Head :- Body)
+    ;  Clause = Clause0
+    ).
+
+inline_meta_calls(Goal0, Module, Goal, Evacuable) :-
+    (  var(Goal0) ->
+       Goal = Goal0
+    ;  control_construct(Goal0, Goals0, Goal, Goals) ->
+       inline_meta_calls_(Goals0, Module, Goals, Evacuable)
+    ;  Goal0 = M:Goal1,
+       atom(M) ->
+       inline_meta_calls(Goal1, M, Goal2, Evacuable),
+       Goal = M:Goal2
+    ;  inline_meta_call(Goal0, Module, Goal, Evacuable) ->
+       true
+    ;  Goal = Goal0
+    ).
+
+inline_meta_calls_([], _, [], _).
+inline_meta_calls_([Goal0 | Goals0], Module, [Goal | Goals], Evacuable) :-
+    inline_meta_calls(Goal0, Module, Goal, Evacuable),
+    inline_meta_calls_(Goals0, Module, Goals, Evacuable).
+
+control_construct((G1, G2), [G1, G2], (H1, H2), [H1, H2]).
+control_construct((G1 ; G2), [G1, G2], (H1 ; H2), [H1, H2]).
+control_construct((G1 -> G2), [G1, G2], (H1 -> H2), [H1, H2]).
+control_construct((\+ G), [G], (\+ H), [H]).
+
+inline_meta_call(Goal0, Module, Goal, Evacuable) :-
+    Goal0 =.. [Name, Closure | Args],
+    nonvar(Closure),
+    strip_module(Module, Closure, ClosureModule, ClosureGoal),
+    atom(ClosureModule),
+    (  atom(ClosureGoal) ->
+       true
+    ;  compound(ClosureGoal)
+    ),
+    \+ control_construct(ClosureGoal, _, _, _),
+    \+ ClosureGoal = _:_,
+    \+ ClosureGoal == !,
+    length(Args, N),
+    inline_meta_call(Name, N, Goal0, Module, ClosureModule, ClosureGoal, Args, Goal, Evacuable).
+
+inline_meta_call(call, N, _, Module, ClosureModule, ClosureGoal, Args, Goal, _) :-
+    N > 0,
+    ClosureGoal =.. [F | Params],
+    append(Params, Args, Args1),
+    Goal1 =.. [F | Args1],
+    % the module qualification of the arguments of a meta-predicate
+    % was settled by expand_goal, so they're left to call/N.
+    \+ predicate_property(ClosureModule:Goal1, meta_predicate(_)),
+    \+ expandable_goal(ClosureModule, Goal1),
+    qualified_goal(ClosureModule, Module, Goal1, Goal).
+inline_meta_call(maplist, N, Goal0, Module, ClosureModule, ClosureGoal, Lists, Goal, Evacuable) :-
+    N >= 1,
+    N =< 8,
+    closure_meta_spec(Goal0, Module, N),
+    aux_predicate(maplist, N, Module, ClosureModule, ClosureGoal, Lists, [], Goal, Evacuable).
+inline_meta_call(foldl, N, Goal0, Module, ClosureModule, ClosureGoal, Args, Goal, Evacuable) :-
+    N >= 3,
+    N =< 5,
+    closure_meta_spec(Goal0, Module, N),
+    append(Lists, [V0, V], Args),
+    aux_predicate(foldl, N, Module, ClosureModule, ClosureGoal, Lists, [V0, V], Goal, Evacuable).
+
+%% Goals rewritten by goal_expansion/2 are left to call/N, which
+%% expands them once their arguments are known. Errors raised by
+%% goal_expansion/2 are those of the clause being loaded.
+
+expandable_goal(Module, Goal) :-
+    (  Module0 = Module
+    ;  Module0 = user
+    ),
+    catch('$call'(Module0:goal_expansion(Goal, _)),
+          error(existence_error(procedure, goal_expansion/2), _),
+          false),
+    !.
+
+%% The goal refers to lists:maplist/N or lists:foldl/N only if its
+%% closure is a meta-argument taking N more arguments.
+
+closure_meta_spec(Goal, Module, N) :-
+    predicate_property(Module:Goal, meta_predicate([MetaSpec | _])),
+    integer(MetaSpec),
+    MetaSpec =:= N.
+
+qualified_goal(GoalModule, Module, Goal0, Goal) :-
+    (  GoalModule == Module ->
+       Goal = Goal0
+    ;  Goal = GoalModule:Goal0
+    ).
+
+aux_predicate(Kind, N, Module, ClosureModule, ClosureGoal, Lists, Accs, Goal, Evacuable) :-
+    load_context(Target),
+    ClosureGoal =.. [F | Params],
+    length(Params, K),
+    length(Es, N),
+    append(Params, Es, CallArgs),
+    Call =.. [F | CallArgs],
+    \+ expandable_goal(ClosureModule, Call),
+    append(Lists, Accs, Args0),
+    append(Args0, Params, Args),
+    length(Args, AuxArity),
+    aux_predicate_name(Kind, AuxArity, Target, ClosureModule, F, K, AuxName),
+    Goal0 =.. [AuxName | Args],
+    qualified_goal(Target, Module, Goal0, Goal),
+    (  aux_predicate_exists(Target, AuxName, AuxArity) ->
+       true
+    ;  length(Lists, L),
+       length(AuxParams, K),
+       aux_clauses(Kind, L, Target, ClosureModule, F, AuxName, AuxParams, Clauses),
+       '$add_dynamic_predicate'(Target, '$aux_clauses', 2, Evacuable),
+       assertz(Target:'$aux_clauses'(AuxName/AuxArity, Clauses))
+    ).
+
+aux_predicate_exists(Target, AuxName, AuxArity) :-
+    (  '$predicate_defined'(Target, AuxName, AuxArity) ->
+       true
+    ;  predicate_property(Target:'$aux_clauses'(_, _), dynamic),
+       '$call'(Target:'$aux_clauses'(AuxName/AuxArity, _))
+    ).
+
+aux_predicate_name(Kind, AuxArity, Target, ClosureModule, F, K, AuxName) :-
+    (  ClosureModule == Target ->
+       Parts = ['__aux_', Kind, '/', AuxArity, '_', F, '+', K]
+    ;  Parts = ['__aux_', Kind, '/', AuxArity, '_', ClosureModule, ':', F, '+', K]
+    ),
+    atomic_parts_concat(Parts, AuxName).
+
+atomic_parts_concat([], '').
+atomic_parts_concat([Part | Parts], Atom) :-
+    atomic_parts_concat(Parts, Atom0),
+    (  number(Part) ->
+       number_chars(Part, Chars),
+       atom_chars(PartAtom, Chars)
+    ;  PartAtom = Part
+    ),
+    atom_concat(PartAtom, Atom0, Atom).
+
+%% The clauses of the auxiliary predicates follow those of
+%% lists:maplist/N and lists:foldl/N, with the lists moved to the
+%% front for first argument indexing.
+
+aux_clauses(Kind, L, Target, ClosureModule, F, AuxName, Params, [Clause1, Clause2]) :-
+    length(Es, L),
+    length(Ts, L),
+    maplist(loader:list_cell, Es, Ts, Cells),
+    length(Nils, L),
+    maplist(=([]), Nils),
+    (  Kind == maplist ->
+       append(Es, [], CallArgs0),
+       Accs1 = [],
+       Accs2 = [],
+       Accs3 = []
+    ;  append(Es, [V0, V1], CallArgs0),
+       Accs1 = [V, V],
+       Accs2 = [V0, V],
+       Accs3 = [V1, V]
+    ),
+    append(Params, CallArgs0, CallArgs),
+    Call0 =.. [F | CallArgs],
+    qualified_goal(ClosureModule, Target, Call0, Call),
+    aux_head(AuxName, Nils, Accs1, Params, Head1),
+    aux_head(AuxName, Cells, Accs2, Params, Head2),
+    aux_head(AuxName, Ts, Accs3, Params, Head3),
+    Clause1 = Head1,
+    Clause2 = (Head2 :- Call, Head3).
+
+list_cell(E, T, [E | T]).
+
+aux_head(AuxName, Lists, Accs, Params, Head) :-
+    append(Lists, Accs, Args0),
+    append(Args0, Params, Args),
+    Head =.. [AuxName | Args].
+
+%% Auxiliary predicates must be defined before the clauses calling
+%% them can run, which may happen during the load through the
+%% expansion hooks, so they're compiled whenever the term queue is
+%% flushed. Their clauses may in turn call for more auxiliary
+%% predicates, which are compiled in the same way.
+
+flush_term_queue(Evacuable) :-
+    '$flush_term_queue'(Evacuable),
+    compile_aux_clauses(Evacuable).
+
+compile_aux_clauses(Evacuable) :-
+    load_context(Module),
+    (  inline_meta_calls_enabled(Module),
+       predicate_property(Module:'$aux_clauses'(_, _), dynamic),
+       '$call'(builtins:retract(Module:'$aux_clauses'(_, Clauses))) ->
+       compile_aux_clause_list(Evacuable, Clauses),
+       compile_aux_clauses(Evacuable)
+    ;  true
+    ).
+
+compile_aux_clause_list(Evacuable, Clauses) :-
+    maplist(loader:compile_aux_clause(Evacuable), Clauses),
+    flush_term_queue(Evacuable).
+
+compile_aux_clause(Evacuable, Clause0) :-
+    expand_term_goals(Clause0, Clause),
+    compile_dispatch_or_clause(Clause, Evacuable).
+
+abolish_aux_clauses :-
+    load_context(Module),
+    (  predicate_property(Module:'$aux_clauses'(_, _), dynamic) ->
+       abolish(Module:'$aux_clauses'/2)
+    ;  true
+    ).
+
+
+%% The occurs_check flag of a module other than user is set by a
+%% set_prolog_flag/2 directive in its file, and kept with the
+%% module. The clauses of the module loaded or asserted after it is
+%% set unify their heads, and call =/2, as it says. The predicates
+%% they call in other modules, builtins among them, unify as the
+%% global flag says. A module forgets its value when its module
+%% declaration is loaded again.
+
+set_module_occurs_check(Module, Value) :-
+    (  var(Value) ->
+       instantiation_error(set_prolog_flag/2)
+    ;  memberchk(Value, [false, true, error]) ->
+       '$set_module_occurs_check'(Module, Value)
+    ;  domain_error(flag_value, occurs_check + Value, set_prolog_flag/2)
+    ).
+
+%% The value of the occurs_check flag of the module being loaded, if
+%% it was set.
+
+load_context_occurs_check(Value) :-
+    prolog_load_context(module, Module),
+    '$module_occurs_check'(Module, Value).
+
+
 %%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%
 %
 % call/{1-64} with dynamic goal expansion.
@@ -0,0 +1,108 @@
+use lazy_static::lazy_static;
+
+use crate::machine::{raise_event, ALARM_EVENT};
+
+use std::collections::BTreeMap;
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+lazy_static! {
+    static ref ALARM_CLOCK: AlarmClock = AlarmClock::new();
+}
+
+// alarms are kept by a single timer thread, started with the first
+// alarm. once an alarm is due, the thread moves it to the fired queue
+// and raises ALARM_EVENT, which the machine notices at its next call.
+struct AlarmClock {
+    state: Mutex<AlarmState>,
+    wakeup: Condvar,
+}
+
+#[derive(Default)]
+struct AlarmState {
+    next_id: usize,
+    pending: BTreeMap<usize, Instant>,
+    fired: Vec<usize>,
+    running: bool,
+}
+
+impl AlarmClock {
+    fn new() -> Self {
+        AlarmClock {
+            state: Mutex::new(AlarmState::default()),
+            wakeup: Condvar::new(),
+        }
+    }
+
+    fn run(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        loop {
+            let now = Instant::now();
+
+            let due: Vec<usize> = state
+                .pending
+                .iter()
+                .filter(|(_, &at)| at <= now)
+                .map(|(&id, _)| id)
+                .collect();
+
+            for id in due {
+                state.pending.remove(&id);
+                state.fired.push(id);
+                raise_event(ALARM_EVENT);
+            }
+
+            state = match state.pending.values().min().cloned() {
+                Some(at) => self.wakeup.wait_timeout(state, at - now).unwrap().0,
+                None => self.wakeup.wait(state).unwrap(),
+            };
+        }
+    }
+}
+
+// reserves the identifier of an alarm yet to be scheduled.
+pub(crate) fn new_alarm_id() -> usize {
+    let mut state = ALARM_CLOCK.state.lock().unwrap();
+
+    let id = state.next_id;
+
+    state.next_id += 1;
+
+    id
+}
+
+// schedules the alarm with the given identifier to fire after the
+// given duration.
+pub(crate) fn schedule_alarm(id: usize, duration: Duration) {
+    let mut state = ALARM_CLOCK.state.lock().unwrap();
+
+    // an alarm too far off to be represented never fires.
+    if let Some(at) = Instant::now().checked_add(duration) {
+        state.pending.insert(id, at);
+    }
+
+    if !state.running {
+        state.running = true;
+        thread::spawn(|| ALARM_CLOCK.run());
+    }
+
+    ALARM_CLOCK.wakeup.notify_one();
+}
+
+// removes an alarm, whether it is still pending or has fired without
+// being handled yet.
+pub(crate) fn cancel_alarm(id: usize) {
+    let mut state = ALARM_CLOCK.state.lock().unwrap();
+
+    state.pending.remove(&id);
+    state.fired.retain(|&fired_id| fired_id != id);
+
+    ALARM_CLOCK.wakeup.notify_one();
+}
+
+pub(crate) fn take_fired_alarms() -> Vec<usize> {
+    let mut state = ALARM_CLOCK.state.lock().unwrap();
+    state.fired.drain(..).collect()
+}
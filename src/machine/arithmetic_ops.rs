@@ -6,6 +6,7 @@ use prolog_parser::clause_name;
 use crate::arithmetic::*;
 use crate::clause_types::*;
 use crate::forms::*;
+use crate::instructions::NullaryInstruction;
 use crate::machine::machine_errors::*;
 use crate::machine::machine_indices::*;
 use crate::machine::machine_state::*;
@@ -127,9 +128,9 @@ impl MachineState {
                     let a1 = interms.pop().unwrap();
 
                     match name.as_str() {
-                        "+" => interms.push(try_numeric_result!(self, a1 + a2, caller)?),
-                        "-" => interms.push(try_numeric_result!(self, a1 - a2, caller)?),
-                        "*" => interms.push(try_numeric_result!(self, a1 * a2, caller)?),
+                        "+" => interms.push(self.add(a1, a2)?),
+                        "-" => interms.push(self.sub(a1, a2)?),
+                        "*" => interms.push(self.mul(a1, a2)?),
                         "/" => interms.push(self.div(a1, a2)?),
                         "**" => interms.push(self.pow(a1, a2, "is")?),
                         "^" => interms.push(self.int_pow(a1, a2)?),
@@ -160,6 +161,13 @@ impl MachineState {
                         "mod" => interms.push(self.modulus(a1, a2)?),
                         "rem" => interms.push(self.remainder(a1, a2)?),
                         "atan2" => interms.push(Number::Float(OrderedFloat(self.atan2(a1, a2)?))),
+                        "log" => interms.push(Number::Float(OrderedFloat(self.log_base(a1, a2)?))),
+                        "copysign" => {
+                            interms.push(Number::Float(OrderedFloat(self.copysign(a1, a2)?)))
+                        }
+                        "nexttoward" => {
+                            interms.push(Number::Float(OrderedFloat(self.nexttoward(a1, a2)?)))
+                        }
                         "gcd" => interms.push(self.gcd(a1, a2)?),
                         _ => {
                             let evaluable_stub = MachineError::functor_stub(name.clone(), 2);
@@ -198,6 +206,24 @@ impl MachineState {
                         "floor" => interms.push(self.floor(a1)),
                         "\\" => interms.push(self.bitwise_complement(a1)?),
                         "sign" => interms.push(self.sign(a1)),
+                        "cot" => interms.push(Number::Float(OrderedFloat(self.cot(a1)?))),
+                        "acot" => interms.push(Number::Float(OrderedFloat(self.acot(a1)?))),
+                        "sinh" => interms.push(Number::Float(OrderedFloat(self.sinh(a1)?))),
+                        "cosh" => interms.push(Number::Float(OrderedFloat(self.cosh(a1)?))),
+                        "tanh" => interms.push(Number::Float(OrderedFloat(self.tanh(a1)?))),
+                        "asinh" => interms.push(Number::Float(OrderedFloat(self.asinh(a1)?))),
+                        "acosh" => interms.push(Number::Float(OrderedFloat(self.acosh(a1)?))),
+                        "atanh" => interms.push(Number::Float(OrderedFloat(self.atanh(a1)?))),
+                        "log2" => interms.push(Number::Float(OrderedFloat(self.log2(a1)?))),
+                        "float_integer_part" => interms.push(Number::Float(OrderedFloat(self.float_integer_part(a1)?))),
+                        "float_fractional_part" => interms.push(Number::Float(OrderedFloat(self.float_fractional_part(a1)?))),
+                        "msb" => interms.push(self.msb(a1)?),
+                        "lsb" => interms.push(self.lsb(a1)?),
+                        "popcount" => interms.push(self.popcount(a1)?),
+                        "succ_or_zero" => interms.push(self.succ_or_zero(a1)?),
+                        "random" => interms.push(self.random(a1)?),
+                        "trunc" => interms.push(self.truncate(a1)),
+                        "integer" => interms.push(self.integer(a1)?),
                         _ => {
                             let evaluable_stub = MachineError::functor_stub(name.clone(), 1);
 
@@ -240,16 +266,21 @@ impl MachineState {
                     ));
                 }
                 &HeapCellValue::Atom(ref name, _) => {
-                    let evaluable_stub = MachineError::functor_stub(name.clone(), 0);
+                    match NullaryInstruction::from_name(name.as_str()) {
+                        Some(instr) => interms.push(self.nullary_value(instr)),
+                        None => {
+                            let evaluable_stub = MachineError::functor_stub(name.clone(), 0);
 
-                    return Err(self.error_form(
-                        MachineError::type_error(
-                            self.heap.h(),
-                            ValidType::Evaluable,
-                            evaluable_stub,
-                        ),
-                        caller,
-                    ));
+                            return Err(self.error_form(
+                                MachineError::type_error(
+                                    self.heap.h(),
+                                    ValidType::Evaluable,
+                                    evaluable_stub,
+                                ),
+                                caller,
+                            ));
+                        }
+                    }
                 }
                 &HeapCellValue::Addr(addr) if addr.is_ref() => {
                     return Err(self.error_form(MachineError::instantiation_error(), caller));
@@ -354,13 +385,51 @@ impl MachineState {
         }
     }
 
+    // applies the float flags to the result of an arithmetic operator
+    // on numbers.
+    fn number_result(
+        &self,
+        result: Result<Number, EvalError>,
+        operands: &[f64],
+        stub: MachineStub,
+    ) -> Result<Number, MachineStub> {
+        let result = result.and_then(|n| number_result(&self.flags, n, operands));
+        try_numeric_result!(self, result, stub)
+    }
+
+    pub(crate) fn add(&self, n1: Number, n2: Number) -> Result<Number, MachineStub> {
+        let stub = MachineError::functor_stub(clause_name!("is"), 2);
+        let operands = [operand_f(&n1), operand_f(&n2)];
+
+        self.number_result(n1 + n2, &operands, stub)
+    }
+
+    pub(crate) fn sub(&self, n1: Number, n2: Number) -> Result<Number, MachineStub> {
+        let stub = MachineError::functor_stub(clause_name!("is"), 2);
+        let operands = [operand_f(&n1), operand_f(&n2)];
+
+        self.number_result(n1 - n2, &operands, stub)
+    }
+
+    pub(crate) fn mul(&self, n1: Number, n2: Number) -> Result<Number, MachineStub> {
+        let stub = MachineError::functor_stub(clause_name!("is"), 2);
+        let operands = [operand_f(&n1), operand_f(&n2)];
+
+        self.number_result(n1 * n2, &operands, stub)
+    }
+
     pub(crate) fn div(&self, n1: Number, n2: Number) -> Result<Number, MachineStub> {
         let stub = MachineError::functor_stub(clause_name!("(/)"), 2);
 
-        if n2.is_zero() {
+        // an untrapped float division by zero yields an infinity, or
+        // NaN if the dividend is zero.
+        let float_division = matches!(n1, Number::Float(_)) || matches!(n2, Number::Float(_));
+
+        if n2.is_zero() && (self.flags.trap_float_zero_div || !float_division) {
             Err(self.error_form(MachineError::evaluation_error(EvalError::ZeroDivisor), stub))
         } else {
-            try_numeric_result!(self, n1 / n2, stub)
+            let operands = [operand_f(&n1), operand_f(&n2)];
+            self.number_result(n1 / n2, &operands, stub)
         }
     }
 
@@ -510,15 +579,15 @@ impl MachineState {
     }
 
     pub(crate) fn float_pow(&self, n1: Number, n2: Number) -> Result<Number, MachineStub> {
-        let f1 = result_f(&n1, rnd_f);
-        let f2 = result_f(&n2, rnd_f);
+        let f1 = result_f(&self.flags, &n1, rnd_f);
+        let f2 = result_f(&self.flags, &n2, rnd_f);
 
         let stub = MachineError::functor_stub(clause_name!("(**)"), 2);
 
         let f1 = try_numeric_result!(self, f1, stub)?;
         let f2 = try_numeric_result!(self, f2, stub)?;
 
-        let result = result_f(&Number::Float(OrderedFloat(f1.powf(f2))), rnd_f);
+        let result = float_result(&self.flags, f1.powf(f2), &[f1, f2]);
 
         Ok(Number::Float(OrderedFloat(try_numeric_result!(
             self, result, stub
@@ -550,10 +619,10 @@ impl MachineState {
     {
         let stub = MachineError::functor_stub(clause_name!("is"), 2);
 
-        let f1 = try_numeric_result!(self, result_f(&n1, rnd_f), stub)?;
-        let f1 = result_f(&Number::Float(OrderedFloat(f(f1))), rnd_f);
+        let f1 = try_numeric_result!(self, result_f(&self.flags, &n1, rnd_f), stub)?;
+        let result = float_result(&self.flags, f(f1), &[f1]);
 
-        try_numeric_result!(self, f1, stub)
+        try_numeric_result!(self, result, stub)
     }
 
     #[inline]
@@ -597,19 +666,111 @@ impl MachineState {
     }
 
     #[inline]
-    pub(crate) fn sqrt(&self, n1: Number) -> Result<f64, MachineStub> {
-        if n1.is_negative() {
-            let stub = MachineError::functor_stub(clause_name!("is"), 2);
-            return Err(self.error_form(MachineError::evaluation_error(EvalError::Undefined), stub));
+    pub(crate) fn cot(&self, n1: Number) -> Result<f64, MachineStub> {
+        self.unary_float_fn_template(n1, |f| f.tan().recip())
+    }
+
+    #[inline]
+    pub(crate) fn acot(&self, n1: Number) -> Result<f64, MachineStub> {
+        self.unary_float_fn_template(n1, |f| f.recip().atan())
+    }
+
+    #[inline]
+    pub(crate) fn sinh(&self, n1: Number) -> Result<f64, MachineStub> {
+        self.unary_float_fn_template(n1, |f| f.sinh())
+    }
+
+    #[inline]
+    pub(crate) fn cosh(&self, n1: Number) -> Result<f64, MachineStub> {
+        self.unary_float_fn_template(n1, |f| f.cosh())
+    }
+
+    #[inline]
+    pub(crate) fn tanh(&self, n1: Number) -> Result<f64, MachineStub> {
+        self.unary_float_fn_template(n1, |f| f.tanh())
+    }
+
+    #[inline]
+    pub(crate) fn asinh(&self, n1: Number) -> Result<f64, MachineStub> {
+        self.unary_float_fn_template(n1, |f| f.asinh())
+    }
+
+    #[inline]
+    pub(crate) fn acosh(&self, n1: Number) -> Result<f64, MachineStub> {
+        self.unary_float_fn_template(n1, |f| f.acosh())
+    }
+
+    #[inline]
+    pub(crate) fn atanh(&self, n1: Number) -> Result<f64, MachineStub> {
+        self.unary_float_fn_template(n1, |f| f.atanh())
+    }
+
+    #[inline]
+    pub(crate) fn log2(&self, n1: Number) -> Result<f64, MachineStub> {
+        self.unary_float_fn_template(n1, |f| f.log2())
+    }
+
+    pub(crate) fn log_base(&self, n1: Number, n2: Number) -> Result<f64, MachineStub> {
+        let stub = MachineError::functor_stub(clause_name!("is"), 2);
+
+        if !n1.is_positive() || !n2.is_positive() {
+            Err(self.error_form(MachineError::evaluation_error(EvalError::Undefined), stub))
+        } else {
+            let base = self.float(n1)?;
+
+            if base == 1.0 {
+                return Err(self.error_form(
+                    MachineError::evaluation_error(EvalError::ZeroDivisor),
+                    stub,
+                ));
+            }
+
+            self.unary_float_fn_template(n2, |f| f.ln() / base.ln())
         }
+    }
+
+    pub(crate) fn copysign(&self, n1: Number, n2: Number) -> Result<f64, MachineStub> {
+        let f2 = self.float(n2)?;
+        self.unary_float_fn_template(n1, |f| f.copysign(f2))
+    }
+
+    // the float next to n1 in the direction of n2.
+    pub(crate) fn nexttoward(&self, n1: Number, n2: Number) -> Result<f64, MachineStub> {
+        let f1 = self.float(n1)?;
+        let f2 = self.float(n2)?;
+
+        let result = if f1 == f2 {
+            f1
+        } else if f1 == 0.0 {
+            f64::from_bits(1).copysign(f2)
+        } else if (f1 < f2) == (f1 > 0.0) {
+            f64::from_bits(f1.to_bits() + 1)
+        } else {
+            f64::from_bits(f1.to_bits() - 1)
+        };
+
+        self.unary_float_fn_template(Number::Float(OrderedFloat(result)), |f| f)
+    }
 
+    #[inline]
+    pub(crate) fn float_integer_part(&self, n1: Number) -> Result<f64, MachineStub> {
+        self.unary_float_fn_template(n1, |f| f.trunc())
+    }
+
+    #[inline]
+    pub(crate) fn float_fractional_part(&self, n1: Number) -> Result<f64, MachineStub> {
+        self.unary_float_fn_template(n1, |f| f.fract())
+    }
+
+    #[inline]
+    pub(crate) fn sqrt(&self, n1: Number) -> Result<f64, MachineStub> {
         self.unary_float_fn_template(n1, |f| f.sqrt())
     }
 
     #[inline]
     pub(crate) fn float(&self, n: Number) -> Result<f64, MachineStub> {
         let stub = MachineError::functor_stub(clause_name!("is"), 2);
-        try_numeric_result!(self, result_f(&n, rnd_f), stub)
+        try_numeric_result!(self, result_f(&self.flags, &n, rnd_f), stub)
     }
 
     #[inline]
@@ -632,14 +793,19 @@ impl MachineState {
     }
 
     pub(crate) fn round(&self, n: Number) -> Result<Number, MachineStub> {
-        let stub = MachineError::functor_stub(clause_name!("is"), 2);
-
-        let result = n + Number::Float(OrderedFloat(0.5f64));
-        let result = try_numeric_result!(self, result, stub)?;
-
+        let result = self.add(n, Number::Float(OrderedFloat(0.5f64)))?;
         Ok(self.floor(result))
     }
 
+    // round/1 rounds halves up, integer/1 rounds them away from zero.
+    pub(crate) fn integer(&self, n: Number) -> Result<Number, MachineStub> {
+        if n.is_negative() {
+            Ok(-self.round(n.abs())?)
+        } else {
+            self.round(n)
+        }
+    }
+
     pub(crate) fn shr(&self, n1: Number, n2: Number) -> Result<Number, MachineStub> {
         let stub = MachineError::functor_stub(clause_name!("(>>)"), 2);
 
@@ -942,8 +1108,8 @@ impl MachineState {
             (n1, n2) => {
                 let stub = MachineError::functor_stub(clause_name!("max"), 2);
 
-                let f1 = try_numeric_result!(self, result_f(&n1, rnd_f), stub)?;
-                let f2 = try_numeric_result!(self, result_f(&n2, rnd_f), stub)?;
+                let f1 = try_numeric_result!(self, result_f(&self.flags, &n1, rnd_f), stub)?;
+                let f2 = try_numeric_result!(self, result_f(&self.flags, &n2, rnd_f), stub)?;
 
                 Ok(Number::Float(cmp::max(OrderedFloat(f1), OrderedFloat(f2))))
             }
@@ -983,8 +1149,8 @@ impl MachineState {
             (n1, n2) => {
                 let stub = MachineError::functor_stub(clause_name!("max"), 2);
 
-                let f1 = try_numeric_result!(self, result_f(&n1, rnd_f), stub)?;
-                let f2 = try_numeric_result!(self, result_f(&n2, rnd_f), stub)?;
+                let f1 = try_numeric_result!(self, result_f(&self.flags, &n1, rnd_f), stub)?;
+                let f2 = try_numeric_result!(self, result_f(&self.flags, &n2, rnd_f), stub)?;
 
                 Ok(Number::Float(cmp::min(OrderedFloat(f1), OrderedFloat(f2))))
             }
@@ -1000,4 +1166,108 @@ impl MachineState {
             Number::from(0)
         }
     }
+
+    fn positive_integer(&self, n: Number, name: &'static str) -> Result<Integer, MachineStub> {
+        let stub = MachineError::functor_stub(clause_name!(name), 1);
+
+        match n {
+            Number::Fixnum(n1) if n1 > 0 => Ok(Integer::from(n1)),
+            Number::Integer(n1) if *n1 > 0 => Ok(Integer::from(&*n1)),
+            Number::Fixnum(_) | Number::Integer(_) => Err(self.error_form(
+                MachineError::domain_error(DomainErrorType::NotLessThanOne, n),
+                stub,
+            )),
+            _ => Err(self.error_form(
+                MachineError::type_error(self.heap.h(), ValidType::Integer, n),
+                stub,
+            )),
+        }
+    }
+
+    pub(crate) fn msb(&self, n: Number) -> Result<Number, MachineStub> {
+        let n = self.positive_integer(n, "msb")?;
+        Ok(Number::from(n.significant_bits() as isize - 1))
+    }
+
+    pub(crate) fn lsb(&self, n: Number) -> Result<Number, MachineStub> {
+        let n = self.positive_integer(n, "lsb")?;
+        Ok(Number::from(n.find_one(0).unwrap() as isize))
+    }
+
+    pub(crate) fn popcount(&self, n: Number) -> Result<Number, MachineStub> {
+        let stub = MachineError::functor_stub(clause_name!("popcount"), 1);
+
+        match n {
+            Number::Fixnum(n1) if n1 >= 0 => Ok(Number::from(n1.count_ones() as isize)),
+            Number::Integer(ref n1) if **n1 >= 0 => {
+                Ok(Number::from(n1.count_ones().unwrap() as isize))
+            }
+            Number::Fixnum(_) | Number::Integer(_) => Err(self.error_form(
+                MachineError::domain_error(DomainErrorType::NotLessThanZero, n),
+                stub,
+            )),
+            _ => Err(self.error_form(
+                MachineError::type_error(self.heap.h(), ValidType::Integer, n),
+                stub,
+            )),
+        }
+    }
+
+    // the successor of a natural number, and zero for negative
+    // integers.
+    pub(crate) fn succ_or_zero(&self, n: Number) -> Result<Number, MachineStub> {
+        let stub = MachineError::functor_stub(clause_name!("succ_or_zero"), 1);
+
+        match n {
+            Number::Fixnum(_) | Number::Integer(_) if n.is_negative() => Ok(Number::from(0)),
+            Number::Fixnum(_) | Number::Integer(_) => {
+                try_numeric_result!(self, n + Number::from(1), stub)
+            }
+            _ => Err(self.error_form(
+                MachineError::type_error(self.heap.h(), ValidType::Integer, n),
+                stub,
+            )),
+        }
+    }
+
+    pub(crate) fn random(&self, n: Number) -> Result<Number, MachineStub> {
+        let n = self.positive_integer(n, "random")?;
+        Ok(Number::from(self.prng.below(&n)))
+    }
+
+    #[inline]
+    pub(crate) fn random_float(&self) -> f64 {
+        self.prng.next_f64()
+    }
+
+    // the float flags as a bit set, in the order float_overflow,
+    // float_zero_div, float_undefined and float_underflow, where a set
+    // bit means the condition yields a special value, not an error.
+    pub(crate) fn cmpflags(&self) -> Number {
+        let traps = [
+            self.flags.trap_float_overflow,
+            self.flags.trap_float_zero_div,
+            self.flags.trap_float_undefined,
+            self.flags.trap_float_underflow,
+        ];
+
+        let bits = traps
+            .iter()
+            .enumerate()
+            .fold(0, |bits, (i, &trap)| if trap { bits } else { bits | 1 << i });
+
+        Number::from(bits)
+    }
+
+    // the value of an evaluable atom with an instruction of its own.
+    pub(crate) fn nullary_value(&self, instr: NullaryInstruction) -> Number {
+        match instr {
+            NullaryInstruction::CmpFlags => self.cmpflags(),
+            NullaryInstruction::Epsilon => Number::Float(OrderedFloat(f64::EPSILON)),
+            NullaryInstruction::Inf => Number::Float(OrderedFloat(f64::INFINITY)),
+            NullaryInstruction::MaxTaggedInteger => Number::Fixnum(isize::max_value()),
+            NullaryInstruction::NaN => Number::Float(OrderedFloat(f64::NAN)),
+            NullaryInstruction::RandomFloat => Number::Float(OrderedFloat(self.random_float())),
+        }
+    }
 }
@@ -0,0 +1,214 @@
+use prolog_parser::ast::*;
+use prolog_parser::clause_name;
+
+use crate::forms::*;
+use crate::instructions::*;
+use crate::machine::machine_indices::*;
+use crate::machine::machine_state::*;
+use crate::rug::Integer;
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+// maps the first instruction of each predicate to its module and key.
+type PredicateStarts = BTreeMap<usize, (ClauseName, PredicateKey)>;
+
+fn index_ptr_start(code_index: &CodeIndex) -> Option<usize> {
+    match code_index.get() {
+        IndexPtr::Index(p) | IndexPtr::DynamicIndex(p) => Some(p),
+        IndexPtr::DynamicUndefined | IndexPtr::Undefined => None,
+    }
+}
+
+fn predicate_starts(indices: &IndexStore) -> PredicateStarts {
+    let mut starts = PredicateStarts::new();
+
+    // imported predicates share their code index with the exporting
+    // module, so exported predicates are claimed first, then those of
+    // user, and finally those private to their modules.
+    for (module_name, module) in indices.modules.iter() {
+        for (key, code_index) in module.code_dir.iter() {
+            let exported = module
+                .module_decl
+                .exports
+                .iter()
+                .any(|export| match export {
+                    ModuleExport::PredicateKey(ref export_key) => export_key == key,
+                    ModuleExport::OpDecl(_) => false,
+                });
+
+            if let (true, Some(p)) = (exported, index_ptr_start(code_index)) {
+                starts.insert(p, (module_name.clone(), key.clone()));
+            }
+        }
+    }
+
+    for (key, code_index) in indices.code_dir.iter() {
+        if let Some(p) = index_ptr_start(code_index) {
+            starts
+                .entry(p)
+                .or_insert_with(|| (clause_name!("user"), key.clone()));
+        }
+    }
+
+    for (module_name, module) in indices.modules.iter() {
+        for (key, code_index) in module.code_dir.iter() {
+            if let Some(p) = index_ptr_start(code_index) {
+                starts
+                    .entry(p)
+                    .or_insert_with(|| (module_name.clone(), key.clone()));
+            }
+        }
+    }
+
+    starts
+}
+
+// the key of the predicate whose code contains p.
+pub(super) fn predicate_at(indices: &IndexStore, p: usize) -> Option<PredicateKey> {
+    let starts = predicate_starts(indices);
+
+    starts
+        .range(..=p)
+        .next_back()
+        .map(|(_, (_, key))| key.clone())
+}
+
+// clauses are numbered from 1 by following the chain of choice
+// instructions beginning at the start of the predicate.
+fn clause_number(code: &Code, start: usize, p: usize) -> usize {
+    let mut q = start;
+    let mut clause_num = 1;
+
+    if let Some(Line::IndexingCode(_)) = code.get(q) {
+        q += 1;
+    }
+
+    loop {
+        let offset = match code.get(q) {
+            Some(Line::Choice(ChoiceInstruction::TryMeElse(offset)))
+            | Some(Line::Choice(ChoiceInstruction::RetryMeElse(offset)))
+            | Some(Line::Choice(ChoiceInstruction::DefaultRetryMeElse(offset)))
+            | Some(Line::Choice(ChoiceInstruction::DynamicElse(_, _, NextOrFail::Next(offset))))
+            | Some(Line::Choice(ChoiceInstruction::DynamicInternalElse(
+                _,
+                _,
+                NextOrFail::Next(offset),
+            ))) if *offset > 0 => *offset,
+            _ => return clause_num,
+        };
+
+        if p < q + offset {
+            return clause_num;
+        }
+
+        q += offset;
+        clause_num += 1;
+    }
+}
+
+impl MachineState {
+    // records the code pointers of the active environment frames,
+    // innermost first.
+    pub(super) fn capture_backtrace(&mut self) {
+        self.backtrace.clear();
+
+        let mut ptrs = vec![self.p.local()];
+        let (mut cp, mut e) = (self.cp, self.e);
+        let mut sites = self.last_call_sites.iter().rev().peekable();
+
+        loop {
+            // the clauses left by last calls returning to cp come
+            // before it. sites of environments passed already are
+            // stale.
+            while let Some(&&(site, site_cp, site_e)) = sites.peek() {
+                if (site_cp, site_e) == (cp, e) {
+                    ptrs.push(site);
+                } else if site_e < e {
+                    break;
+                }
+
+                sites.next();
+            }
+
+            ptrs.push(cp);
+
+            if e == 0 {
+                break;
+            }
+
+            let frame = self.stack.index_and_frame(e);
+
+            cp = frame.prelude.cp;
+            e = frame.prelude.e;
+        }
+
+        for ptr in ptrs {
+            let p = match ptr {
+                LocalCodePtr::DirEntry(p) | LocalCodePtr::IndexingBuf(p, ..) => p,
+                LocalCodePtr::Halt => continue,
+            };
+
+            if self.backtrace.last() != Some(&p) {
+                self.backtrace.push(p);
+            }
+        }
+    }
+
+    // a last call discards the frame of its clause, so while the debug
+    // flag is on, its call site is kept for backtraces. the sites of
+    // environments since popped are dropped first.
+    pub(super) fn record_last_call(&mut self) {
+        let (cp, e) = (self.cp, self.e);
+
+        while let Some(&(_, site_cp, site_e)) = self.last_call_sites.last() {
+            if (site_cp, site_e) == (cp, e) || site_e < e {
+                break;
+            }
+
+            self.last_call_sites.pop();
+        }
+
+        self.last_call_sites.push((self.p.local(), cp, e));
+    }
+
+    // writes the captured backtrace to the heap as a list of
+    // frame(Module:Name/Arity, ClauseNumber) terms.
+    pub(super) fn backtrace_to_list(&mut self, code: &Code, indices: &IndexStore) -> Addr {
+        let starts = predicate_starts(indices);
+        let mut frames = vec![];
+
+        for &p in self.backtrace.iter() {
+            if let Some((&start, (module_name, (name, arity)))) = starts.range(..=p).next_back() {
+                let h = self.heap.h();
+                let clause_num = clause_number(code, start, p);
+
+                self.heap
+                    .push(HeapCellValue::NamedStr(2, clause_name!("frame"), None));
+                self.heap.push(HeapCellValue::Addr(Addr::HeapCell(h + 3)));
+                self.heap
+                    .push(HeapCellValue::Integer(Rc::new(Integer::from(clause_num))));
+                self.heap.push(HeapCellValue::NamedStr(
+                    2,
+                    clause_name!(":"),
+                    Some(SharedOpDesc::new(600, XFY)),
+                ));
+                self.heap
+                    .push(HeapCellValue::Atom(module_name.clone(), None));
+                self.heap.push(HeapCellValue::Addr(Addr::HeapCell(h + 6)));
+                self.heap.push(HeapCellValue::NamedStr(
+                    2,
+                    clause_name!("/"),
+                    Some(SharedOpDesc::new(400, YFX)),
+                ));
+                self.heap.push(HeapCellValue::Atom(name.clone(), None));
+                self.heap
+                    .push(HeapCellValue::Integer(Rc::new(Integer::from(*arity))));
+
+                frames.push(Addr::HeapCell(h));
+            }
+        }
+
+        Addr::HeapCell(self.heap.to_list(frames.into_iter()))
+    }
+}
@@ -1,16 +1,36 @@
 use crate::clause_types::*;
+use crate::forms::*;
 use crate::instructions::*;
+use crate::machine::loader::*;
 use crate::machine::machine_indices::*;
 
+use indexmap::IndexMap;
+
 #[derive(Debug)]
 pub(crate) struct CodeRepo {
     pub(super) code: Code,
+    // the number of clauses of each predicate compiled without a
+    // skeleton, which keeps the clauses of all others. Recompiling a
+    // predicate replaces its count.
+    pub(super) static_clauses: IndexMap<(CompilationTarget, PredicateKey), (CodeIndex, usize)>,
 }
 
 impl CodeRepo {
     #[inline]
     pub(super) fn new() -> Self {
-        CodeRepo { code: Code::new() }
+        CodeRepo {
+            code: Code::new(),
+            static_clauses: IndexMap::new(),
+        }
+    }
+
+    // the number of clauses of the static predicates still defined.
+    pub(super) fn static_clause_count(&self) -> usize {
+        self.static_clauses
+            .values()
+            .filter(|(code_index, _)| !code_index.is_undefined())
+            .map(|(_, num_clauses)| num_clauses)
+            .sum()
     }
 
     #[inline]
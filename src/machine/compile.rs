@@ -57,6 +57,7 @@ pub(super) fn compile_appendix(
     mut queue: VecDeque<TopLevel>,
     jmp_by_locs: Vec<usize>,
     non_counted_bt: bool,
+    occurs_check: Option<OccursCheck>,
     atom_tbl: TabledData<Atom>,
 ) -> Result<(), CompilationError> {
     let mut jmp_by_locs = VecDeque::from(jmp_by_locs);
@@ -78,6 +79,7 @@ pub(super) fn compile_appendix(
             global_clock_tick: None,
             is_extensible: false,
             non_counted_bt,
+            occurs_check,
         };
 
         let mut cg = CodeGenerator::<DebrayAllocator>::new(atom_tbl.clone(), settings);
@@ -876,6 +878,7 @@ fn prepend_compiled_clause(
         },
         is_extensible: true,
         non_counted_bt: false,
+        occurs_check: None,
     };
 
     let clause_loc = if skeleton.clauses[0]
@@ -1161,6 +1164,7 @@ fn append_compiled_clause(
         },
         is_extensible: true,
         non_counted_bt: false,
+        occurs_check: None,
     };
 
     skeleton.clauses[target_pos].clause_start = clause_loc;
@@ -1351,6 +1355,7 @@ impl<'a> LoadState<'a> {
             queue,
             cg.jmp_by_locs,
             settings.non_counted_bt,
+            settings.occurs_check,
             atom_tbl,
         )?;
 
@@ -1387,11 +1392,23 @@ impl<'a> LoadState<'a> {
         let queue = preprocessor.parse_queue(self)?;
         let mut code = cg.compile_predicate(&clauses)?;
 
+        let static_key = (predicates.compilation_target.clone(), key.clone());
+
+        if settings.is_extensible {
+            self.wam.code_repo.static_clauses.remove(&static_key);
+        } else {
+            self.wam
+                .code_repo
+                .static_clauses
+                .insert(static_key, (code_index.clone(), clauses.len()));
+        }
+
         compile_appendix(
             &mut code,
             queue,
             cg.jmp_by_locs,
             settings.non_counted_bt,
+            settings.occurs_check,
             self.wam.machine_st.atom_tbl.clone(),
         )?;
 
@@ -1619,6 +1636,8 @@ impl<'a> LoadState<'a> {
         non_counted_bt: bool,
         append_or_prepend: AppendOrPrepend,
     ) -> Result<CodeIndex, SessionError> {
+        let occurs_check = self.wam.indices.occurs_check(&compilation_target);
+
         let settings = match self
             .wam
             .indices
@@ -1633,6 +1652,7 @@ impl<'a> LoadState<'a> {
                     },
                     is_extensible: true,
                     non_counted_bt,
+                    occurs_check,
                 }
             },
             skeleton_opt => {
@@ -1648,6 +1668,7 @@ impl<'a> LoadState<'a> {
                     },
                     is_extensible: true,
                     non_counted_bt,
+                    occurs_check,
                 };
 
                 let mut predicate_queue = predicate_queue![clause];
@@ -2337,6 +2358,11 @@ impl<'a, TS: TermStream> Loader<'a, TS> {
                 },
                 is_extensible: predicate_info.is_extensible,
                 non_counted_bt,
+                occurs_check: self
+                    .load_state
+                    .wam
+                    .indices
+                    .occurs_check(&self.predicates.compilation_target),
             };
 
             let code_index
@@ -242,6 +242,13 @@ impl<T: CopierTarget> CopyTermState<T> {
             match self.value_at_scan() {
                 &mut HeapCellValue::Addr(addr) => match addr {
                     Addr::Con(h) => {
+                        // a constant displaced by setarg/3 is read
+                        // from its copy.
+                        let h = match self.target.store(addr) {
+                            Addr::Con(h) => h,
+                            _ => h,
+                        };
+
                         let addr = self.target[h].as_addr(h);
 
                         if addr == Addr::Con(h) {
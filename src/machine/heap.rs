@@ -6,10 +6,12 @@ use crate::machine::machine_indices::*;
 use crate::machine::partial_string::*;
 use crate::machine::raw_block::*;
 
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::mem;
 use std::ops::{Index, IndexMut};
 use std::ptr;
+use std::rc::Rc;
 
 #[derive(Debug)]
 pub(crate) struct StandardHeapTraits {}
@@ -29,6 +31,10 @@ impl RawBlockTraits for StandardHeapTraits {
 #[derive(Debug)]
 pub(crate) struct HeapTemplate<T: RawBlockTraits> {
     buf: RawBlock<T>,
+    // inline constants overwritten by setarg/3 and nb_setarg/3, by
+    // the location of their argument, with the location and value of
+    // the copies that the variables bound to them now see.
+    displaced_constants: BTreeMap<usize, (usize, HeapCellValue)>,
     _marker: PhantomData<HeapCellValue>,
 }
 
@@ -51,6 +57,7 @@ impl<T: RawBlockTraits> Drop for HeapIntoIter<T> {
     fn drop(&mut self) {
         let mut heap = HeapTemplate {
             buf: self.buf.take(),
+            displaced_constants: BTreeMap::new(),
             _marker: PhantomData,
         };
 
@@ -140,6 +147,7 @@ impl<T: RawBlockTraits> HeapTemplate<T> {
     pub(crate) fn new() -> Self {
         HeapTemplate {
             buf: RawBlock::new(),
+            displaced_constants: BTreeMap::new(),
             _marker: PhantomData,
         }
     }
@@ -157,6 +165,7 @@ impl<T: RawBlockTraits> HeapTemplate<T> {
             }
             &HeapCellValue::PartialString(..) => HeapCellValue::Addr(Addr::PStrLocation(h, 0)),
             &HeapCellValue::Rational(ref r) => HeapCellValue::Rational(r.clone()),
+            &HeapCellValue::OpaqueString(ref s) => HeapCellValue::OpaqueString(s.clone()),
             &HeapCellValue::Stream(_) => HeapCellValue::Addr(Addr::Stream(h)),
             &HeapCellValue::TcpListener(_) => HeapCellValue::Addr(Addr::TcpListener(h)),
         }
@@ -185,6 +194,11 @@ impl<T: RawBlockTraits> HeapTemplate<T> {
         addr
     }
 
+    #[inline]
+    pub(crate) fn put_opaque_string(&mut self, s: &str) -> Addr {
+        Addr::Con(self.push(HeapCellValue::OpaqueString(Rc::new(s.to_string()))))
+    }
+
     #[inline]
     pub(crate) fn put_constant(&mut self, c: Constant) -> Addr {
         match c {
@@ -202,6 +216,7 @@ impl<T: RawBlockTraits> HeapTemplate<T> {
                     self.put_complete_string(&s)
                 }
             }
+            Constant::OpaqueString(s) => Addr::Con(self.push(HeapCellValue::OpaqueString(s))),
             Constant::Usize(n) => Addr::Usize(n),
         }
     }
@@ -242,6 +257,15 @@ impl<T: RawBlockTraits> HeapTemplate<T> {
         }
     }
 
+    #[inline]
+    pub(crate) fn opaque_string_at(&self, h: usize) -> bool {
+        if let HeapCellValue::OpaqueString(_) = &self[h] {
+            true
+        } else {
+            false
+        }
+    }
+
     #[inline]
     pub(crate) fn to_unifiable(&mut self, non_heap_value: HeapCellValue) -> Addr {
         match non_heap_value {
@@ -249,7 +273,8 @@ impl<T: RawBlockTraits> HeapTemplate<T> {
             val @ HeapCellValue::Atom(..)
             | val @ HeapCellValue::Integer(_)
             | val @ HeapCellValue::DBRef(_)
-            | val @ HeapCellValue::Rational(_) => Addr::Con(self.push(val)),
+            | val @ HeapCellValue::Rational(_)
+            | val @ HeapCellValue::OpaqueString(_) => Addr::Con(self.push(val)),
             val @ HeapCellValue::LoadStatePayload(_) => Addr::LoadStatePayload(self.push(val)),
             val @ HeapCellValue::NamedStr(..) => Addr::Str(self.push(val)),
             HeapCellValue::PartialString(pstr, has_tail) => {
@@ -318,6 +343,10 @@ impl<T: RawBlockTraits> HeapTemplate<T> {
 
     #[inline]
     pub(crate) fn truncate(&mut self, h: usize) {
+        if !self.displaced_constants.is_empty() {
+            self.displaced_constants.split_off(&h);
+        }
+
         let new_top = h * mem::size_of::<HeapCellValue>() + self.buf.base as usize;
         let mut h = new_top;
 
@@ -337,6 +366,76 @@ impl<T: RawBlockTraits> HeapTemplate<T> {
         (self.buf.top as usize - self.buf.base as usize) / mem::size_of::<HeapCellValue>()
     }
 
+    #[inline]
+    pub(crate) fn limit(&self) -> usize {
+        self.buf.limit
+    }
+
+    #[inline]
+    pub(crate) fn set_limit(&mut self, limit: usize) {
+        self.buf.limit = limit;
+    }
+
+    #[inline]
+    pub(crate) fn take_exhausted(&mut self) -> bool {
+        self.buf.take_exhausted()
+    }
+
+    // an inline constant is stored in the cell of its argument, which
+    // is what a variable bound to the argument refers to. before
+    // setarg/3 or nb_setarg/3 overwrite the cell, the constant is
+    // copied, so that those variables keep their value. returns the
+    // location of the copy.
+    pub(crate) fn displace_constant(&mut self, h: usize) -> Option<usize> {
+        let value = match &self[h] {
+            HeapCellValue::Addr(_) => return None,
+            value => value.context_free_clone(),
+        };
+
+        let copy_h = self.push(value.context_free_clone());
+        self.displaced_constants.insert(h, (copy_h, value));
+
+        Some(copy_h)
+    }
+
+    // copies the constant displaced from the cell at h again, if
+    // backtracking reclaimed its copy while the cell kept the value
+    // nb_setarg/3 gave it.
+    pub(crate) fn restore_displaced_constant(&mut self, h: usize) {
+        let value = match self.displaced_constants.get(&h) {
+            Some((copy_h, value)) if *copy_h >= self.h() => value.context_free_clone(),
+            _ => return,
+        };
+
+        let copy_h = self.push(value.context_free_clone());
+        self.displaced_constants.insert(h, (copy_h, value));
+    }
+
+    // the constant a variable bound to addr refers to, which is a
+    // copy if the constant was displaced from its cell.
+    #[inline]
+    pub(crate) fn resolve_constant(&self, addr: Addr) -> Addr {
+        match addr {
+            Addr::Con(h) if !self.displaced_constants.is_empty() => match &self[h] {
+                HeapCellValue::Addr(_) => match self.displaced_constants.get(&h) {
+                    Some(&(copy_h, _)) => Addr::Con(copy_h),
+                    None => addr,
+                },
+                _ => addr,
+            },
+            addr => addr,
+        }
+    }
+
+    // appends vals without marking the heap as exhausted. The machine
+    // uses it for the terms it must write regardless of the limit,
+    // like the ball of an exception.
+    pub(crate) fn append_unlimited(&mut self, vals: Vec<HeapCellValue>) {
+        let exhausted = self.buf.exhausted;
+        self.append(vals);
+        self.buf.exhausted = exhausted;
+    }
+
     pub(crate) fn append(&mut self, vals: Vec<HeapCellValue>) {
         for val in vals {
             self.push(val);
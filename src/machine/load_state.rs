@@ -924,6 +924,7 @@ impl<'a> LoadState<'a> {
         match self.wam.indices.modules.get_mut(&module_name) {
             Some(module) => {
                 let old_module_decl = mem::replace(&mut module.module_decl, module_decl.clone());
+                module.occurs_check = None;
 
                 let local_extensible_predicates = mem::replace(
                     &mut module.local_extensible_predicates,
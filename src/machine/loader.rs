@@ -855,6 +855,12 @@ impl<'a, TS: TermStream> Loader<'a, TS> {
                         Constant::Rational(rational.clone()),
                     ));
                 }
+                HeapCellValue::OpaqueString(ref string) => {
+                    term_stack.push(Term::Constant(
+                        Cell::default(),
+                        Constant::OpaqueString(string.clone()),
+                    ));
+                }
                 _ => {
                     return Err(SessionError::from(CompilationError::UnreadableTerm));
                 }
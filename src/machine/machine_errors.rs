@@ -387,6 +387,16 @@ impl MachineError {
         }
     }
 
+    pub(super) fn resource_error(resource: &'static str) -> Self {
+        let stub = functor!("resource_error", [atom(resource)]);
+
+        MachineError {
+            stub,
+            location: None,
+            from: ErrorProvenance::Received,
+        }
+    }
+
     pub(super) fn representation_error(flag: RepFlag) -> Self {
         let stub = functor!("representation_error", [atom(flag.as_str())]);
 
@@ -568,6 +578,7 @@ impl ValidType {
 #[derive(Debug, Clone, Copy)]
 pub(crate) enum DomainErrorType {
     IOMode,
+    NotLessThanOne,
     NotLessThanZero,
     Order,
     SourceSink,
@@ -579,6 +590,7 @@ impl DomainErrorType {
     pub(crate) fn as_str(self) -> &'static str {
         match self {
             DomainErrorType::IOMode => "io_mode",
+            DomainErrorType::NotLessThanOne => "not_less_than_one",
             DomainErrorType::NotLessThanZero => "not_less_than_zero",
             DomainErrorType::Order => "order",
             DomainErrorType::SourceSink => "source_sink",
@@ -619,7 +631,7 @@ impl RepFlag {
 pub(crate) enum EvalError {
     FloatOverflow,
     Undefined,
-    //    Underflow,
+    Underflow,
     ZeroDivisor,
 }
 
@@ -628,7 +640,7 @@ impl EvalError {
         match self {
             EvalError::FloatOverflow => "float_overflow",
             EvalError::Undefined => "undefined",
-            //            EvalError::FloatUnderflow => "underflow",
+            EvalError::Underflow => "underflow",
             EvalError::ZeroDivisor => "zero_divisor",
         }
     }
@@ -796,7 +808,7 @@ impl MachineState {
         self.ball.boundary = 0;
         self.ball.stub.truncate(0);
 
-        self.heap.append(err);
+        self.heap.append_unlimited(err);
 
         self.registers[1] = Addr::HeapCell(h);
 
@@ -52,6 +52,7 @@ pub(crate) enum TermOrderCategory {
     FloatingPoint,
     Integer,
     Atom,
+    String,
     Compound,
 }
 
@@ -184,6 +185,7 @@ impl Addr {
                 Addr::Float(_) => Some(TermOrderCategory::FloatingPoint),
                 &Addr::Con(h) => match &heap[h] {
                     HeapCellValue::Atom(..) => Some(TermOrderCategory::Atom),
+                    HeapCellValue::OpaqueString(_) => Some(TermOrderCategory::String),
                     HeapCellValue::DBRef(_) => None,
                     _ => {
                         unreachable!()
@@ -212,6 +214,7 @@ impl Addr {
                 &HeapCellValue::Atom(ref name, _) => Some(Constant::Atom(name.clone(), None)),
                 &HeapCellValue::Integer(ref n) => Some(Constant::Integer(n.clone())),
                 &HeapCellValue::Rational(ref n) => Some(Constant::Rational(n.clone())),
+                &HeapCellValue::OpaqueString(ref s) => Some(Constant::OpaqueString(s.clone())),
                 _ => None,
             },
             &Addr::EmptyList => Some(Constant::EmptyList),
@@ -298,6 +301,8 @@ pub(crate) enum TrailRef {
     AttrVarListLink(usize, usize),
     BlackboardEntry(usize),
     BlackboardOffset(usize, usize), // key atom heap location, key value heap location
+    ArgOffset(usize, usize), // argument heap location, old argument value heap location
+    NbArg(usize),            // argument heap location of a value stored by nb_setarg/3
 }
 
 impl From<Ref> for TrailRef {
@@ -316,6 +321,7 @@ pub(crate) enum HeapCellValue {
     NamedStr(usize, ClauseName, Option<SharedOpDesc>), // arity, name, precedence/Specifier if it has one.
     Rational(Rc<Rational>),
     PartialString(PartialString, bool), // the partial string, a bool indicating whether it came from a Constant.
+    OpaqueString(Rc<String>), // a string object, which is atomic and not a list.
     Stream(Stream),
     TcpListener(TcpListener),
 }
@@ -328,7 +334,8 @@ impl HeapCellValue {
             HeapCellValue::Atom(..)
             | HeapCellValue::DBRef(..)
             | HeapCellValue::Integer(..)
-            | HeapCellValue::Rational(..) => Addr::Con(focus),
+            | HeapCellValue::Rational(..)
+            | HeapCellValue::OpaqueString(..) => Addr::Con(focus),
             HeapCellValue::LoadStatePayload(_) => Addr::LoadStatePayload(focus),
             HeapCellValue::NamedStr(_, _, _) => Addr::Str(focus),
             HeapCellValue::PartialString(..) => Addr::PStrLocation(focus, 0),
@@ -354,6 +361,7 @@ impl HeapCellValue {
             &HeapCellValue::PartialString(ref pstr, has_tail) => {
                 HeapCellValue::PartialString(pstr.clone(), has_tail)
             }
+            &HeapCellValue::OpaqueString(ref s) => HeapCellValue::OpaqueString(s.clone()),
             &HeapCellValue::Stream(ref stream) => HeapCellValue::Stream(stream.clone()),
             &HeapCellValue::TcpListener(_) => {
                 HeapCellValue::Atom(clause_name!("$tcp_listener"), None)
@@ -777,6 +785,18 @@ impl IndexStore {
         }
     }
 
+    // the occurs_check flag of the module, if it sets it. the clauses
+    // of user unify as the global flag says.
+    pub(crate) fn occurs_check(&self, compilation_target: &CompilationTarget) -> Option<OccursCheck> {
+        match compilation_target {
+            CompilationTarget::User => None,
+            CompilationTarget::Module(ref module_name) => self
+                .modules
+                .get(module_name)
+                .and_then(|module| module.occurs_check),
+        }
+    }
+
     pub(crate) fn remove_predicate_skeleton(
         &mut self,
         compilation_target: &CompilationTarget,
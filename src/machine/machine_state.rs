@@ -11,9 +11,11 @@ use crate::machine::heap::*;
 use crate::machine::machine_errors::*;
 use crate::machine::machine_indices::*;
 use crate::machine::partial_string::HeapPStrIter;
+use crate::machine::prng::*;
 use crate::machine::stack::*;
 use crate::machine::streams::*;
 use crate::rug::Integer;
+use ordered_float::OrderedFloat;
 
 use downcast::{
     downcast, downcast_methods, downcast_methods_core, downcast_methods_std, impl_downcast, Any,
@@ -21,13 +23,16 @@ use downcast::{
 
 use indexmap::IndexMap;
 
+use std::borrow::Cow;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::fmt;
 use std::io::Write;
 use std::mem;
 use std::ops::{Index, IndexMut};
 use std::rc::Rc;
+use std::time::SystemTime;
 
 #[derive(Debug)]
 pub(crate) struct Ball {
@@ -167,7 +172,7 @@ impl<'a> CopierTarget for CopyBallTerm<'a> {
     }
 
     fn store(&self, addr: Addr) -> Addr {
-        match addr {
+        let addr = match addr {
             Addr::HeapCell(h) | Addr::AttrVar(h) if h < self.heap_boundary => {
                 self.heap[h].as_addr(h)
             }
@@ -177,6 +182,11 @@ impl<'a> CopierTarget for CopyBallTerm<'a> {
             }
             Addr::StackCell(fr, sc) => self.stack.index_and_frame(fr)[sc],
             addr => addr,
+        };
+
+        match addr {
+            Addr::Con(h) if h < self.heap_boundary => self.heap.resolve_constant(addr),
+            addr => addr,
         }
     }
 
@@ -292,16 +302,41 @@ pub(crate) struct MachineState {
     pub(crate) stack: Stack,
     pub(super) registers: Registers,
     pub(super) trail: Vec<TrailRef>,
+    pub(super) trail_limit: usize,
+    // whether any of the heap, stack or trail limits is set, and so
+    // checked after each instruction.
+    pub(super) memory_limited: bool,
     pub(super) tr: usize,
     pub(super) hb: usize,
     pub(super) block: usize, // an offset into the OR stack.
     pub(super) ball: Ball,
+    pub(super) backtrace: Vec<usize>,
+    pub(super) keep_backtrace: bool,
+    // the call sites of the last calls made while the debug flag is
+    // on, with the continuation and environment they share with their
+    // callees.
+    pub(super) last_call_sites: Vec<(LocalCodePtr, LocalCodePtr, usize)>,
+    // the backtrace at the last interrupt, shown by the interrupt menu.
+    pub(super) interrupt_backtrace: Vec<usize>,
+    // whether the next call to a user predicate is the retry of one
+    // that was just traced. tracing itself is TRACE_EVENT.
+    pub(super) trace_retry: bool,
     pub(super) lifted_heap: Heap,
+    // the values stored by nb_setarg/3, by the heap location of their
+    // argument. backtracking reclaims their copies on the heap, which
+    // are then made again from these.
+    pub(super) nb_args: BTreeMap<usize, Ball>,
+    pub(super) nb_arg_restores: Vec<usize>,
     pub(super) interms: Vec<Number>, // intermediate numbers.
+    pub(super) prng: Prng,
     pub(super) last_call: bool,
     pub(crate) flags: MachineFlags,
     pub(crate) cc: usize,
     pub(crate) global_clock: usize,
+    // the number of predicate calls made, and the moment the machine
+    // was created, for statistics/2.
+    pub(crate) inferences: u64,
+    pub(crate) start_time: SystemTime,
     pub(crate) dynamic_mode: FirstOrNext,
     pub(crate) unify_fn: fn(&mut MachineState, Addr, Addr),
     pub(crate) bind_fn: fn(&mut MachineState, Ref, Addr),
@@ -329,12 +364,23 @@ impl fmt::Debug for MachineState {
          .field("hb", &self.hb)
          .field("block", &self.block)
          .field("ball", &self.ball)
+         .field("backtrace", &self.backtrace)
+         .field("keep_backtrace", &self.keep_backtrace)
+         .field("last_call_sites", &self.last_call_sites)
+         .field("interrupt_backtrace", &self.interrupt_backtrace)
+         .field("trace_retry", &self.trace_retry)
          .field("lifted_heap", &self.lifted_heap)
+         .field("nb_args", &self.nb_args)
+         .field("nb_arg_restores", &self.nb_arg_restores)
          .field("interms", &self.interms)
+         .field("prng", &self.prng)
          .field("last_call", &self.last_call)
          .field("flags", &self.flags)
          .field("cc", &self.cc)
          .field("global_clock", &self.global_clock)
+         .field("inferences", &self.inferences)
+         .field("memory_limited", &self.memory_limited)
+         .field("start_time", &self.start_time)
          .field("dynamic_mode", &self.dynamic_mode)
          .field("unify_fn",
                 if self.unify_fn as usize == MachineState::unify as usize {
@@ -608,11 +654,112 @@ impl MachineState {
         self.error_form(MachineError::existence_error(h, key), stub)
     }
 
+    #[inline]
+    pub(super) fn trail_bytes_used(&self) -> usize {
+        self.trail.len() * mem::size_of::<TrailRef>()
+    }
+
     #[inline]
     pub(crate) fn heap_pstr_iter<'a>(&'a self, focus: Addr) -> HeapPStrIter<'a> {
         HeapPStrIter::new(self, focus)
     }
 
+    // the text of an atom, number, string, or list of characters or
+    // codes, as accepted by the string predicates.
+    pub(super) fn text_to_string(&self, addr: Addr) -> Option<String> {
+        let addr = self.store(self.deref(addr));
+
+        match addr {
+            Addr::Con(h) if self.heap.atom_at(h) => {
+                if let HeapCellValue::Atom(ref name, _) = &self.heap[h] {
+                    Some(name.as_str().to_string())
+                } else {
+                    unreachable!()
+                }
+            }
+            Addr::Con(h) if self.heap.opaque_string_at(h) => {
+                if let HeapCellValue::OpaqueString(ref s) = &self.heap[h] {
+                    Some(s.to_string())
+                } else {
+                    unreachable!()
+                }
+            }
+            Addr::Char(c) => Some(c.to_string()),
+            Addr::EmptyList => Some(String::new()),
+            Addr::Lis(_) | Addr::PStrLocation(..) => {
+                let mut heap_pstr_iter = self.heap_pstr_iter(addr);
+                let string = heap_pstr_iter.to_string();
+
+                if let Addr::EmptyList = heap_pstr_iter.focus() {
+                    return Some(string);
+                }
+
+                let mut string = String::new();
+                let mut focus = addr;
+
+                loop {
+                    match self.store(self.deref(focus)) {
+                        Addr::Lis(l) => {
+                            let code = self.store(self.deref(Addr::HeapCell(l)));
+
+                            match Number::try_from((code, &self.heap)) {
+                                Ok(Number::Fixnum(n)) => {
+                                    string.push(u32::try_from(n).ok().and_then(std::char::from_u32)?)
+                                }
+                                _ => return None,
+                            }
+
+                            focus = Addr::HeapCell(l + 1);
+                        }
+                        Addr::EmptyList => return Some(string),
+                        _ => return None,
+                    }
+                }
+            }
+            _ => match Number::try_from((addr, &self.heap)) {
+                Ok(Number::Fixnum(n)) => Some(n.to_string()),
+                Ok(Number::Integer(n)) => Some(n.to_string()),
+                Ok(Number::Float(OrderedFloat(f))) => Some(float_str(f, FloatFormat::Shortest)),
+                _ => None,
+            },
+        }
+    }
+
+    // the packed text of the partial string at addr, up to the first
+    // tail that is not itself a partial string. the buffer is borrowed
+    // from the heap unless the text spans several segments.
+    pub(super) fn pstr_prefix(&self, addr: Addr) -> Option<(Cow<'_, str>, Addr)> {
+        let mut text = Cow::Borrowed("");
+        let mut addr = self.store(self.deref(addr));
+
+        match addr {
+            Addr::PStrLocation(..) | Addr::EmptyList => {}
+            _ => return None,
+        }
+
+        while let Addr::PStrLocation(h, n) = addr {
+            if let &HeapCellValue::PartialString(ref pstr, has_tail) = &self.heap[h] {
+                let segment = pstr.as_str_from(n);
+
+                if text.is_empty() {
+                    text = Cow::Borrowed(segment);
+                } else {
+                    text.to_mut().push_str(segment);
+                }
+
+                addr = if has_tail {
+                    self.store(self.deref(Addr::HeapCell(h + 1)))
+                } else {
+                    Addr::EmptyList
+                };
+            } else {
+                unreachable!()
+            }
+        }
+
+        Some((text, addr))
+    }
+
     pub(super) fn try_char_list(&self, addrs: Vec<Addr>) -> Result<String, MachineError> {
         let mut chars = String::new();
         let mut iter = addrs.iter();
@@ -644,6 +791,45 @@ impl MachineState {
         Ok(chars)
     }
 
+    pub(super) fn try_code_list(&self, addrs: Vec<Addr>) -> Result<String, MachineError> {
+        let mut chars = String::new();
+
+        for addr in addrs {
+            let addr = self.store(self.deref(addr));
+
+            // strings read under double_quotes set to chars are
+            // accepted as well.
+            if let Addr::Char(c) = addr {
+                chars.push(c);
+                continue;
+            }
+
+            match Number::try_from((addr, &self.heap)) {
+                Ok(Number::Fixnum(n)) => {
+                    match u32::try_from(n).ok().and_then(std::char::from_u32) {
+                        Some(c) => chars.push(c),
+                        None => {
+                            return Err(MachineError::representation_error(
+                                RepFlag::CharacterCode,
+                            ))
+                        }
+                    }
+                }
+                Ok(Number::Integer(_)) => {
+                    return Err(MachineError::representation_error(
+                        RepFlag::CharacterCode,
+                    ))
+                }
+                _ => {
+                    let h = self.heap.h();
+                    return Err(MachineError::type_error(h, ValidType::Integer, addr));
+                }
+            }
+        }
+
+        Ok(chars)
+    }
+
     pub(super) fn read_predicate_key(&self, name: Addr, arity: Addr) -> (ClauseName, usize) {
         let predicate_name = atom_from!(self, self.store(self.deref(name)));
         let arity = self.store(self.deref(arity));
@@ -766,6 +952,7 @@ pub(crate) trait CallPolicy: Any + fmt::Debug {
             .backtrack(attr_var_init_queue_b, attr_var_init_bindings_b);
 
         machine_st.hb = machine_st.heap.h();
+        machine_st.restore_nb_args();
         machine_st.p += 1;
 
         Ok(())
@@ -822,6 +1009,7 @@ pub(crate) trait CallPolicy: Any + fmt::Debug {
             .backtrack(attr_var_init_queue_b, attr_var_init_bindings_b);
 
         machine_st.hb = machine_st.heap.h();
+        machine_st.restore_nb_args();
         machine_st.p = CodePtr::Local(dir_entry!(machine_st.p.local().abs_loc() + offset));
 
         Ok(())
@@ -879,6 +1067,7 @@ pub(crate) trait CallPolicy: Any + fmt::Debug {
         machine_st.stack.truncate(b);
 
         machine_st.hb = machine_st.heap.h();
+        machine_st.restore_nb_args();
         machine_st.p = CodePtr::Local(dir_entry!(machine_st.p.local().abs_loc() + offset));
 
         Ok(())
@@ -935,6 +1124,7 @@ pub(crate) trait CallPolicy: Any + fmt::Debug {
         machine_st.stack.truncate(b);
 
         machine_st.hb = machine_st.heap.h();
+        machine_st.restore_nb_args();
         machine_st.p += 1;
 
         Ok(())
@@ -947,6 +1137,8 @@ pub(crate) trait CallPolicy: Any + fmt::Debug {
         arity: usize,
         idx: &CodeIndex,
     ) -> CallResult {
+        machine_st.inferences += 1;
+
         if machine_st.last_call {
             self.try_execute(machine_st, name, arity, idx)
         } else {
@@ -1017,6 +1209,8 @@ pub(crate) trait CallPolicy: Any + fmt::Debug {
         current_input_stream: &mut Stream,
         current_output_stream: &mut Stream,
     ) -> CallResult {
+        machine_st.inferences += 1;
+
         match ct {
             &BuiltInClauseType::AcyclicTerm => {
                 let addr = machine_st[temp_v!(1)];
@@ -15,17 +15,41 @@ use crate::machine::machine_errors::*;
 use crate::machine::machine_indices::*;
 use crate::machine::machine_state::*;
 use crate::machine::partial_string::*;
+use crate::machine::prng::*;
 use crate::machine::stack::*;
 use crate::machine::streams::*;
-use crate::machine::INTERRUPT;
+use crate::machine::{
+    take_event, ALARM_EVENT, INTERRUPT_EVENT, PENDING_EVENTS, SIGNAL_EVENT, TRACE_EVENT,
+};
 use crate::rug::Integer;
 use ordered_float::*;
 
 use indexmap::{IndexMap, IndexSet};
 
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
+use std::mem;
 use std::rc::Rc;
+use std::time::SystemTime;
+
+// the functions that unify and bind as the occurs_check flag value
+// says, for the clauses of a module that sets the flag.
+fn occurs_check_fns(
+    occurs_check: OccursCheck,
+) -> (fn(&mut MachineState, Addr, Addr), fn(&mut MachineState, Ref, Addr)) {
+    match occurs_check {
+        OccursCheck::False => (MachineState::unify, MachineState::bind),
+        OccursCheck::True => (
+            MachineState::unify_with_occurs_check,
+            MachineState::bind_with_occurs_check_wrapper,
+        ),
+        OccursCheck::Error => (
+            MachineState::unify_with_occurs_check_with_error,
+            MachineState::bind_with_occurs_check_with_error_wrapper,
+        ),
+    }
+}
 
 impl MachineState {
     pub(crate) fn new() -> Self {
@@ -45,16 +69,28 @@ impl MachineState {
             stack: Stack::new(),
             registers: vec![Addr::HeapCell(0); MAX_ARITY + 1], // self.registers[0] is never used.
             trail: vec![],
+            trail_limit: usize::max_value(),
+            memory_limited: false,
             tr: 0,
             hb: 0,
             block: 0,
             ball: Ball::new(),
+            backtrace: vec![],
+            keep_backtrace: false,
+            last_call_sites: vec![],
+            interrupt_backtrace: vec![],
+            trace_retry: false,
             lifted_heap: Heap::new(),
+            nb_args: BTreeMap::new(),
+            nb_arg_restores: vec![],
             interms: vec![Number::default(); 256],
+            prng: Prng::new(),
             last_call: false,
             flags: MachineFlags::default(),
             cc: 0,
             global_clock: 0,
+            inferences: 0,
+            start_time: SystemTime::now(),
             dynamic_mode: FirstOrNext::First,
             unify_fn: MachineState::unify,
             bind_fn: MachineState::bind,
@@ -67,7 +103,7 @@ impl MachineState {
     }
 
     pub(crate) fn store(&self, addr: Addr) -> Addr {
-        match addr {
+        let addr = match addr {
             Addr::AttrVar(h) | Addr::HeapCell(h) => self.heap[h].as_addr(h),
             Addr::StackCell(fr, sc) => self.stack.index_and_frame(fr)[sc],
             Addr::PStrLocation(h, n) => {
@@ -84,7 +120,9 @@ impl MachineState {
                 }
             }
             addr => addr,
-        }
+        };
+
+        self.heap.resolve_constant(addr)
     }
 
     pub(crate) fn deref(&self, mut addr: Addr) -> Addr {
@@ -377,6 +415,10 @@ impl MachineState {
                             &HeapCellValue::DBRef(ref db_ref_1),
                             &HeapCellValue::DBRef(ref db_ref_2),
                         ) if db_ref_1 == db_ref_2 => {}
+                        (
+                            &HeapCellValue::OpaqueString(ref s1),
+                            &HeapCellValue::OpaqueString(ref s2),
+                        ) if s1 == s2 => {}
                         (v1, v2) => {
                             if let Ok(n1) = Number::try_from(v1) {
                                 if let Ok(n2) = Number::try_from(v2) {
@@ -582,6 +624,10 @@ impl MachineState {
                             &HeapCellValue::DBRef(ref db_ref_1),
                             &HeapCellValue::DBRef(ref db_ref_2),
                         ) if db_ref_1 == db_ref_2 => {}
+                        (
+                            &HeapCellValue::OpaqueString(ref s1),
+                            &HeapCellValue::OpaqueString(ref s2),
+                        ) if s1 == s2 => {}
                         (v1, v2) => {
                             if let Ok(n1) = Number::try_from(v1) {
                                 if let Ok(n2) = Number::try_from(v2) {
@@ -682,6 +728,18 @@ impl MachineState {
                 self.trail.push(TrailRef::BlackboardEntry(key_h));
                 self.tr += 1;
             }
+            TrailRef::ArgOffset(h, value_h) => {
+                if h < self.hb {
+                    self.trail.push(TrailRef::ArgOffset(h, value_h));
+                    self.tr += 1;
+                }
+            }
+            TrailRef::NbArg(h) => {
+                if h < self.hb {
+                    self.trail.push(TrailRef::NbArg(h));
+                    self.tr += 1;
+                }
+            }
         }
     }
 
@@ -744,6 +802,12 @@ impl MachineState {
                         None => unreachable!(),
                     }
                 }
+                TrailRef::ArgOffset(h, value_h) => {
+                    self.heap[h] = self.heap[value_h].context_free_clone();
+                }
+                TrailRef::NbArg(h) => {
+                    self.nb_arg_restores.push(h);
+                }
                 TrailRef::BlackboardEntry(key_h) => {
                     let key = atom_from!(
                         self,
@@ -759,6 +823,41 @@ impl MachineState {
         }
     }
 
+    // copies the values of nb_setarg/3 back to the heap, once the heap
+    // has been cut back to the choice point of a backtrack, for the
+    // arguments whose copies were reclaimed. the values of arguments
+    // that were reclaimed along with their terms are forgotten.
+    pub(super) fn restore_nb_args(&mut self) {
+        if self.nb_args.is_empty() {
+            self.nb_arg_restores.clear();
+            return;
+        }
+
+        let h = self.heap.h();
+        self.nb_args.split_off(&h);
+
+        self.nb_arg_restores.sort_unstable();
+        self.nb_arg_restores.dedup();
+
+        while let Some(arg_h) = self.nb_arg_restores.pop() {
+            match self.heap[arg_h] {
+                HeapCellValue::Addr(Addr::HeapCell(value_h)) if value_h >= h => {}
+                _ => continue,
+            }
+
+            self.heap.restore_displaced_constant(arg_h);
+
+            if let Some(ball) = self.nb_args.get(&arg_h) {
+                let value_h = self.heap.h();
+                let stub = ball.copy_and_align(value_h);
+
+                self.heap.extend(stub.into_iter());
+                self.heap[arg_h] = HeapCellValue::Addr(Addr::HeapCell(value_h));
+                self.trail(TrailRef::NbArg(arg_h));
+            }
+        }
+    }
+
     pub(super) fn match_partial_string(&mut self, addr: Addr, string: &String, has_tail: bool) {
         let mut heap_pstr_iter = self.heap_pstr_iter(addr);
 
@@ -861,6 +960,13 @@ impl MachineState {
                             true
                         }
                     }
+                    HeapCellValue::OpaqueString(ref s1) => {
+                        self.fail = if let Constant::OpaqueString(ref s2) = c {
+                            s1 != s2
+                        } else {
+                            true
+                        }
+                    }
                     HeapCellValue::PartialString(..) => {
                         if let Constant::String(ref s2) = c {
                             self.match_partial_string(Addr::PStrLocation(c1, 0), &s2, false);
@@ -915,28 +1021,26 @@ impl MachineState {
     }
 
     pub(super) fn execute_arith_instr(&mut self, instr: &ArithmeticInstruction) {
-        let stub = MachineError::functor_stub(clause_name!("is"), 2);
-
         match instr {
             &ArithmeticInstruction::Add(ref a1, ref a2, t) => {
                 let n1 = try_or_fail!(self, self.get_number(a1));
                 let n2 = try_or_fail!(self, self.get_number(a2));
 
-                self.interms[t - 1] = try_or_fail!(self, try_numeric_result!(self, n1 + n2, stub));
+                self.interms[t - 1] = try_or_fail!(self, self.add(n1, n2));
                 self.p += 1;
             }
             &ArithmeticInstruction::Sub(ref a1, ref a2, t) => {
                 let n1 = try_or_fail!(self, self.get_number(a1));
                 let n2 = try_or_fail!(self, self.get_number(a2));
 
-                self.interms[t - 1] = try_or_fail!(self, try_numeric_result!(self, n1 - n2, stub));
+                self.interms[t - 1] = try_or_fail!(self, self.sub(n1, n2));
                 self.p += 1;
             }
             &ArithmeticInstruction::Mul(ref a1, ref a2, t) => {
                 let n1 = try_or_fail!(self, self.get_number(a1));
                 let n2 = try_or_fail!(self, self.get_number(a2));
 
-                self.interms[t - 1] = try_or_fail!(self, try_numeric_result!(self, n1 * n2, stub));
+                self.interms[t - 1] = try_or_fail!(self, self.mul(n1, n2));
                 self.p += 1;
             }
             &ArithmeticInstruction::Max(ref a1, ref a2, t) => {
@@ -1181,6 +1285,147 @@ impl MachineState {
                 self.interms[t - 1] = n1;
                 self.p += 1;
             }
+            &ArithmeticInstruction::Cot(ref a1, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+
+                self.interms[t - 1] =
+                    Number::Float(OrderedFloat(try_or_fail!(self, self.cot(n1))));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::ACot(ref a1, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+
+                self.interms[t - 1] =
+                    Number::Float(OrderedFloat(try_or_fail!(self, self.acot(n1))));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::Sinh(ref a1, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+
+                self.interms[t - 1] =
+                    Number::Float(OrderedFloat(try_or_fail!(self, self.sinh(n1))));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::Cosh(ref a1, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+
+                self.interms[t - 1] =
+                    Number::Float(OrderedFloat(try_or_fail!(self, self.cosh(n1))));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::Tanh(ref a1, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+
+                self.interms[t - 1] =
+                    Number::Float(OrderedFloat(try_or_fail!(self, self.tanh(n1))));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::ASinh(ref a1, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+
+                self.interms[t - 1] =
+                    Number::Float(OrderedFloat(try_or_fail!(self, self.asinh(n1))));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::ACosh(ref a1, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+
+                self.interms[t - 1] =
+                    Number::Float(OrderedFloat(try_or_fail!(self, self.acosh(n1))));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::ATanh(ref a1, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+
+                self.interms[t - 1] =
+                    Number::Float(OrderedFloat(try_or_fail!(self, self.atanh(n1))));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::Log2(ref a1, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+
+                self.interms[t - 1] =
+                    Number::Float(OrderedFloat(try_or_fail!(self, self.log2(n1))));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::FloatIntegerPart(ref a1, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+
+                self.interms[t - 1] =
+                    Number::Float(OrderedFloat(try_or_fail!(self, self.float_integer_part(n1))));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::FloatFractionalPart(ref a1, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+
+                self.interms[t - 1] =
+                    Number::Float(OrderedFloat(try_or_fail!(self, self.float_fractional_part(n1))));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::Msb(ref a1, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+
+                self.interms[t - 1] = try_or_fail!(self, self.msb(n1));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::Lsb(ref a1, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+
+                self.interms[t - 1] = try_or_fail!(self, self.lsb(n1));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::PopCount(ref a1, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+
+                self.interms[t - 1] = try_or_fail!(self, self.popcount(n1));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::SuccOrZero(ref a1, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+
+                self.interms[t - 1] = try_or_fail!(self, self.succ_or_zero(n1));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::Random(ref a1, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+
+                self.interms[t - 1] = try_or_fail!(self, self.random(n1));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::LogBase(ref a1, ref a2, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+                let n2 = try_or_fail!(self, self.get_number(a2));
+
+                self.interms[t - 1] =
+                    Number::Float(OrderedFloat(try_or_fail!(self, self.log_base(n1, n2))));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::CopySign(ref a1, ref a2, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+                let n2 = try_or_fail!(self, self.get_number(a2));
+
+                self.interms[t - 1] =
+                    Number::Float(OrderedFloat(try_or_fail!(self, self.copysign(n1, n2))));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::NextToward(ref a1, ref a2, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+                let n2 = try_or_fail!(self, self.get_number(a2));
+
+                self.interms[t - 1] =
+                    Number::Float(OrderedFloat(try_or_fail!(self, self.nexttoward(n1, n2))));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::Integer(ref a1, t) => {
+                let n1 = try_or_fail!(self, self.get_number(a1));
+
+                self.interms[t - 1] = try_or_fail!(self, self.integer(n1));
+                self.p += 1;
+            }
+            &ArithmeticInstruction::Nullary(instr, t) => {
+                self.interms[t - 1] = self.nullary_value(instr);
+                self.p += 1;
+            }
         };
     }
 
@@ -1256,10 +1501,12 @@ impl MachineState {
                 self[norm] = self.registers[arg];
             }
             &FactInstruction::GetValue(norm, arg) => {
-                let norm_addr = self[norm];
-                let reg_addr = self.registers[arg];
-
-                (self.unify_fn)(self, norm_addr, reg_addr);
+                let unify_fn = self.unify_fn;
+                self.get_value(norm, arg, unify_fn);
+            }
+            &FactInstruction::GetValueWithOccursCheck(norm, arg, occurs_check) => {
+                let (unify_fn, _) = occurs_check_fns(occurs_check);
+                self.get_value(norm, arg, unify_fn);
             }
             &FactInstruction::UnifyConstant(ref c) => {
                 match self.mode {
@@ -1293,71 +1540,111 @@ impl MachineState {
                 };
             }
             &FactInstruction::UnifyLocalValue(reg) => {
+                let (unify_fn, bind_fn) = (self.unify_fn, self.bind_fn);
+                self.unify_local_value(reg, unify_fn, bind_fn);
+            }
+            &FactInstruction::UnifyLocalValueWithOccursCheck(reg, occurs_check) => {
+                let (unify_fn, bind_fn) = occurs_check_fns(occurs_check);
+                self.unify_local_value(reg, unify_fn, bind_fn);
+            }
+            &FactInstruction::UnifyValue(reg) => {
+                let (unify_fn, bind_fn) = (self.unify_fn, self.bind_fn);
+                self.unify_value(reg, unify_fn, bind_fn);
+            }
+            &FactInstruction::UnifyValueWithOccursCheck(reg, occurs_check) => {
+                let (unify_fn, bind_fn) = occurs_check_fns(occurs_check);
+                self.unify_value(reg, unify_fn, bind_fn);
+            }
+            &FactInstruction::UnifyVoid(n) => {
                 match self.mode {
                     MachineMode::Read => {
-                        let reg_addr = self[reg];
-
-                        (self.unify_fn)(self, reg_addr, self.s.read(&self.heap));
-                        self.increment_s_ptr(1);
+                        self.increment_s_ptr(n);
                     }
                     MachineMode::Write => {
-                        let addr = self.store(self.deref(self[reg]));
                         let h = self.heap.h();
 
-                        if let Addr::HeapCell(hc) = addr {
-                            let val = self.heap.clone(hc);
-
-                            self.heap.push(val);
-                            self.increment_s_ptr(1);
-
-                            return;
+                        for i in h..h + n {
+                            self.heap.push(HeapCellValue::Addr(Addr::HeapCell(i)));
                         }
-
-                        self.heap.push(HeapCellValue::Addr(Addr::HeapCell(h)));
-                        (self.bind_fn)(self, Ref::HeapCell(h), addr);
                     }
                 };
             }
-            &FactInstruction::UnifyValue(reg) => {
-                match self.mode {
-                    MachineMode::Read => {
-                        let reg_addr = self[reg];
+        };
+    }
 
-                        (self.unify_fn)(self, reg_addr, self.s.read(&self.heap));
-                        self.increment_s_ptr(1);
-                    }
-                    MachineMode::Write => {
-                        let h = self.heap.h();
-                        self.heap.push(HeapCellValue::Addr(Addr::HeapCell(h)));
+    fn get_value(
+        &mut self,
+        norm: RegType,
+        arg: usize,
+        unify_fn: fn(&mut MachineState, Addr, Addr),
+    ) {
+        let norm_addr = self[norm];
+        let reg_addr = self.registers[arg];
 
-                        let addr = self.store(self[reg]);
-                        (self.bind_fn)(self, Ref::HeapCell(h), addr);
+        unify_fn(self, norm_addr, reg_addr);
+    }
+
+    fn unify_local_value(
+        &mut self,
+        reg: RegType,
+        unify_fn: fn(&mut MachineState, Addr, Addr),
+        bind_fn: fn(&mut MachineState, Ref, Addr),
+    ) {
+        match self.mode {
+            MachineMode::Read => {
+                let reg_addr = self[reg];
+
+                unify_fn(self, reg_addr, self.s.read(&self.heap));
+                self.increment_s_ptr(1);
+            }
+            MachineMode::Write => {
+                let addr = self.store(self.deref(self[reg]));
+                let h = self.heap.h();
 
-                        // the former code of this match arm was:
+                if let Addr::HeapCell(hc) = addr {
+                    let val = self.heap.clone(hc);
 
-                        // let addr = self.store(self[reg]);
-                        // self.heap.push(HeapCellValue::Addr(addr));
+                    self.heap.push(val);
+                    self.increment_s_ptr(1);
 
-                        // the old code didn't perform the occurs
-                        // check when enabled and so it was changed to
-                        // the above, which is only slightly less
-                        // efficient when the occurs_check is disabled.
-                    }
-                };
+                    return;
+                }
+
+                self.heap.push(HeapCellValue::Addr(Addr::HeapCell(h)));
+                bind_fn(self, Ref::HeapCell(h), addr);
             }
-            &FactInstruction::UnifyVoid(n) => {
-                match self.mode {
-                    MachineMode::Read => {
-                        self.increment_s_ptr(n);
-                    }
-                    MachineMode::Write => {
-                        let h = self.heap.h();
+        };
+    }
 
-                        for i in h..h + n {
-                            self.heap.push(HeapCellValue::Addr(Addr::HeapCell(i)));
-                        }
-                    }
-                };
+    fn unify_value(
+        &mut self,
+        reg: RegType,
+        unify_fn: fn(&mut MachineState, Addr, Addr),
+        bind_fn: fn(&mut MachineState, Ref, Addr),
+    ) {
+        match self.mode {
+            MachineMode::Read => {
+                let reg_addr = self[reg];
+
+                unify_fn(self, reg_addr, self.s.read(&self.heap));
+                self.increment_s_ptr(1);
+            }
+            MachineMode::Write => {
+                let h = self.heap.h();
+                self.heap.push(HeapCellValue::Addr(Addr::HeapCell(h)));
+
+                let addr = self.store(self[reg]);
+                bind_fn(self, Ref::HeapCell(h), addr);
+
+                // the former code of this match arm was:
+
+                // let addr = self.store(self[reg]);
+                // self.heap.push(HeapCellValue::Addr(addr));
+
+                // the old code didn't perform the occurs
+                // check when enabled and so it was changed to
+                // the above, which is only slightly less
+                // efficient when the occurs_check is disabled.
             }
         };
     }
@@ -1664,6 +1951,16 @@ impl MachineState {
     pub(super) fn set_ball(&mut self) {
         self.ball.reset();
 
+        // a ball rethrown after '$keep_backtrace' keeps the backtrace
+        // it was originally thrown with.
+        if !std::mem::replace(&mut self.keep_backtrace, false) {
+            if self.flags.debug {
+                self.capture_backtrace();
+            } else {
+                self.backtrace.clear();
+            }
+        }
+
         let addr = self[temp_v!(1)];
         self.ball.boundary = self.heap.h();
 
@@ -2025,6 +2322,11 @@ impl MachineState {
                             return true;
                         }
                     }
+                    (&HeapCellValue::OpaqueString(ref s1), &HeapCellValue::OpaqueString(ref s2)) => {
+                        if s1 != s2 {
+                            return true;
+                        }
+                    }
                     (v1, v2) => {
                         if let Ok(n1) = Number::try_from(v1) {
                             if let Ok(n2) = Number::try_from(v2) {
@@ -2238,6 +2540,24 @@ impl MachineState {
                         return None;
                     }
                 },
+                Some(TermOrderCategory::String) => match (v1, v2) {
+                    (Addr::Con(h1), Addr::Con(h2)) => {
+                        if let HeapCellValue::OpaqueString(ref s1) = &self.heap[h1] {
+                            if let HeapCellValue::OpaqueString(ref s2) = &self.heap[h2] {
+                                if s1 != s2 {
+                                    return Some(s1.cmp(&s2));
+                                }
+                            } else {
+                                unreachable!()
+                            }
+                        } else {
+                            unreachable!()
+                        }
+                    }
+                    _ => {
+                        unreachable!()
+                    }
+                },
                 Some(TermOrderCategory::Compound) => match (v1, v2) {
                     (Addr::Lis(_), Addr::Lis(_)) => {}
                     (pstr1 @ Addr::PStrLocation(..), pstr2 @ Addr::PStrLocation(..)) => {
@@ -2968,6 +3288,11 @@ impl MachineState {
                         return true;
                     }
                 }
+                (HeapCellValue::OpaqueString(ref s1), HeapCellValue::OpaqueString(ref s2)) => {
+                    if s1 != s2 {
+                        return true;
+                    }
+                }
                 (v1, v2) => {
                     if let Ok(n1) = Number::try_from(v1) {
                         if let Ok(n2) = Number::try_from(v2) {
@@ -3054,6 +3379,105 @@ impl MachineState {
         self.throw_exception(err);
     }
 
+    fn module_predicate_location(
+        indices: &IndexStore,
+        module_name: &'static str,
+        name: &'static str,
+        arity: usize,
+    ) -> Option<usize> {
+        indices
+            .modules
+            .get(&clause_name!(module_name))
+            .and_then(|module| module.code_dir.get(&(clause_name!(name), arity)))
+            .and_then(|code_index| code_index.local())
+    }
+
+    // the location of the handler of a pending signal or alarm, if
+    // any. signals are handled by library(os), alarms by library(time).
+    fn pending_interrupt_handler(events: u32, indices: &IndexStore) -> Option<usize> {
+        if events & SIGNAL_EVENT != 0 && take_event(SIGNAL_EVENT) {
+            Self::module_predicate_location(indices, "os", "handle_signals", 0)
+        } else if events & ALARM_EVENT != 0 && take_event(ALARM_EVENT) {
+            Self::module_predicate_location(indices, "time", "fire_alarms", 0)
+        } else {
+            None
+        }
+    }
+
+    // the interrupt menu of the toplevel is offered only if there is
+    // a terminal to read the choice from. otherwise, an interrupt
+    // aborts the query.
+    fn interrupt_menu(indices: &IndexStore) -> Option<usize> {
+        if unsafe { libc::isatty(libc::STDIN_FILENO) } == 1 {
+            Self::module_predicate_location(indices, "$toplevel", "interrupt_menu", 1)
+        } else {
+            None
+        }
+    }
+
+    // calls to the predicates visible in user, its own and those it
+    // imports, are traced.
+    fn is_traced_call(indices: &IndexStore, ct: &ClauseType, arity: usize) -> bool {
+        let (name, code_index) = match ct {
+            &ClauseType::Named(ref name, _, ref code_index)
+            | &ClauseType::Op(ref name, _, ref code_index) => (name, code_index),
+            _ => return false,
+        };
+
+        match indices.code_dir.get(&(name.clone(), arity)) {
+            Some(user_index) => Rc::ptr_eq(&user_index.0, &code_index.0),
+            None => false,
+        }
+    }
+
+    // writes the goal of the pending call to a predicate to the heap.
+    fn call_goal(&mut self, ct: &ClauseType, arity: usize) -> Addr {
+        let (name, spec) = match *ct {
+            ClauseType::Named(ref name, ..) => (name.clone(), None),
+            ClauseType::Op(ref name, ref spec, _) => (name.clone(), Some(spec.clone())),
+            _ => unreachable!(),
+        };
+
+        let h = self.heap.h();
+
+        if arity == 0 {
+            self.heap.push(HeapCellValue::Atom(name, spec));
+            return Addr::Con(h);
+        }
+
+        self.heap.push(HeapCellValue::NamedStr(arity, name, spec));
+
+        for i in 1..arity + 1 {
+            let addr = self[RegType::Temp(i)];
+            self.heap.push(HeapCellValue::Addr(addr));
+        }
+
+        Addr::Str(h)
+    }
+
+    // runs the interrupt handler at p before the pending call of the
+    // given arity, saving the call's arguments in a frame from which
+    // '$return_from_verify_attr' restores them to retry the call.
+    fn interrupt_call(&mut self, p: usize, arity: usize) {
+        let cp = self.p.local();
+
+        self.allocate(arity + 2);
+
+        let e = self.e;
+        self.stack.index_and_frame_mut(e).prelude.interrupt_cp = cp;
+
+        for i in 1..arity + 1 {
+            self.stack.index_and_frame_mut(e)[i] = self[RegType::Temp(i)];
+        }
+
+        self.stack.index_and_frame_mut(e)[arity + 1] = Addr::CutPoint(self.b0);
+        self.stack.index_and_frame_mut(e)[arity + 2] = Addr::Usize(self.num_of_args);
+
+        self.num_of_args = 0;
+        self.b0 = self.b;
+        self.p = CodePtr::Local(LocalCodePtr::DirEntry(p));
+    }
+
     fn handle_call_clause(
         &mut self,
         indices: &mut IndexStore,
@@ -3067,21 +3491,10 @@ impl MachineState {
         lco: bool,
         use_default_cp: bool,
     ) {
-        let interrupted = INTERRUPT.load(std::sync::atomic::Ordering::Relaxed);
-
-        match INTERRUPT.compare_exchange(
-            interrupted,
-            false,
-            std::sync::atomic::Ordering::Relaxed,
-            std::sync::atomic::Ordering::Relaxed,
-        ) {
-            Ok(interruption) => {
-                if interruption {
-                    self.throw_interrupt_exception();
-                    return;
-                }
-            }
-            Err(_) => unreachable!(),
+        let events = PENDING_EVENTS.load(std::sync::atomic::Ordering::Relaxed);
+
+        if events != 0 && self.handle_pending_events(events, indices, ct, arity) {
+            return;
         }
 
         let mut default_call_policy: Box<dyn CallPolicy> = Box::new(DefaultCallPolicy {});
@@ -3092,6 +3505,10 @@ impl MachineState {
             call_policy
         };
 
+        if lco && self.flags.debug {
+            self.record_last_call();
+        }
+
         self.last_call = lco;
 
         match ct {
@@ -3147,6 +3564,90 @@ impl MachineState {
         self.last_call = false;
     }
 
+    // handles the pending events before a call, and returns whether
+    // the call was replaced by the call of a handler or an error.
+    fn handle_pending_events(
+        &mut self,
+        events: u32,
+        indices: &IndexStore,
+        ct: &ClauseType,
+        arity: usize,
+    ) -> bool {
+        // builtins may leave live values in registers beyond their
+        // arguments, so interrupts that resume the computation are only
+        // handled at calls to predicates.
+        let is_predicate_call = match ct {
+            &ClauseType::Named(..) | &ClauseType::Op(..) => true,
+            _ => false,
+        };
+
+        if events & INTERRUPT_EVENT != 0 {
+            match Self::interrupt_menu(indices) {
+                Some(p) if is_predicate_call => {
+                    take_event(INTERRUPT_EVENT);
+
+                    let backtrace = mem::take(&mut self.backtrace);
+
+                    self.capture_backtrace();
+                    self.interrupt_backtrace = mem::replace(&mut self.backtrace, backtrace);
+
+                    // the menu is not traced. it is passed whether
+                    // tracing was on, to resume it on continuing.
+                    let tracing = if take_event(TRACE_EVENT) {
+                        clause_name!("true")
+                    } else {
+                        clause_name!("false")
+                    };
+
+                    self.interrupt_call(p, arity);
+
+                    let h = self.heap.push(HeapCellValue::Atom(tracing, None));
+
+                    self.registers[1] = Addr::Con(h);
+                    self.num_of_args = 1;
+
+                    return true;
+                }
+                Some(_) => {}
+                None => {
+                    take_event(INTERRUPT_EVENT);
+                    self.throw_interrupt_exception();
+                    return true;
+                }
+            }
+        }
+
+        if is_predicate_call {
+            if let Some(p) = Self::pending_interrupt_handler(events, indices) {
+                self.interrupt_call(p, arity);
+                return true;
+            }
+        }
+
+        // tracing is suspended while the trace handler runs, which
+        // resumes it to retry the traced call without tracing it again.
+        if events & TRACE_EVENT != 0
+            && Self::is_traced_call(indices, ct, arity)
+            && !mem::replace(&mut self.trace_retry, false)
+        {
+            let handler = Self::module_predicate_location(indices, "$toplevel", "trace_call", 1);
+
+            if let Some(p) = handler {
+                let goal = self.call_goal(ct, arity);
+
+                self.interrupt_call(p, arity);
+
+                self.registers[1] = goal;
+                self.num_of_args = 1;
+                take_event(TRACE_EVENT);
+
+                return true;
+            }
+        }
+
+        false
+    }
+
     pub(super) fn execute_ctrl_instr(
         &mut self,
         indices: &mut IndexStore,
@@ -2,8 +2,6 @@ use prolog_parser::ast::*;
 use prolog_parser::tabled_rc::*;
 use prolog_parser::{clause_name, temp_v};
 
-use lazy_static::lazy_static;
-
 use crate::clause_types::*;
 use crate::forms::*;
 use crate::instructions::*;
@@ -12,7 +10,9 @@ use crate::machine::loader::*;
 use crate::machine::term_stream::{LiveTermStream, LoadStatePayload, TermStream};
 use crate::read::*;
 
+mod alarms;
 mod attributed_variables;
+mod backtrace;
 pub(super) mod code_repo;
 pub(crate) mod code_walker;
 #[macro_use]
@@ -26,7 +26,9 @@ pub(crate) mod machine_indices;
 pub(super) mod machine_state;
 pub(crate) mod partial_string;
 mod preprocessor;
+mod prng;
 mod raw_block;
+mod signals;
 mod stack;
 pub(crate) mod streams;
 mod term_stream;
@@ -37,6 +39,7 @@ mod arithmetic_ops;
 mod machine_state_impl;
 mod system_calls;
 
+use crate::machine::backtrace::predicate_at;
 use crate::machine::code_repo::*;
 use crate::machine::compile::*;
 use crate::machine::machine_errors::*;
@@ -51,7 +54,7 @@ use prolog_parser::ast::ClauseName;
 use std::fs::File;
 use std::mem;
 use std::path::PathBuf;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 #[derive(Debug)]
 pub(crate) struct MachinePolicies {
@@ -59,8 +62,26 @@ pub(crate) struct MachinePolicies {
     cut_policy: Box<dyn CutPolicy>,
 }
 
-lazy_static! {
-    pub static ref INTERRUPT: AtomicBool = AtomicBool::new(false);
+// the events the machine handles at its next call, as bits of one
+// word, so that calls check for all of them with a single load. the
+// SIGINT and signal handlers and the alarm thread only set bits.
+pub static PENDING_EVENTS: AtomicU32 = AtomicU32::new(0);
+
+pub const INTERRUPT_EVENT: u32 = 1;
+pub(crate) const SIGNAL_EVENT: u32 = 1 << 1;
+pub(crate) const ALARM_EVENT: u32 = 1 << 2;
+// set for as long as calls are traced.
+pub(crate) const TRACE_EVENT: u32 = 1 << 3;
+
+#[inline]
+pub fn raise_event(event: u32) {
+    PENDING_EVENTS.fetch_or(event, Ordering::Relaxed);
+}
+
+// clears event, and returns whether it was pending.
+#[inline]
+pub(crate) fn take_event(event: u32) -> bool {
+    PENDING_EVENTS.fetch_and(!event, Ordering::Relaxed) & event != 0
 }
 
 impl MachinePolicies {
@@ -368,6 +389,16 @@ impl Machine {
             .insert(clause_name!("user_output"), self.user_output.clone());
 
         self.indices.streams.insert(self.user_output.clone());
+
+        let mut user_error = Stream::stderr();
+
+        user_error.options_mut().alias = Some(clause_name!("user_error"));
+
+        self.indices
+            .stream_aliases
+            .insert(clause_name!("user_error"), user_error.clone());
+
+        self.indices.streams.insert(user_error);
     }
 
     fn throw_session_error(&mut self, err: SessionError, key: PredicateKey) {
@@ -592,14 +623,64 @@ impl MachineState {
             None => return,
         };
 
-        self.dispatch_instr(
-            instr.as_ref(),
-            indices,
-            policies,
-            code_repo,
-            user_input,
-            user_output,
-        );
+        if self.memory_limited {
+            let p = self.p.local();
+
+            self.dispatch_instr(
+                instr.as_ref(),
+                indices,
+                policies,
+                code_repo,
+                user_input,
+                user_output,
+            );
+
+            self.check_memory_limits(instr.as_ref(), p, indices);
+        } else {
+            self.dispatch_instr(
+                instr.as_ref(),
+                indices,
+                policies,
+                code_repo,
+                user_input,
+                user_output,
+            );
+        }
+    }
+
+    // throws resource_error(memory) if instr grew the heap or the stack
+    // past its limit, as noted when they allocated, or the trail past
+    // its limit. The error names the predicate instr calls, or else
+    // the predicate of its clause.
+    fn check_memory_limits(&mut self, instr: &Line, p: LocalCodePtr, indices: &IndexStore) {
+        let heap_exhausted = self.heap.take_exhausted();
+        let stack_exhausted = self.stack.take_exhausted();
+        let trail_exhausted = self.trail_bytes_used() > self.trail_limit;
+
+        // a failure or another error unwinds what was allocated.
+        if self.fail || !(heap_exhausted || stack_exhausted || trail_exhausted) {
+            return;
+        }
+
+        let stub = match instr {
+            &Line::Control(ControlInstruction::CallClause(ref ct, arity, ..)) => {
+                MachineError::functor_stub(ct.name(), arity)
+            }
+            _ => match p {
+                LocalCodePtr::DirEntry(p) | LocalCodePtr::IndexingBuf(p, ..) => {
+                    match predicate_at(indices, p) {
+                        Some((name, arity)) => MachineError::functor_stub(name, arity),
+                        None => functor!("repl"),
+                    }
+                }
+                LocalCodePtr::Halt => functor!("repl"),
+            },
+        };
+
+        let err = MachineError::resource_error("memory");
+        let err = self.error_form(err, stub);
+
+        self.throw_exception(err);
     }
 
     fn backtrack(&mut self) {
@@ -0,0 +1,116 @@
+use crate::rug::Integer;
+
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// xoshiro256** seeded by splitmix64, as recommended by its authors.
+// the state is kept in a Cell so numbers can be drawn during
+// arithmetic evaluation, which borrows the machine immutably.
+#[derive(Debug)]
+pub(crate) struct Prng {
+    state: Cell<[u64; 4]>,
+}
+
+fn splitmix64(seed: &mut u64) -> u64 {
+    *seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+    let mut z = *seed;
+
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+    z ^ (z >> 31)
+}
+
+impl Prng {
+    pub(crate) fn from_seed(mut seed: u64) -> Self {
+        let state = [
+            splitmix64(&mut seed),
+            splitmix64(&mut seed),
+            splitmix64(&mut seed),
+            splitmix64(&mut seed),
+        ];
+
+        Prng {
+            state: Cell::new(state),
+        }
+    }
+
+    pub(crate) fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0);
+
+        Prng::from_seed(seed)
+    }
+
+    #[inline]
+    pub(crate) fn seed(&self, seed: u64) {
+        self.state.set(Prng::from_seed(seed).state.get());
+    }
+
+    #[inline]
+    pub(crate) fn state(&self) -> [u64; 4] {
+        self.state.get()
+    }
+
+    // the all-zero state is a fixed point of the generator, and is
+    // refused.
+    pub(crate) fn set_state(&self, state: [u64; 4]) -> bool {
+        if state == [0; 4] {
+            false
+        } else {
+            self.state.set(state);
+            true
+        }
+    }
+
+    pub(crate) fn next_u64(&self) -> u64 {
+        let mut s = self.state.get();
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+
+        self.state.set(s);
+        result
+    }
+
+    // a float uniformly distributed in the open interval (0, 1).
+    pub(crate) fn next_f64(&self) -> f64 {
+        ((self.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+    }
+
+    // an integer uniformly distributed in [0, n), for positive n.
+    // candidates with as many bits as n are drawn until one falls
+    // below n, which takes fewer than two draws on average.
+    pub(crate) fn below(&self, n: &Integer) -> Integer {
+        let bits = n.significant_bits();
+
+        loop {
+            let mut candidate = Integer::from(0);
+            let mut remaining = bits;
+
+            while remaining >= 64 {
+                candidate = (candidate << 64) | Integer::from(self.next_u64());
+                remaining -= 64;
+            }
+
+            if remaining > 0 {
+                let word = self.next_u64() >> (64 - remaining);
+                candidate = (candidate << remaining) | Integer::from(word);
+            }
+
+            if &candidate < n {
+                return candidate;
+            }
+        }
+    }
+}
@@ -19,6 +19,8 @@ pub(crate) struct RawBlock<T: RawBlockTraits> {
     pub(crate) size: usize,
     pub(crate) base: *const u8,
     pub(crate) top: *const u8,
+    pub(crate) limit: usize,
+    pub(crate) exhausted: bool,
     _marker: PhantomData<T>,
 }
 
@@ -28,6 +30,8 @@ impl<T: RawBlockTraits> RawBlock<T> {
         let mut block = RawBlock { size: 0,
                                    base: ptr::null(),
                                    top: ptr::null(),
+                                   limit: usize::max_value(),
+                                   exhausted: false,
                                    _marker: PhantomData };
 
         unsafe {
@@ -64,6 +68,8 @@ impl<T: RawBlockTraits> RawBlock<T> {
         RawBlock { size: 0,
                  base: ptr::null(),
                  top: ptr::null(),
+                 limit: usize::max_value(),
+                 exhausted: false,
                  _marker: PhantomData }
     }
 
@@ -73,6 +79,22 @@ impl<T: RawBlockTraits> RawBlock<T> {
         mem::replace(self, Self::empty_block())
     }
 
+    // the number of bytes in use, measured from the base of the block.
+    #[inline]
+    pub(crate)
+    fn bytes_used(&self) -> usize {
+        self.top as usize - self.base as usize
+    }
+
+    // allocations past the limit succeed, but mark the block as
+    // exhausted, so that the machine throws a resource_error once the
+    // instruction that allocated is done.
+    #[inline]
+    pub(crate)
+    fn take_exhausted(&mut self) -> bool {
+        mem::replace(&mut self.exhausted, false)
+    }
+
     #[inline]
     fn free_space(&self) -> usize {
         debug_assert!(self.top >= self.base,
@@ -85,6 +107,10 @@ impl<T: RawBlockTraits> RawBlock<T> {
     #[inline]
     pub(crate)
     unsafe fn new_block(&mut self, size: usize) -> *const u8 {
+        if self.bytes_used() + size > self.limit {
+            self.exhausted = true;
+        }
+
         loop {
             if self.free_space() >= size {
                 return (self.top as usize + size) as *const _;
@@ -0,0 +1,54 @@
+use nix::sys::signal::{self, SigHandler, Signal};
+
+use crate::machine::{raise_event, SIGNAL_EVENT};
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// the signals that Prolog code may handle with on_signal/3, by name.
+const SIGNALS: [(&str, Signal); 5] = [
+    ("hup", Signal::SIGHUP),
+    ("term", Signal::SIGTERM),
+    ("usr1", Signal::SIGUSR1),
+    ("usr2", Signal::SIGUSR2),
+    ("pipe", Signal::SIGPIPE),
+];
+
+// a bit for each signal received but not yet handled. the signal
+// handler only sets bits, as it may run at any point; the machine
+// runs the Prolog handlers at its next call.
+static PENDING_SIGNALS: AtomicU32 = AtomicU32::new(0);
+
+extern "C" fn handle_signal(signo: libc::c_int) {
+    PENDING_SIGNALS.fetch_or(1 << signo, Ordering::SeqCst);
+    raise_event(SIGNAL_EVENT);
+}
+
+// installs the action for the named signal, which is one of
+// "default", "ignore" or "handle". returns false if the signal is
+// unknown or the action cannot be installed.
+pub(crate) fn set_signal_action(name: &str, action: &str) -> bool {
+    let signal = match SIGNALS.iter().find(|(signal_name, _)| *signal_name == name) {
+        Some(&(_, signal)) => signal,
+        None => return false,
+    };
+
+    let handler = match action {
+        "default" => SigHandler::SigDfl,
+        "ignore" => SigHandler::SigIgn,
+        "handle" => SigHandler::Handler(handle_signal),
+        _ => return false,
+    };
+
+    unsafe { signal::signal(signal, handler) }.is_ok()
+}
+
+// the names of the signals received since the last call.
+pub(crate) fn take_pending_signals() -> Vec<&'static str> {
+    let pending = PENDING_SIGNALS.swap(0, Ordering::SeqCst);
+
+    SIGNALS
+        .iter()
+        .filter(|&&(_, signal)| pending & (1 << signal as u32) != 0)
+        .map(|&(name, _)| name)
+        .collect()
+}
@@ -215,6 +215,27 @@ impl Stack {
         }
     }
 
+    // the number of bytes occupied by frames.
+    #[inline]
+    pub(crate) fn bytes_used(&self) -> usize {
+        self.buf.top as usize - self.buf.base as usize
+    }
+
+    #[inline]
+    pub(crate) fn limit(&self) -> usize {
+        self.buf.limit
+    }
+
+    #[inline]
+    pub(crate) fn set_limit(&mut self, limit: usize) {
+        self.buf.limit = limit;
+    }
+
+    #[inline]
+    pub(crate) fn take_exhausted(&mut self) -> bool {
+        self.buf.take_exhausted()
+    }
+
     #[inline]
     pub(crate) fn index_and_frame(&self, e: usize) -> &AndFrame {
         unsafe {
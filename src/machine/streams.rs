@@ -14,7 +14,7 @@ use std::fmt;
 use std::fs::File;
 use std::hash::{Hash, Hasher};
 use std::io;
-use std::io::{stdout, Cursor, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::io::{stderr, stdout, Cursor, ErrorKind, Read, Seek, SeekFrom, Write};
 use std::mem;
 use std::net::{Shutdown, TcpStream};
 use std::ops::DerefMut;
@@ -114,12 +114,24 @@ enum StreamInstance {
     PausedPrologStream(Vec<u8>, Box<StreamInstance>),
     ReadlineStream(ReadlineStream),
     StaticStr(Cursor<&'static str>),
+    Stderr,
     Stdout,
     TcpStream(ClauseName, TcpStream),
     TlsStream(ClauseName, TlsStream<TcpStream>),
 }
 
 impl StreamInstance {
+    // a file stream that was read from is paused, but keeps its name.
+    fn file_name(&self) -> Option<ClauseName> {
+        match self {
+            StreamInstance::InputFile(ref name, _) => Some(name.clone()),
+            StreamInstance::OutputFile(ref name, ..) => Some(name.clone()),
+            StreamInstance::TcpStream(ref name, _) => Some(name.clone()),
+            StreamInstance::PausedPrologStream(_, ref stream) => stream.file_name(),
+            _ => None,
+        }
+    }
+
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         match self {
             StreamInstance::PausedPrologStream(ref mut put_back, ref mut stream) => {
@@ -148,12 +160,13 @@ impl StreamInstance {
             StreamInstance::ReadlineStream(ref mut rl_stream) => rl_stream.read(buf),
             StreamInstance::StaticStr(ref mut src) => src.read(buf),
             StreamInstance::Bytes(ref mut cursor) => cursor.read(buf),
-            StreamInstance::OutputFile(..) | StreamInstance::Stdout | StreamInstance::Null => {
-                Err(std::io::Error::new(
-                    ErrorKind::PermissionDenied,
-                    StreamError::ReadFromOutputStream,
-                ))
-            }
+            StreamInstance::OutputFile(..)
+            | StreamInstance::Stderr
+            | StreamInstance::Stdout
+            | StreamInstance::Null => Err(std::io::Error::new(
+                ErrorKind::PermissionDenied,
+                StreamError::ReadFromOutputStream,
+            )),
         }
     }
 }
@@ -186,6 +199,7 @@ impl fmt::Debug for StreamInstance {
             &StreamInstance::ReadlineStream(ref readline_stream) => {
                 write!(fmt, "ReadlineStream({:?})", readline_stream)
             }
+            &StreamInstance::Stderr => write!(fmt, "Stderr"),
             &StreamInstance::Stdout => write!(fmt, "Stdout"),
             &StreamInstance::TcpStream(_, ref tcp_stream) => {
                 write!(fmt, "TcpStream({:?})", tcp_stream)
@@ -203,6 +217,7 @@ pub(crate) struct InnerStream {
     stream_inst: StreamInstance,
     past_end_of_stream: bool,
     lines_read: usize,
+    line_position: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -216,6 +231,7 @@ impl WrappedStreamInstance {
             stream_inst,
             past_end_of_stream,
             lines_read: 0,
+            line_position: 0,
         })))
     }
 }
@@ -401,6 +417,24 @@ impl Stream {
         self.stream_inst.0.borrow_mut().lines_read += incr_num_lines_read;
     }
 
+    #[inline]
+    pub(crate) fn line_position(&self) -> usize {
+        self.stream_inst.0.borrow().line_position
+    }
+
+    // the column is relative to the start of the read unless a
+    // newline was consumed along the way.
+    #[inline]
+    pub(crate) fn advance_line_position(&mut self, incr_num_lines_read: usize, col_num: usize) {
+        let mut inner_stream = self.stream_inst.0.borrow_mut();
+
+        if incr_num_lines_read > 0 {
+            inner_stream.line_position = col_num;
+        } else {
+            inner_stream.line_position += col_num;
+        }
+    }
+
     #[inline]
     pub(crate) fn options(&self) -> std::cell::Ref<'_, StreamOptions> {
         std::cell::Ref::map(self.stream_inst.0.borrow(), |inner_stream| {
@@ -503,12 +537,7 @@ impl Stream {
 
     #[inline]
     pub(crate) fn file_name(&self) -> Option<ClauseName> {
-        match self.stream_inst.0.borrow().stream_inst {
-            StreamInstance::InputFile(ref name, _) => Some(name.clone()),
-            StreamInstance::OutputFile(ref name, ..) => Some(name.clone()),
-            StreamInstance::TcpStream(ref name, _) => Some(name.clone()),
-            _ => None,
-        }
+        self.stream_inst.0.borrow().stream_inst.file_name()
     }
 
     #[inline]
@@ -521,7 +550,9 @@ impl Stream {
             | StreamInstance::InputFile(..) => "read",
             StreamInstance::TcpStream(..) | StreamInstance::TlsStream(..) => "read_append",
             StreamInstance::OutputFile(_, _, true) => "append",
-            StreamInstance::Stdout | StreamInstance::OutputFile(_, _, false) => "write",
+            StreamInstance::Stderr
+            | StreamInstance::Stdout
+            | StreamInstance::OutputFile(_, _, false) => "write",
             StreamInstance::Null => "",
         }
     }
@@ -538,6 +569,11 @@ impl Stream {
         Stream::from_inst(StreamInstance::Stdout)
     }
 
+    #[inline]
+    pub fn stderr() -> Self {
+        Stream::from_inst(StreamInstance::Stderr)
+    }
+
     #[inline]
     pub(crate) fn from_tcp_stream(address: ClauseName, tcp_stream: TcpStream) -> Self {
         tcp_stream.set_read_timeout(None).unwrap();
@@ -569,6 +605,22 @@ impl Stream {
         }
     }
 
+    #[inline]
+    pub(crate) fn is_stderr(&self) -> bool {
+        match self.stream_inst.0.borrow().stream_inst {
+            StreamInstance::Stderr => true,
+            _ => false,
+        }
+    }
+
+    // the query history of the toplevel, kept by its readline stream.
+    pub(crate) fn query_history(&self) -> Vec<String> {
+        match self.stream_inst.0.borrow().stream_inst {
+            StreamInstance::ReadlineStream(ref rl_stream) => rl_stream.history(),
+            _ => vec![],
+        }
+    }
+
     #[inline]
     pub(crate) fn is_stdin(&self) -> bool {
         match self.stream_inst.0.borrow().stream_inst {
@@ -608,7 +660,8 @@ impl Stream {
     #[inline]
     pub(crate) fn is_output_stream(&self) -> bool {
         match self.stream_inst.0.borrow().stream_inst {
-            StreamInstance::Stdout
+            StreamInstance::Stderr
+            | StreamInstance::Stdout
             | StreamInstance::TcpStream(..)
             | StreamInstance::TlsStream(..)
             | StreamInstance::Bytes(_)
@@ -636,6 +689,7 @@ impl Stream {
     #[inline]
     pub(super) fn reset(&mut self) -> bool {
         self.stream_inst.0.borrow_mut().lines_read = 0;
+        self.stream_inst.0.borrow_mut().line_position = 0;
         self.stream_inst.0.borrow_mut().past_end_of_stream = false;
 
         loop {
@@ -1102,13 +1156,27 @@ impl Read for Stream {
     }
 }
 
+// the column following buf, given the column before it. columns count
+// characters, not bytes.
+fn line_position_after(line_position: usize, buf: &[u8]) -> usize {
+    let (line_position, rest) = match buf.iter().rposition(|&b| b == b'\n') {
+        Some(i) => (0, &buf[i + 1..]),
+        None => (line_position, buf),
+    };
+
+    line_position + rest.iter().filter(|&&b| b & 0xc0 != 0x80).count()
+}
+
 impl Write for Stream {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        match self.stream_inst.0.borrow_mut().stream_inst {
+        let mut inner_stream = self.stream_inst.0.borrow_mut();
+
+        let result = match inner_stream.stream_inst {
             StreamInstance::OutputFile(_, ref mut file, _) => file.write(buf),
             StreamInstance::TcpStream(_, ref mut tcp_stream) => tcp_stream.write(buf),
             StreamInstance::TlsStream(_, ref mut tls_stream) => tls_stream.write(buf),
             StreamInstance::Bytes(ref mut cursor) => cursor.write(buf),
+            StreamInstance::Stderr => stderr().write(buf),
             StreamInstance::Stdout => stdout().write(buf),
             StreamInstance::PausedPrologStream(..)
             | StreamInstance::StaticStr(_)
@@ -1118,7 +1186,13 @@ impl Write for Stream {
                 ErrorKind::PermissionDenied,
                 StreamError::WriteToInputStream,
             )),
+        };
+
+        if let Ok(n) = result {
+            inner_stream.line_position = line_position_after(inner_stream.line_position, &buf[..n]);
         }
+
+        result
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
@@ -1127,6 +1201,7 @@ impl Write for Stream {
             StreamInstance::TcpStream(_, ref mut tcp_stream) => tcp_stream.flush(),
             StreamInstance::TlsStream(_, ref mut tls_stream) => tls_stream.flush(),
             StreamInstance::Bytes(ref mut cursor) => cursor.flush(),
+            StreamInstance::Stderr => stderr().flush(),
             StreamInstance::Stdout => stdout().flush(),
             StreamInstance::PausedPrologStream(..)
             | StreamInstance::StaticStr(_)
@@ -9,11 +9,13 @@ use prolog_parser::{
 
 use lazy_static::lazy_static;
 
+use crate::arithmetic::*;
 use crate::clause_types::*;
 use crate::forms::*;
 use crate::heap_print::*;
 use crate::instructions::*;
 use crate::machine;
+use crate::machine::alarms::*;
 use crate::machine::code_repo::CodeRepo;
 use crate::machine::code_walker::*;
 use crate::machine::copier::*;
@@ -21,10 +23,12 @@ use crate::machine::machine_errors::*;
 use crate::machine::machine_indices::*;
 use crate::machine::machine_state::*;
 use crate::machine::preprocessor::to_op_decl;
+use crate::machine::signals::*;
 use crate::machine::streams::*;
+use crate::machine::{raise_event, take_event, PENDING_EVENTS, TRACE_EVENT};
 
-use crate::read::readline;
-use crate::rug::Integer;
+use crate::read::{readline, PrologStream};
+use crate::rug::{Integer, Rational};
 use ordered_float::OrderedFloat;
 
 use indexmap::IndexSet;
@@ -37,14 +41,15 @@ use std::env;
 use std::fs;
 use std::io::{ErrorKind, Read, Write};
 use std::iter::{once, FromIterator};
+use std::mem;
 use std::net::{TcpListener, TcpStream};
 use std::num::NonZeroU32;
 use std::ops::Sub;
 use std::rc::Rc;
 
-use chrono::{offset::Local, DateTime};
+use chrono::{offset::Local, DateTime, Datelike, NaiveDate, NaiveDateTime, TimeZone};
 use cpu_time::ProcessTime;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyModifiers};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
@@ -176,6 +181,38 @@ impl MachineState {
         }
     }
 
+    // partial string segments are acyclic, so the hare can be moved
+    // over all but the last of the characters left in a segment at
+    // once, rather than a cell at a time. the last character is left
+    // to brents_alg_step, which follows the tail of the segment.
+    fn skip_pstr_segment(&self, brent_st: &mut BrentAlgState, max_steps: usize) {
+        if let Addr::PStrLocation(h, n) = self.store(self.deref(brent_st.hare)) {
+            if let HeapCellValue::PartialString(ref pstr, _) = &self.heap[h] {
+                let max_skip = max_steps - brent_st.steps - 1;
+                let mut chars = pstr.range_from(n..).peekable();
+                let mut offset = n;
+                let mut skipped = 0;
+
+                while skipped < max_skip {
+                    match chars.next() {
+                        Some(c) if chars.peek().is_some() => {
+                            offset += c.len_utf8();
+                            skipped += 1;
+                        }
+                        _ => break,
+                    }
+                }
+
+                if skipped > 0 {
+                    brent_st.hare = Addr::PStrLocation(h, offset);
+                    brent_st.tortoise = brent_st.hare;
+                    brent_st.steps += skipped;
+                    brent_st.power = 2 * brent_st.steps;
+                }
+            }
+        }
+    }
+
     pub(super) fn detect_cycles_with_max(&self, max_steps: usize, addr: Addr) -> CycleSearchResult {
         let hare = match self.store(self.deref(addr)) {
             Addr::Lis(offset) if max_steps > 0 => Addr::Lis(offset),
@@ -215,6 +252,8 @@ impl MachineState {
                 return brent_st.to_result();
             }
 
+            self.skip_pstr_segment(&mut brent_st, max_steps);
+
             if let Some(result) = self.brents_alg_step(&mut brent_st) {
                 return result;
             }
@@ -244,6 +283,8 @@ impl MachineState {
         let mut brent_st = BrentAlgState::new(hare);
 
         loop {
+            self.skip_pstr_segment(&mut brent_st, usize::MAX);
+
             if let Some(result) = self.brents_alg_step(&mut brent_st) {
                 return result;
             }
@@ -701,6 +742,36 @@ impl MachineState {
         }
     }
 
+    // the heap location of argument N of Term, for setarg/3 and
+    // nb_setarg/3, or None if Term has no argument N.
+    fn setarg_location(&mut self, name: &'static str) -> Result<Option<usize>, MachineStub> {
+        let n = match Number::try_from((self[temp_v!(1)], &self.heap)) {
+            Ok(Number::Fixnum(n)) => usize::try_from(n).ok(),
+            Ok(Number::Integer(n)) => n.to_usize(),
+            _ => unreachable!(),
+        };
+
+        match (n, self.store(self.deref(self[temp_v!(2)]))) {
+            (Some(n), Addr::Str(s)) => match &self.heap[s] {
+                &HeapCellValue::NamedStr(arity, ..) if 1 <= n && n <= arity => Ok(Some(s + n)),
+                _ => Ok(None),
+            },
+            (Some(n), Addr::Lis(l)) if 1 <= n && n <= 2 => Ok(Some(l + n - 1)),
+            (_, addr @ Addr::PStrLocation(..)) => {
+                let stub = MachineError::functor_stub(clause_name!(name), 3);
+                let err = MachineError::permission_error(
+                    self.heap.h(),
+                    Permission::Modify,
+                    "partial_string",
+                    addr,
+                );
+
+                Err(self.error_form(err, stub))
+            }
+            _ => Ok(None),
+        }
+    }
+
     pub(super) fn system_call(
         &mut self,
         ct: &SystemClauseType,
@@ -1189,6 +1260,25 @@ impl MachineState {
 
                 return Ok(());
             }
+            &SystemClauseType::CharsOutputStreamContents => {
+                let addr = self.store(self.deref(self[temp_v!(1)]));
+                let stream =
+                    self.get_stream_or_alias(addr, indices, "$chars_output_stream_contents", 2)?;
+
+                let string = match Stream::bytes(&stream) {
+                    Some(bytes) => String::from_utf8_lossy(&bytes).to_string(),
+                    None => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                };
+
+                let chars = string.chars().map(|c| Addr::Char(c));
+                let char_list = Addr::HeapCell(self.heap.to_list(chars));
+
+                let a2 = self[temp_v!(2)];
+                (self.unify_fn)(self, char_list, a2);
+            }
             &SystemClauseType::CharsToNumber => {
                 let stub = MachineError::functor_stub(clause_name!("number_chars"), 2);
 
@@ -1291,6 +1381,133 @@ impl MachineState {
                     }
                 }
             }
+            &SystemClauseType::PStrAffix => {
+                let kind = self.atom_argument_to_string(1);
+                let affix = self.text_to_string(self[temp_v!(3)]);
+
+                self.fail = match (self.pstr_prefix(self[temp_v!(2)]), affix) {
+                    (Some((text, _)), Some(affix)) => match kind.as_str() {
+                        "prefix" => !text.starts_with(affix.as_str()),
+                        "suffix" => !text.ends_with(affix.as_str()),
+                        _ => true,
+                    },
+                    _ => true,
+                };
+            }
+            &SystemClauseType::PStrAppend => {
+                let text = match self.pstr_prefix(self[temp_v!(1)]) {
+                    Some((text, Addr::EmptyList)) => text.into_owned(),
+                    _ => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                };
+
+                if text.is_empty() {
+                    (self.unify_fn)(self, self[temp_v!(2)], self[temp_v!(3)]);
+                    return return_from_clause!(self.last_call, self);
+                }
+
+                let pstr = self.heap.allocate_pstr(&text);
+                let h = self.heap.h();
+                let pstr_tail = self.heap[h - 1].as_addr(h - 1);
+
+                (self.unify_fn)(self, pstr_tail, self[temp_v!(2)]);
+
+                if !self.fail {
+                    (self.unify_fn)(self, pstr, self[temp_v!(3)]);
+                }
+            }
+            &SystemClauseType::PStrAtom => {
+                let atom = match self.pstr_prefix(self[temp_v!(1)]) {
+                    Some((text, Addr::EmptyList)) => {
+                        if text == "[]" {
+                            Addr::EmptyList
+                        } else {
+                            let name = clause_name!(text.into_owned(), self.atom_tbl);
+                            self.heap.to_unifiable(HeapCellValue::Atom(name, None))
+                        }
+                    }
+                    _ => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                };
+
+                (self.unify_fn)(self, atom, self[temp_v!(2)]);
+            }
+            &SystemClauseType::PStrLength => {
+                let length = match self.pstr_prefix(self[temp_v!(1)]) {
+                    Some((text, _)) => text.chars().count(),
+                    None => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                };
+
+                (self.unify_fn)(self, Addr::Usize(length), self[temp_v!(2)]);
+            }
+            &SystemClauseType::PStrSearch => {
+                let from = self.store(self.deref(self[temp_v!(3)]));
+
+                let from = match Number::try_from((from, &self.heap)) {
+                    Ok(Number::Fixnum(n)) => usize::try_from(n).ok(),
+                    Ok(Number::Integer(n)) => n.to_usize(),
+                    _ => None,
+                };
+
+                let index = match (
+                    self.pstr_prefix(self[temp_v!(1)]),
+                    self.text_to_string(self[temp_v!(2)]),
+                    from,
+                ) {
+                    (Some((text, _)), Some(sub), Some(from)) => {
+                        let offset = text
+                            .char_indices()
+                            .map(|(i, _)| i)
+                            .chain(once(text.len()))
+                            .nth(from);
+
+                        offset.and_then(|offset| {
+                            text[offset..]
+                                .find(sub.as_str())
+                                .map(|i| from + text[offset..offset + i].chars().count())
+                        })
+                    }
+                    _ => None,
+                };
+
+                match index {
+                    Some(index) => {
+                        (self.unify_fn)(self, Addr::Usize(index), self[temp_v!(4)]);
+                    }
+                    None => {
+                        self.fail = true;
+                    }
+                }
+            }
+            &SystemClauseType::PStrSplit => {
+                let fields: Vec<String> = match (
+                    self.pstr_prefix(self[temp_v!(1)]),
+                    self.text_to_string(self[temp_v!(2)]),
+                ) {
+                    (Some((text, _)), Some(sep)) if !sep.is_empty() => {
+                        text.split(sep.as_str()).map(String::from).collect()
+                    }
+                    _ => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                };
+
+                let fields: Vec<_> = fields
+                    .into_iter()
+                    .map(|field| self.heap.put_complete_string(&field))
+                    .collect();
+
+                let list = Addr::HeapCell(self.heap.to_list(fields.into_iter()));
+                (self.unify_fn)(self, list, self[temp_v!(3)]);
+            }
             &SystemClauseType::PeekByte => {
                 let mut stream =
                     self.get_stream_or_alias(self[temp_v!(1)], indices, "peek_byte", 2)?;
@@ -1611,9 +1828,7 @@ impl MachineState {
                 let n = self.store(self.deref(n));
 
                 let string = match Number::try_from((n, &self.heap)) {
-                    Ok(Number::Float(OrderedFloat(n))) => {
-                        format!("{0:<20?}", n)
-                    }
+                    Ok(Number::Float(OrderedFloat(n))) => float_str(n, FloatFormat::Shortest),
                     Ok(Number::Fixnum(n)) => n.to_string(),
                     Ok(Number::Integer(n)) => n.to_string(),
                     Ok(Number::Rational(r)) => {
@@ -1637,9 +1852,7 @@ impl MachineState {
                 let chs = self[temp_v!(2)];
 
                 let string = match Number::try_from((n, &self.heap)) {
-                    Ok(Number::Float(OrderedFloat(n))) => {
-                        format!("{0:<20?}", n)
-                    }
+                    Ok(Number::Float(OrderedFloat(n))) => float_str(n, FloatFormat::Shortest),
                     Ok(Number::Fixnum(n)) => n.to_string(),
                     Ok(Number::Integer(n)) => n.to_string(),
                     Ok(Number::Rational(r)) => {
@@ -1666,7 +1879,7 @@ impl MachineState {
                     Err(e) => {
                         return Err(e);
                     }
-                    Ok(addrs) => match self.try_char_list(addrs) {
+                    Ok(addrs) => match self.try_code_list(addrs) {
                         Ok(chars) => {
                             let stub = MachineError::functor_stub(clause_name!("number_codes"), 2);
                             self.parse_number_from_string(chars, indices, stub)?;
@@ -1834,6 +2047,16 @@ impl MachineState {
             &SystemClauseType::CopyTermWithoutAttrVars => {
                 self.copy_term(AttrVarPolicy::StripAttributes);
             }
+            &SystemClauseType::GlobalVarKeys => {
+                let keys: Vec<_> = indices
+                    .global_variables
+                    .keys()
+                    .map(|key| HeapCellValue::Atom(key.clone(), None))
+                    .collect();
+
+                let keys = self.heap.to_list(keys.into_iter());
+                (self.unify_fn)(self, self[temp_v!(1)], Addr::HeapCell(keys));
+            }
             &SystemClauseType::FetchGlobalVar => {
                 let (key_h, key) = match self.store(self.deref(self[temp_v!(1)])) {
                     Addr::Con(h) if self.heap.atom_at(h) => {
@@ -2544,28 +2767,45 @@ impl MachineState {
                 stream.flush().unwrap();
             }
             &SystemClauseType::GetSingleChar => {
-                let ctrl_c = KeyEvent {
-                    code: KeyCode::Char('c'),
-                    modifiers: KeyModifiers::CONTROL,
-                };
-                let key = get_key();
-                if key == ctrl_c {
-                    let stub = MachineError::functor_stub(clause_name!("get_single_char"), 1);
-                    let err = MachineError::interrupt_error();
-                    let err = self.error_form(err, stub);
+                // without a terminal to read keys from, the next
+                // character of the current input is read.
+                let c = if unsafe { libc::isatty(libc::STDIN_FILENO) } != 1 {
+                    let mut iter = self.open_parsing_stream(
+                        current_input_stream.clone(),
+                        "get_single_char",
+                        1,
+                    )?;
+
+                    match iter.next() {
+                        Some(Ok(c)) => Addr::Char(c),
+                        _ => self
+                            .heap
+                            .to_unifiable(HeapCellValue::Atom(clause_name!("end_of_file"), None)),
+                    }
+                } else {
+                    let ctrl_c = KeyEvent {
+                        code: KeyCode::Char('c'),
+                        modifiers: KeyModifiers::CONTROL,
+                    };
+                    let key = get_key();
+                    if key == ctrl_c {
+                        let stub = MachineError::functor_stub(clause_name!("get_single_char"), 1);
+                        let err = MachineError::interrupt_error();
+                        let err = self.error_form(err, stub);
 
-                    return Err(err);
-                }
-                let c = match key.code {
-                    KeyCode::Enter => '\n',
-                    KeyCode::Tab => '\t',
-                    KeyCode::Char(c) => c,
-                    _ => unreachable!(),
+                        return Err(err);
+                    }
+                    match key.code {
+                        KeyCode::Enter => Addr::Char('\n'),
+                        KeyCode::Tab => Addr::Char('\t'),
+                        KeyCode::Char(c) => Addr::Char(c),
+                        _ => unreachable!(),
+                    }
                 };
 
                 let a1 = self[temp_v!(1)];
 
-                (self.unify_fn)(self, Addr::Char(c), a1);
+                (self.unify_fn)(self, c, a1);
             }
             &SystemClauseType::HeadIsDynamic => {
                 let module_name = atom_from!(self, self.store(self.deref(self[temp_v!(1)])));
@@ -2616,7 +2856,7 @@ impl MachineState {
                     indices.streams.insert(current_output_stream.clone());
                 }
 
-                if !stream.is_stdin() && !stream.is_stdout() {
+                if !stream.is_stdin() && !stream.is_stdout() && !stream.is_stderr() {
                     stream.close();
 
                     if let Some(ref alias) = stream.options().alias {
@@ -2988,12 +3228,7 @@ impl MachineState {
                 }
             }
             &SystemClauseType::Maybe => {
-                let result = {
-                    let mut rand = RANDOM_STATE.borrow_mut();
-                    rand.bits(1) == 0
-                };
-
-                self.fail = result;
+                self.fail = self.prng.next_u64() >> 63 == 0;
             }
             &SystemClauseType::CpuNow => {
                 let a1 = self[temp_v!(1)];
@@ -3006,6 +3241,219 @@ impl MachineState {
                 let str = self.systemtime_to_timestamp(SystemTime::now());
                 (self.unify_fn)(self, self[temp_v!(1)], str);
             }
+            &SystemClauseType::Statistics => {
+                let key = self.atom_argument_to_string(1);
+
+                let value = match key.as_str() {
+                    "cputime" => {
+                        let secs = ProcessTime::now().as_duration().as_secs_f64();
+                        self.heap.put_constant(Constant::Float(OrderedFloat(secs)))
+                    }
+                    "runtime" => {
+                        let millis = ProcessTime::now().as_duration().as_millis();
+                        Addr::Usize(millis as usize)
+                    }
+                    "walltime" => {
+                        let millis = SystemTime::now()
+                            .duration_since(self.start_time)
+                            .map(|d| d.as_millis())
+                            .unwrap_or(0);
+
+                        Addr::Usize(millis as usize)
+                    }
+                    "epoch" => {
+                        let secs = self
+                            .start_time
+                            .duration_since(SystemTime::UNIX_EPOCH)
+                            .map(|d| d.as_secs_f64())
+                            .unwrap_or(0.0);
+
+                        self.heap.put_constant(Constant::Float(OrderedFloat(secs)))
+                    }
+                    "inferences" => {
+                        let n = Integer::from(self.inferences);
+                        self.heap.put_constant(Constant::Integer(Rc::new(n)))
+                    }
+                    "heapused" => Addr::Usize(self.heap.h() * mem::size_of::<HeapCellValue>()),
+                    "stack" => Addr::Usize(self.stack.bytes_used()),
+                    "trail" => Addr::Usize(self.trail.len() * mem::size_of::<TrailRef>()),
+                    "choicepoints" => {
+                        let mut b = self.b;
+                        let mut count = 0;
+
+                        while b > 0 {
+                            count += 1;
+                            b = self.stack.index_or_frame(b).prelude.b;
+                        }
+
+                        Addr::Usize(count)
+                    }
+                    "atoms" => Addr::Usize(self.atom_tbl.borrow_mut().len()),
+                    "clauses" => {
+                        let count_clauses = |preds: &ExtensiblePredicates| {
+                            preds
+                                .values()
+                                .map(|skeleton| skeleton.clauses.len())
+                                .sum::<usize>()
+                        };
+
+                        let count = indices
+                            .modules
+                            .values()
+                            .map(|module| count_clauses(&module.extensible_predicates))
+                            .sum::<usize>();
+
+                        let count = count
+                            + count_clauses(&indices.extensible_predicates)
+                            + code_repo.static_clause_count();
+
+                        Addr::Usize(count)
+                    }
+                    "modules" => Addr::Usize(indices.modules.len()),
+                    "codes" => Addr::Usize(code_repo.code.len()),
+                    _ => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                };
+
+                (self.unify_fn)(self, self[temp_v!(2)], value);
+            }
+            &SystemClauseType::GetTime => {
+                let secs = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map(|d| d.as_secs_f64())
+                    .unwrap_or(0.0);
+
+                let addr = self.heap.put_constant(Constant::Float(OrderedFloat(secs)));
+                (self.unify_fn)(self, self[temp_v!(1)], addr);
+            }
+            &SystemClauseType::MonotonicTime => {
+                let secs = monotonic_epoch().elapsed().as_secs_f64();
+
+                let addr = self.heap.put_constant(Constant::Float(OrderedFloat(secs)));
+                (self.unify_fn)(self, self[temp_v!(1)], addr);
+            }
+            &SystemClauseType::NewAlarmId => {
+                let id = new_alarm_id();
+                (self.unify_fn)(self, self[temp_v!(1)], Addr::Usize(id));
+            }
+            &SystemClauseType::ScheduleAlarm => {
+                let time = self.store(self.deref(self[temp_v!(1)]));
+
+                let secs = match Number::try_from((time, &self.heap)) {
+                    Ok(Number::Float(OrderedFloat(n))) => n,
+                    Ok(Number::Fixnum(n)) => n as f64,
+                    Ok(Number::Integer(n)) => n.to_f64(),
+                    Ok(Number::Rational(n)) => n.to_f64(),
+                    Err(_) => unreachable!(),
+                };
+
+                let secs = secs.max(0.0).min(u32::MAX as f64);
+
+                match Number::try_from((self.store(self.deref(self[temp_v!(2)])), &self.heap)) {
+                    Ok(Number::Fixnum(n)) => {
+                        schedule_alarm(n as usize, Duration::from_secs_f64(secs))
+                    }
+                    Ok(Number::Integer(n)) => {
+                        schedule_alarm(n.to_usize().unwrap(), Duration::from_secs_f64(secs))
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            &SystemClauseType::CancelAlarm => {
+                match Number::try_from((self.store(self.deref(self[temp_v!(1)])), &self.heap)) {
+                    Ok(Number::Fixnum(n)) => cancel_alarm(n as usize),
+                    Ok(Number::Integer(n)) => cancel_alarm(n.to_usize().unwrap()),
+                    _ => unreachable!(),
+                }
+            }
+            &SystemClauseType::FiredAlarms => {
+                let ids = take_fired_alarms()
+                    .into_iter()
+                    .map(|id| HeapCellValue::Addr(Addr::Usize(id)));
+
+                let ids = Addr::HeapCell(self.heap.to_list(ids));
+                (self.unify_fn)(self, self[temp_v!(1)], ids);
+            }
+            &SystemClauseType::SetSignalAction => {
+                let name = self.atom_argument_to_string(1);
+                let action = self.atom_argument_to_string(2);
+
+                self.fail = !set_signal_action(&name, &action);
+            }
+            &SystemClauseType::PendingSignals => {
+                let names = take_pending_signals()
+                    .into_iter()
+                    .map(|name| HeapCellValue::Atom(clause_name!(name), None));
+
+                let names = Addr::HeapCell(self.heap.to_list(names));
+                (self.unify_fn)(self, self[temp_v!(1)], names);
+            }
+            &SystemClauseType::LocalTimeZone => {
+                let addr = self.store(self.deref(self[temp_v!(1)]));
+
+                let secs = match Number::try_from((addr, &self.heap)) {
+                    Ok(Number::Fixnum(n)) => Some(n as i64),
+                    Ok(Number::Integer(n)) => n.to_isize().map(|n| n as i64),
+                    _ => {
+                        return Err(self.type_error(
+                            ValidType::Integer,
+                            addr,
+                            clause_name!("$local_time_zone"),
+                            4,
+                        ));
+                    }
+                };
+
+                let datetime =
+                    match secs.and_then(|secs| NaiveDateTime::from_timestamp_opt(secs, 0)) {
+                        Some(datetime) => datetime,
+                        None => {
+                            self.fail = true;
+                            return Ok(());
+                        }
+                    };
+
+                let local_offset = Local.offset_from_utc_datetime(&datetime).local_minus_utc();
+
+                // chrono knows neither the name of the time zone nor
+                // whether daylight saving time is in effect, which
+                // is taken to be the case if the offset is ahead of
+                // the lesser of the offsets on the first of January
+                // and of July.
+                let standard_offset = [1, 7]
+                    .iter()
+                    .filter_map(|&month| NaiveDate::from_ymd_opt(datetime.year(), month, 1))
+                    .map(|date| Local.offset_from_utc_date(&date).local_minus_utc())
+                    .min()
+                    .unwrap_or(local_offset);
+
+                let offset = Addr::Fixnum(-(local_offset as isize));
+
+                let zone = self
+                    .heap
+                    .to_unifiable(HeapCellValue::Atom(clause_name!("-"), None));
+
+                let dst = if local_offset > standard_offset {
+                    "true"
+                } else {
+                    "false"
+                };
+                let dst = self
+                    .heap
+                    .to_unifiable(HeapCellValue::Atom(clause_name!(dst), None));
+
+                (self.unify_fn)(self, self[temp_v!(2)], offset);
+
+                if !self.fail {
+                    (self.unify_fn)(self, self[temp_v!(3)], zone);
+                }
+
+                if !self.fail {
+                    (self.unify_fn)(self, self[temp_v!(4)], dst);
+                }
+            }
             &SystemClauseType::OpDeclaration => {
                 let priority = self[temp_v!(1)];
                 let specifier = self[temp_v!(2)];
@@ -3118,6 +3566,13 @@ impl MachineState {
 
                 self.bind(stream_var.as_var().unwrap(), stream);
             }
+            &SystemClauseType::OpenCharsOutputStream => {
+                let stream = Stream::from(String::new());
+                let stream = self.heap.to_unifiable(HeapCellValue::Stream(stream));
+
+                let a1 = self[temp_v!(1)];
+                (self.unify_fn)(self, a1, stream);
+            }
             &SystemClauseType::TruncateIfNoLiftedHeapGrowthDiff => {
                 self.truncate_if_no_lifted_heap_diff(|h| Addr::HeapCell(h))
             }
@@ -3307,6 +3762,23 @@ impl MachineState {
                     }
                 }
             }
+            &SystemClauseType::GetDebug => {
+                let a1 = self[temp_v!(1)];
+                let value = if self.flags.debug { "true" } else { "false" };
+
+                let atom = self
+                    .heap
+                    .to_unifiable(HeapCellValue::Atom(clause_name!(value), None));
+
+                (self.unify_fn)(self, a1, atom);
+            }
+            &SystemClauseType::GetRawBacktrace => {
+                let a1 = self[temp_v!(1)];
+                let ptrs = self.backtrace.iter().map(|&p| HeapCellValue::Addr(Addr::Usize(p)));
+                let ptrs = Addr::HeapCell(self.heap.to_list(ptrs));
+
+                (self.unify_fn)(self, a1, ptrs);
+            }
             &SystemClauseType::GetDoubleQuotes => {
                 let a1 = self[temp_v!(1)];
 
@@ -3334,43 +3806,303 @@ impl MachineState {
                     }
                 }
             }
-            &SystemClauseType::GetSCCCleaner => {
-                let dest = self[temp_v!(1)];
+            &SystemClauseType::GetFloatFlag => {
+                let flags = self.flags;
 
-                match cut_policy.downcast_mut::<SCCCutPolicy>().ok() {
-                    Some(sgc_policy) => {
-                        if let Some((addr, b_cutoff, prev_b)) = sgc_policy.pop_cont_pt() {
-                            let b = self.stack.index_or_frame(self.b).prelude.b;
+                let value = match self.atom_argument_to_string(1).as_str() {
+                    "float_overflow" if flags.trap_float_overflow => "error",
+                    "float_overflow" => "infinity",
+                    "float_zero_div" if flags.trap_float_zero_div => "error",
+                    "float_zero_div" => "infinity",
+                    "float_undefined" if flags.trap_float_undefined => "error",
+                    "float_undefined" => "nan",
+                    "float_underflow" if flags.trap_float_underflow => "error",
+                    "float_underflow" => "ignore",
+                    _ => unreachable!(),
+                };
 
-                            if b <= b_cutoff {
-                                self.block = prev_b;
+                let a2 = self[temp_v!(2)];
+                let atom = self
+                    .heap
+                    .to_unifiable(HeapCellValue::Atom(clause_name!(value), None));
 
-                                if let Some(r) = dest.as_var() {
-                                    self.bind(r, addr);
-                                    return return_from_clause!(self.last_call, self);
-                                }
-                            } else {
-                                sgc_policy.push_cont_pt(addr, b_cutoff, prev_b);
-                            }
-                        }
-                    }
-                    None => {}
+                (self.unify_fn)(self, a2, atom);
+            }
+            &SystemClauseType::GetMemoryLimit => {
+                let limit = match self.atom_argument_to_string(1).as_str() {
+                    "heap_limit" => self.heap.limit(),
+                    "stack_limit" => self.stack.limit(),
+                    "trail_limit" => self.trail_limit,
+                    _ => unreachable!(),
                 };
 
-                self.fail = true;
+                let value = if limit == usize::max_value() {
+                    self.heap
+                        .to_unifiable(HeapCellValue::Atom(clause_name!("infinite"), None))
+                } else {
+                    Addr::Usize(limit)
+                };
+
+                let a2 = self[temp_v!(2)];
+                (self.unify_fn)(self, a2, value);
             }
-            &SystemClauseType::Halt => {
-                let code = self.store(self.deref(self[temp_v!(1)]));
+            &SystemClauseType::SplitText => {
+                let string = self.text_to_string(self[temp_v!(1)]);
+                let separators = self.text_to_string(self[temp_v!(2)]);
+                let pad = self.text_to_string(self[temp_v!(3)]);
 
-                let code = match Number::try_from((code, &self.heap)) {
-                    Ok(Number::Fixnum(n)) => n as i32,
-                    Ok(Number::Integer(n)) => n.to_i32().unwrap(),
-                    Ok(Number::Rational(r)) => {
-                        // n has already been confirmed as an integer, and
-                        // internally, Rational is assumed reduced, so its
-                        // denominator must be 1.
-                        r.numer().to_i32().unwrap()
-                    }
+                let (string, separators, pad) = match (string, separators, pad) {
+                    (Some(string), Some(separators), Some(pad)) => (string, separators, pad),
+                    _ => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                };
+
+                let is_pad = |c: char| pad.contains(c);
+                let string = string.trim_matches(is_pad);
+
+                let mut substrings = vec![];
+                let mut rest = string;
+
+                loop {
+                    rest = rest.trim_start_matches(is_pad);
+
+                    let (field, next) = match rest.find(|c: char| separators.contains(c)) {
+                        Some(index) => {
+                            let sep_len = rest[index..].chars().next().unwrap().len_utf8();
+                            (&rest[..index], Some(&rest[index + sep_len..]))
+                        }
+                        None => (rest, None),
+                    };
+
+                    substrings.push(field.trim_end_matches(is_pad).to_string());
+
+                    match next {
+                        Some(next) => rest = next,
+                        None => break,
+                    }
+                }
+
+                let substrings: Vec<_> = substrings
+                    .into_iter()
+                    .map(|field| self.heap.put_opaque_string(&field))
+                    .collect();
+
+                let list = Addr::HeapCell(self.heap.to_list(substrings.into_iter()));
+                (self.unify_fn)(self, list, self[temp_v!(4)]);
+            }
+            &SystemClauseType::SubText => {
+                let string = match self.text_to_string(self[temp_v!(1)]) {
+                    Some(string) => string,
+                    None => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                };
+
+                let mut bounds = [0; 2];
+
+                for (i, bound) in bounds.iter_mut().enumerate() {
+                    let addr = self.store(self.deref(self[temp_v!(i + 2)]));
+
+                    let n = match Number::try_from((addr, &self.heap)) {
+                        Ok(Number::Fixnum(n)) => usize::try_from(n).ok(),
+                        Ok(Number::Integer(n)) => n.to_usize(),
+                        _ => None,
+                    };
+
+                    match n {
+                        Some(n) => *bound = n,
+                        None => {
+                            self.fail = true;
+                            return Ok(());
+                        }
+                    }
+                }
+
+                let [before, length] = bounds;
+                let mut char_indices = string.char_indices().map(|(i, _)| i).chain(once(string.len()));
+
+                let start = char_indices.nth(before);
+                let end = match length {
+                    0 => start,
+                    _ => char_indices.nth(length - 1),
+                };
+
+                match (start, end) {
+                    (Some(start), Some(end)) => {
+                        let substring = self.heap.put_opaque_string(&string[start..end]);
+                        (self.unify_fn)(self, substring, self[temp_v!(4)]);
+                    }
+                    _ => {
+                        self.fail = true;
+                    }
+                }
+            }
+            &SystemClauseType::TextConcat => {
+                let prefix = self.text_to_string(self[temp_v!(1)]);
+                let suffix = self.text_to_string(self[temp_v!(2)]);
+
+                match (prefix, suffix) {
+                    (Some(prefix), Some(suffix)) => {
+                        let string = self.heap.put_opaque_string(&(prefix + &suffix));
+                        (self.unify_fn)(self, string, self[temp_v!(3)]);
+                    }
+                    _ => {
+                        self.fail = true;
+                    }
+                }
+            }
+            &SystemClauseType::TextLength => match self.text_to_string(self[temp_v!(1)]) {
+                Some(string) => {
+                    let length = Addr::Usize(string.chars().count());
+                    (self.unify_fn)(self, length, self[temp_v!(2)]);
+                }
+                None => {
+                    self.fail = true;
+                }
+            },
+            &SystemClauseType::TextToString => match self.text_to_string(self[temp_v!(1)]) {
+                Some(string) => {
+                    let string = self.heap.put_opaque_string(&string);
+                    (self.unify_fn)(self, string, self[temp_v!(2)]);
+                }
+                None => {
+                    self.fail = true;
+                }
+            },
+            &SystemClauseType::TextToChars => match self.text_to_string(self[temp_v!(1)]) {
+                Some(string) => {
+                    let chars = self.heap.put_complete_string(&string);
+                    (self.unify_fn)(self, chars, self[temp_v!(2)]);
+                }
+                None => {
+                    self.fail = true;
+                }
+            },
+            &SystemClauseType::IsString => match self.store(self.deref(self[temp_v!(1)])) {
+                Addr::Con(h) if self.heap.opaque_string_at(h) => {}
+                _ => {
+                    self.fail = true;
+                }
+            },
+            &SystemClauseType::FormatNumber => {
+                let directive = self.atom_argument_to_string(1);
+                let digits = self.store(self.deref(self[temp_v!(2)]));
+
+                let digits = match Number::try_from((digits, &self.heap)) {
+                    Ok(Number::Fixnum(n)) => usize::try_from(n).ok(),
+                    Ok(Number::Integer(n)) => n.to_usize(),
+                    _ => None,
+                };
+
+                let n = self.store(self.deref(self[temp_v!(3)]));
+
+                let n = match Number::try_from((n, &self.heap)) {
+                    Ok(n) => n,
+                    Err(_) => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                };
+
+                let digits = match digits {
+                    Some(digits) => digits,
+                    None => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                };
+
+                let string = match (directive.as_str(), n) {
+                    ("f", Number::Fixnum(n)) => fixed_rational_str(&Rational::from(n), digits),
+                    ("f", Number::Integer(n)) => fixed_rational_str(&Rational::from(&*n), digits),
+                    ("f", Number::Rational(r)) => fixed_rational_str(&r, digits),
+                    ("f", Number::Float(OrderedFloat(f))) => match Rational::from_f64(f) {
+                        Some(r) => fixed_rational_str(&r, digits),
+                        None => float_str(f, FloatFormat::Fixed(digits)),
+                    },
+                    ("e", n) => float_str(rnd_f(&n), FloatFormat::Exponential(digits)),
+                    ("g", n) => float_str(rnd_f(&n), FloatFormat::General(digits)),
+                    _ => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                };
+
+                let chars = string.chars().map(|c| Addr::Char(c));
+                let char_list = Addr::HeapCell(self.heap.to_list(chars));
+
+                let a4 = self[temp_v!(4)];
+                (self.unify_fn)(self, char_list, a4);
+            }
+            &SystemClauseType::GetFloatFormat => {
+                let a1 = self[temp_v!(1)];
+                let spec = self.flags.float_format.to_spec();
+
+                let atom = self
+                    .heap
+                    .to_unifiable(HeapCellValue::Atom(clause_name!(spec, self.atom_tbl), None));
+
+                (self.unify_fn)(self, a1, atom);
+            }
+            &SystemClauseType::GetRandomState => {
+                let state = self.prng.state();
+
+                for (i, &word) in state.iter().enumerate() {
+                    let word = match isize::try_from(word) {
+                        Ok(n) => Number::Fixnum(n),
+                        Err(_) => Number::from(Integer::from(word)),
+                    };
+
+                    let a = self[temp_v!(i + 1)];
+                    let addr = self.heap.to_unifiable(word.into());
+
+                    (self.unify_fn)(self, a, addr);
+
+                    if self.fail {
+                        return Ok(());
+                    }
+                }
+            }
+            &SystemClauseType::GetSCCCleaner => {
+                let dest = self[temp_v!(1)];
+
+                match cut_policy.downcast_mut::<SCCCutPolicy>().ok() {
+                    Some(sgc_policy) => {
+                        if let Some((addr, b_cutoff, prev_b)) = sgc_policy.pop_cont_pt() {
+                            let b = self.stack.index_or_frame(self.b).prelude.b;
+
+                            if b <= b_cutoff {
+                                self.block = prev_b;
+
+                                if let Some(r) = dest.as_var() {
+                                    self.bind(r, addr);
+                                    return return_from_clause!(self.last_call, self);
+                                }
+                            } else {
+                                sgc_policy.push_cont_pt(addr, b_cutoff, prev_b);
+                            }
+                        }
+                    }
+                    None => {}
+                };
+
+                self.fail = true;
+            }
+            &SystemClauseType::Halt => {
+                let code = self.store(self.deref(self[temp_v!(1)]));
+
+                let code = match Number::try_from((code, &self.heap)) {
+                    Ok(Number::Fixnum(n)) => n as i32,
+                    Ok(Number::Integer(n)) => n.to_i32().unwrap(),
+                    Ok(Number::Rational(r)) => {
+                        // n has already been confirmed as an integer, and
+                        // internally, Rational is assumed reduced, so its
+                        // denominator must be 1.
+                        r.numer().to_i32().unwrap()
+                    }
                     _ => {
                         unreachable!()
                     }
@@ -3465,6 +4197,19 @@ impl MachineState {
                     }
                 };
             }
+            &SystemClauseType::PredicateDefined => {
+                let module_name = atom_from!(self, self.store(self.deref(self[temp_v!(1)])));
+                let (name, arity) = self.read_predicate_key(self[temp_v!(2)], self[temp_v!(3)]);
+
+                let index = indices
+                    .get_predicate_code_index(name, arity, module_name, None)
+                    .map(|index| index.get());
+
+                self.fail = match index {
+                    Some(IndexPtr::Index(_)) | Some(IndexPtr::DynamicIndex(_)) => false,
+                    _ => true,
+                };
+            }
             &SystemClauseType::NoSuchPredicate => {
                 let module_name = atom_from!(self, self.store(self.deref(self[temp_v!(1)])));
 
@@ -3687,6 +4432,125 @@ impl MachineState {
 
                 *current_output_stream = stream;
             }
+            &SystemClauseType::SetDebug => match self[temp_v!(1)] {
+                Addr::Con(h) if self.heap.atom_at(h) => {
+                    if let HeapCellValue::Atom(ref atom, _) = &self.heap[h] {
+                        self.flags.debug = match atom.as_str() {
+                            "true" => true,
+                            "false" => false,
+                            _ => {
+                                self.fail = true;
+                                return Ok(());
+                            }
+                        };
+                    } else {
+                        unreachable!()
+                    }
+                }
+                _ => {
+                    self.fail = true;
+                }
+            },
+            &SystemClauseType::SetFloatFlag => {
+                let flag = self.atom_argument_to_string(1);
+
+                let value = match self.store(self.deref(self[temp_v!(2)])) {
+                    Addr::Con(h) if self.heap.atom_at(h) => self.atom_argument_to_string(2),
+                    _ => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                };
+
+                let flags = &mut self.flags;
+
+                match (flag.as_str(), value.as_str()) {
+                    ("float_overflow", "error") => flags.trap_float_overflow = true,
+                    ("float_overflow", "infinity") => flags.trap_float_overflow = false,
+                    ("float_zero_div", "error") => flags.trap_float_zero_div = true,
+                    ("float_zero_div", "infinity") => flags.trap_float_zero_div = false,
+                    ("float_undefined", "error") => flags.trap_float_undefined = true,
+                    ("float_undefined", "nan") => flags.trap_float_undefined = false,
+                    ("float_underflow", "error") => flags.trap_float_underflow = true,
+                    ("float_underflow", "ignore") => flags.trap_float_underflow = false,
+                    _ => self.fail = true,
+                }
+            }
+            &SystemClauseType::SetMemoryLimit => {
+                let flag = self.atom_argument_to_string(1);
+
+                let limit = match self.store(self.deref(self[temp_v!(2)])) {
+                    Addr::Con(h) if self.heap.atom_at(h) => {
+                        if self.atom_argument_to_string(2) == "infinite" {
+                            Some(usize::max_value())
+                        } else {
+                            None
+                        }
+                    }
+                    addr => match Number::try_from((addr, &self.heap)) {
+                        Ok(Number::Fixnum(n)) => usize::try_from(n).ok(),
+                        Ok(Number::Integer(n)) => n.to_usize(),
+                        _ => None,
+                    },
+                };
+
+                // a limit must leave room for what is already in use,
+                // or every subsequent call would throw.
+                match (flag.as_str(), limit) {
+                    ("heap_limit", Some(limit))
+                        if limit > self.heap.h() * mem::size_of::<HeapCellValue>() =>
+                    {
+                        self.heap.set_limit(limit)
+                    }
+                    ("stack_limit", Some(limit)) if limit > self.stack.bytes_used() => {
+                        self.stack.set_limit(limit)
+                    }
+                    ("trail_limit", Some(limit)) if limit > self.trail_bytes_used() => {
+                        self.trail_limit = limit
+                    }
+                    _ => self.fail = true,
+                }
+
+                self.memory_limited = self.heap.limit() < usize::max_value()
+                    || self.stack.limit() < usize::max_value()
+                    || self.trail_limit < usize::max_value();
+            }
+            &SystemClauseType::SetFloatFormat => match self.store(self.deref(self[temp_v!(1)])) {
+                Addr::Con(h) if self.heap.atom_at(h) => {
+                    let spec = self.atom_argument_to_string(1);
+
+                    match FloatFormat::from_spec(&spec) {
+                        Some(float_format) => self.flags.float_format = float_format,
+                        None => self.fail = true,
+                    }
+                }
+                _ => {
+                    self.fail = true;
+                }
+            },
+            &SystemClauseType::SetRandomState => {
+                let mut state = [0; 4];
+
+                for (i, word) in state.iter_mut().enumerate() {
+                    let addr = self.store(self.deref(self[temp_v!(i + 1)]));
+
+                    let value = match Number::try_from((addr, &self.heap)) {
+                        Ok(Number::Fixnum(n)) => u64::try_from(n).ok(),
+                        Ok(Number::Integer(n)) => n.to_u64(),
+                        _ => None,
+                    };
+
+                    match value {
+                        Some(value) => *word = value,
+                        None => {
+                            self.fail = true;
+                            return Ok(());
+                        }
+                    }
+                }
+
+                self.fail = !self.prng.set_state(state);
+            }
             &SystemClauseType::SetDoubleQuotes => match self[temp_v!(1)] {
                 Addr::Con(h) if self.heap.atom_at(h) => {
                     if let HeapCellValue::Atom(ref atom, _) = &self.heap[h] {
@@ -3756,6 +4620,55 @@ impl MachineState {
             &SystemClauseType::Fail => {
                 self.fail = true;
             }
+            &SystemClauseType::KeepBacktrace => {
+                let stub = MachineError::functor_stub(clause_name!("$keep_backtrace"), 1);
+                let mut backtrace = vec![];
+
+                for addr in self.try_from_list(temp_v!(1), stub)? {
+                    match self.store(self.deref(addr)) {
+                        Addr::Usize(p) => backtrace.push(p),
+                        _ => {
+                            self.fail = true;
+                            return Ok(());
+                        }
+                    }
+                }
+
+                self.backtrace = backtrace;
+                self.keep_backtrace = true;
+            }
+            &SystemClauseType::GetBacktrace => {
+                let a1 = self[temp_v!(1)];
+                let frames = self.backtrace_to_list(&code_repo.code, indices);
+
+                (self.unify_fn)(self, a1, frames);
+            }
+            &SystemClauseType::GetInterruptBacktrace => {
+                let backtrace = mem::replace(&mut self.backtrace, self.interrupt_backtrace.clone());
+                let frames = self.backtrace_to_list(&code_repo.code, indices);
+
+                self.backtrace = backtrace;
+                (self.unify_fn)(self, self[temp_v!(1)], frames);
+            }
+            &SystemClauseType::SetTrace => {
+                // resume is used by the trace handler before it retries
+                // the call it traced.
+                match self.atom_argument_to_string(1).as_str() {
+                    "true" => {
+                        raise_event(TRACE_EVENT);
+                        self.trace_retry = false;
+                    }
+                    "false" => {
+                        take_event(TRACE_EVENT);
+                        self.trace_retry = false;
+                    }
+                    "resume" => {
+                        raise_event(TRACE_EVENT);
+                        self.trace_retry = true;
+                    }
+                    _ => unreachable!(),
+                }
+            }
             &SystemClauseType::GetBall => {
                 let addr = self.store(self.deref(self[temp_v!(1)]));
                 let h = self.heap.h();
@@ -3898,6 +4811,16 @@ impl MachineState {
                     }
                 }
             }
+            &SystemClauseType::QueryHistory => {
+                let queries: Vec<_> = current_input_stream
+                    .query_history()
+                    .iter()
+                    .map(|query| self.heap.put_complete_string(query))
+                    .collect();
+
+                let queries_list = Addr::HeapCell(self.heap.to_list(queries.into_iter()));
+                (self.unify_fn)(self, self[temp_v!(1)], queries_list);
+            }
             &SystemClauseType::ReadQueryTerm => {
                 current_input_stream.reset();
 
@@ -3967,6 +4890,78 @@ impl MachineState {
                 self.heap.push(HeapCellValue::Addr(Addr::HeapCell(h)));
                 self[temp_v!(4)] = Addr::HeapCell(h);
             }
+            &SystemClauseType::SetArg => {
+                let arg_h = match self.setarg_location("setarg")? {
+                    Some(arg_h) => arg_h,
+                    None => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                };
+
+                let mut value = self.store(self.deref(self[temp_v!(3)]));
+
+                if let Addr::StackCell(fr, sc) = value {
+                    let h = self.heap.h();
+
+                    self.heap.push(HeapCellValue::Addr(Addr::HeapCell(h)));
+                    self.bind(Ref::StackCell(fr, sc), Addr::HeapCell(h));
+
+                    value = Addr::HeapCell(h);
+                }
+
+                let copy_h = self.heap.displace_constant(arg_h);
+
+                if arg_h < self.hb {
+                    // constants are stored inline, so the cell itself
+                    // is saved rather than its address.
+                    let old_value_h = match copy_h {
+                        Some(copy_h) => copy_h,
+                        None => {
+                            let old_value = self.heap[arg_h].context_free_clone();
+                            self.heap.push(old_value)
+                        }
+                    };
+
+                    self.trail(TrailRef::ArgOffset(arg_h, old_value_h));
+                }
+
+                self.heap[arg_h] = HeapCellValue::Addr(value);
+            }
+            &SystemClauseType::NbSetArg => {
+                let arg_h = match self.setarg_location("nb_setarg")? {
+                    Some(arg_h) => arg_h,
+                    None => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                };
+
+                self.heap.displace_constant(arg_h);
+
+                let value = self[temp_v!(3)];
+                let mut ball = Ball::new();
+
+                ball.boundary = self.heap.h();
+
+                copy_term(
+                    CopyBallTerm::new(&mut self.stack, &mut self.heap, &mut ball.stub),
+                    value,
+                    AttrVarPolicy::DeepCopy,
+                );
+
+                // the value is kept off the heap, so that backtracking
+                // can reclaim its copy there. restore_nb_args makes a
+                // new copy if the term outlives the backtrack.
+                let value_h = self.heap.h();
+                let stub = ball.copy_and_align(value_h);
+
+                self.heap.extend(stub.into_iter());
+                self.heap[arg_h] = HeapCellValue::Addr(Addr::HeapCell(value_h));
+
+                self.nb_args.insert(arg_h, ball);
+                self.trail(TrailRef::NbArg(arg_h));
+            }
             &SystemClauseType::SetBall => {
                 self.set_ball();
             }
@@ -3983,8 +4978,8 @@ impl MachineState {
                     }
                 };
 
-                let mut rand = RANDOM_STATE.borrow_mut();
-                rand.seed(&seed);
+                let seed = Integer::from(&seed & &Integer::from(u64::MAX));
+                self.prng.seed(seed.to_u64().unwrap());
             }
             &SystemClauseType::SkipMaxList => {
                 if let Err(err) = self.skip_max_list() {
@@ -4004,8 +4999,22 @@ impl MachineState {
                 };
 
                 let duration = Duration::new(1, 0);
-                let duration = duration.mul_f64(time);
-                ::std::thread::sleep(duration);
+                let deadline = Instant::now() + duration.mul_f64(time);
+
+                // sleep in slices, so that a pending alarm, signal or
+                // interrupt is handled without waiting for the full
+                // duration.
+                loop {
+                    let now = Instant::now();
+
+                    let events = PENDING_EVENTS.load(std::sync::atomic::Ordering::Relaxed);
+
+                    if now >= deadline || events & !TRACE_EVENT != 0 {
+                        break;
+                    }
+
+                    ::std::thread::sleep((deadline - now).min(Duration::from_millis(10)));
+                }
             }
             &SystemClauseType::SocketClientOpen => {
                 let addr = self.store(self.deref(self[temp_v!(1)]));
@@ -4401,6 +5410,9 @@ impl MachineState {
                                     return Ok(());
                                 }
                             }
+                            "line_position" => HeapCellValue::Integer(Rc::new(Integer::from(
+                                stream.line_position(),
+                            ))),
                             "end_of_stream" => {
                                 let end_of_stream_pos = stream.position_relative_to_end();
                                 HeapCellValue::Atom(clause_name!(end_of_stream_pos.as_str()), None)
@@ -4478,7 +5490,19 @@ impl MachineState {
                     }
                 };
 
-                let new_value = self.store(self.deref(self[temp_v!(2)]));
+                let mut new_value = self.store(self.deref(self[temp_v!(2)]));
+
+                if let Addr::StackCell(fr, sc) = new_value {
+                    // the stored value must outlive the frame of
+                    // the caller, so unsafe variables are moved to
+                    // the heap.
+                    let h = self.heap.h();
+
+                    self.heap.push(HeapCellValue::Addr(Addr::HeapCell(h)));
+                    self.bind(Ref::StackCell(fr, sc), Addr::HeapCell(h));
+
+                    new_value = Addr::HeapCell(h);
+                }
 
                 match indices.global_variables.get_mut(&key) {
                     Some((_, ref mut loc)) => {
@@ -4532,7 +5556,13 @@ impl MachineState {
                 let a1 = self[temp_v!(1)];
                 let a2 = self[temp_v!(2)];
 
-                self.unify_with_occurs_check(a1, a2);
+                self.unify_with_occurs_check(a1, a2);
+            }
+            &SystemClauseType::UnifyWithOccursCheckWithError => {
+                let a1 = self[temp_v!(1)];
+                let a2 = self[temp_v!(2)];
+
+                self.unify_with_occurs_check_with_error(a1, a2);
             }
             &SystemClauseType::UnwindEnvironments => {
                 let mut e = self.e;
@@ -5211,16 +6241,19 @@ impl MachineState {
             }
             &SystemClauseType::LoadHTML => {
                 let string = self.heap_pstr_iter(self[temp_v!(1)]).to_string();
+                let options = self.xml_options(self[temp_v!(3)]);
                 let doc = select::document::Document::from_read(string.as_bytes()).unwrap();
-                let result = self.html_node_to_term(indices, doc.nth(0).unwrap());
+                let result = self.html_node_to_term(doc.nth(0).unwrap(), &options);
 
                 (self.unify_fn)(self, self[temp_v!(2)], result);
             }
             &SystemClauseType::LoadXML => {
                 let string = self.heap_pstr_iter(self[temp_v!(1)]).to_string();
+                let options = self.xml_options(self[temp_v!(3)]);
+
                 match roxmltree::Document::parse(&string) {
                     Ok(doc) => {
-                        let result = self.xml_node_to_term(indices, doc.root_element());
+                        let result = self.xml_node_to_term(&doc, doc.root_element(), &options);
                         (self.unify_fn)(self, self[temp_v!(2)], result);
                     }
                     _ => {
@@ -5229,6 +6262,115 @@ impl MachineState {
                     }
                 }
             }
+            &SystemClauseType::XMLStreamRoot => {
+                let mut stream =
+                    self.get_stream_or_alias(self[temp_v!(1)], indices, "load_xml_stream", 3)?;
+                let mut scanner = XmlScanner::new(self.open_parsing_stream(
+                    stream.clone(),
+                    "load_xml_stream",
+                    3,
+                )?);
+
+                let root = scanner.root();
+                stream.add_lines_read(scanner.lines);
+
+                match root {
+                    Some(root) => {
+                        let root = self.heap.put_complete_string(&root);
+                        (self.unify_fn)(self, self[temp_v!(2)], root);
+                    }
+                    None => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                }
+            }
+            &SystemClauseType::XMLStreamElement => {
+                let mut stream =
+                    self.get_stream_or_alias(self[temp_v!(1)], indices, "load_xml_stream", 3)?;
+                let root = self.heap_pstr_iter(self[temp_v!(2)]).to_string();
+                let mut options = self.xml_options(self[temp_v!(3)]);
+                let first = self.atom_argument_to_string(4) == "true";
+
+                // the line the child starts on is counted by the
+                // stream, so that no state is kept between children
+                // on the heap.
+                let line = stream.lines_read() as isize + 1;
+
+                let mut scanner = XmlScanner::new(self.open_parsing_stream(
+                    stream.clone(),
+                    "load_xml_stream",
+                    3,
+                )?);
+
+                let child = scanner.child(&root);
+                stream.add_lines_read(scanner.lines);
+
+                if stream.pause_stream(scanner.take_buf()).is_err() {
+                    self.fail = true;
+                    return Ok(());
+                }
+
+                let (lines_before, child, last) = match child {
+                    Some(child) => child,
+                    None => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                };
+
+                // root is the start tag of the root element, preceded
+                // by the DOCTYPE declaration, if any, so that the
+                // entities it declares are resolved in the child.
+                let start_tag = &root[root.rfind('<').unwrap_or(0)..];
+
+                let root_name: String = start_tag
+                    .chars()
+                    .skip(1)
+                    .take_while(|c| !c.is_whitespace() && *c != '/' && *c != '>')
+                    .collect();
+
+                let text = format!("{}{}</{}>", root, child, root_name);
+
+                options.line_offset =
+                    line + lines_before as isize - root.matches('\n').count() as isize - 1;
+
+                match roxmltree::Document::parse(&text) {
+                    Ok(doc) => {
+                        // a text child is read as a whole, but an entity
+                        // in it may expand to several nodes.
+                        let nodes: Vec<_> = doc
+                            .root_element()
+                            .children()
+                            .filter(|node| node.is_element() || node.is_text())
+                            .collect();
+
+                        let num_nodes = nodes.len();
+                        let mut cvec = Vec::new();
+
+                        for (i, node) in nodes.into_iter().enumerate() {
+                            if node.is_text() {
+                                let text = node.text().unwrap_or("");
+                                let first = first && i == 0;
+                                let last = last && i + 1 == num_nodes;
+
+                                if let Some(text) = options.space.apply(text, first, last) {
+                                    cvec.push(self.heap.put_complete_string(text));
+                                }
+                            } else {
+                                cvec.push(self.xml_node_to_term(&doc, node, &options));
+                            }
+                        }
+
+                        let children = Addr::HeapCell(self.heap.to_list(cvec.into_iter()));
+                        (self.unify_fn)(self, self[temp_v!(5)], children);
+                    }
+                    _ => {
+                        self.fail = true;
+                        return Ok(());
+                    }
+                }
+            }
             &SystemClauseType::GetEnv => {
                 let key = self.heap_pstr_iter(self[temp_v!(1)]).to_string();
                 match env::var(key) {
@@ -5391,6 +6533,36 @@ impl MachineState {
                 self.unify_fn = MachineState::unify_with_occurs_check_with_error;
                 self.bind_fn = MachineState::bind_with_occurs_check_with_error_wrapper;
             }
+            &SystemClauseType::ModuleOccursCheck => {
+                let module_name = atom_from!(self, self.store(self.deref(self[temp_v!(1)])));
+
+                match indices.modules.get(&module_name).and_then(|module| module.occurs_check) {
+                    Some(occurs_check) => {
+                        let value = self.heap.to_unifiable(HeapCellValue::Atom(
+                            clause_name!(occurs_check.as_str()),
+                            None,
+                        ));
+
+                        (self.unify_fn)(self, self[temp_v!(2)], value);
+                    }
+                    None => {
+                        self.fail = true;
+                    }
+                }
+            }
+            &SystemClauseType::SetModuleOccursCheck => {
+                let module_name = atom_from!(self, self.store(self.deref(self[temp_v!(1)])));
+                let value = atom_from!(self, self.store(self.deref(self[temp_v!(2)])));
+
+                match indices.modules.get_mut(&module_name) {
+                    Some(module) => {
+                        module.occurs_check = OccursCheck::from_atom(value.as_str());
+                    }
+                    None => {
+                        self.fail = true;
+                    }
+                }
+            }
             &SystemClauseType::HomeDirectory => {
                 let path = match dirs_next::home_dir() {
                     Some(path) => path,
@@ -5425,7 +6597,7 @@ impl MachineState {
         let mut fstr = "[".to_string();
         let specifiers = vec![
             "Y", "m", "d", "H", "M", "S", "y", "b", "B", "a", "A", "w", "u", "U", "W", "j", "D",
-            "x", "v",
+            "x", "v", "s",
         ];
         for spec in specifiers {
             fstr.push_str(&format!("'{}'=\"%{}\", ", spec, spec).to_string());
@@ -5468,102 +6640,233 @@ impl MachineState {
         }
     }
 
-    pub(super) fn xml_node_to_term(
+    fn xml_options(&self, addr: Addr) -> XmlOptions {
+        let mut options = XmlOptions::default();
+
+        if let Addr::Str(s) = self.store(self.deref(addr)) {
+            let arg = |n: usize| match self.store(self.deref(self.heap[s + n].as_addr(s + n))) {
+                Addr::Con(h) => match &self.heap[h] {
+                    HeapCellValue::Atom(ref name, _) => name.as_str().to_string(),
+                    _ => String::new(),
+                },
+                _ => String::new(),
+            };
+
+            options.space = match arg(1).as_str() {
+                "sgml" => XmlSpace::Sgml,
+                "remove" => XmlSpace::Remove,
+                _ => XmlSpace::Preserve,
+            };
+
+            options.xmlns = arg(2) == "xmlns";
+            options.atom_values = arg(3) == "atom";
+            options.line_numbers = arg(4) == "true";
+        }
+
+        options
+    }
+
+    fn xml_text_value(&mut self, text: &str, atom: bool) -> Addr {
+        if atom {
+            let name = clause_name!(text.to_string(), self.atom_tbl);
+            self.heap.to_unifiable(HeapCellValue::Atom(name, None))
+        } else {
+            self.heap.put_complete_string(text)
+        }
+    }
+
+    fn xml_name(
         &mut self,
-        indices: &mut IndexStore,
-        node: roxmltree::Node,
+        namespace: Option<&str>,
+        prefix: Option<&str>,
+        local: &str,
+        options: &XmlOptions,
     ) -> Addr {
-        if node.is_text() {
-            let string = String::from(node.text().unwrap());
-            self.heap.put_complete_string(&string)
-        } else {
-            let mut avec = Vec::new();
-            for attr in node.attributes() {
-                let chars = clause_name!(String::from(attr.name()), self.atom_tbl);
-                let name = self.heap.to_unifiable(HeapCellValue::Atom(chars, None));
+        let local_name = match prefix {
+            Some(prefix) if !options.xmlns && !prefix.is_empty() => format!("{}:{}", prefix, local),
+            _ => local.to_string(),
+        };
 
-                let value = self.heap.put_complete_string(&attr.value());
+        let local_name = clause_name!(local_name, self.atom_tbl);
+        let local_addr = self
+            .heap
+            .to_unifiable(HeapCellValue::Atom(local_name, None));
 
-                avec.push(HeapCellValue::Addr(Addr::HeapCell(self.heap.h())));
+        match namespace {
+            Some(uri) if options.xmlns => {
+                let uri = clause_name!(uri.to_string(), self.atom_tbl);
+                let uri_addr = self.heap.to_unifiable(HeapCellValue::Atom(uri, None));
+
+                let h = self.heap.h();
 
                 self.heap
-                    .push(HeapCellValue::NamedStr(2, clause_name!("="), None));
-                self.heap.push(HeapCellValue::Addr(name));
-                self.heap.push(HeapCellValue::Addr(value));
-            }
-            let attrs = Addr::HeapCell(self.heap.to_list(avec.into_iter()));
+                    .push(HeapCellValue::NamedStr(2, clause_name!(":"), None));
+                self.heap.push(HeapCellValue::Addr(uri_addr));
+                self.heap.push(HeapCellValue::Addr(local_addr));
 
-            let mut cvec = Vec::new();
-            for child in node.children() {
-                cvec.push(self.xml_node_to_term(indices, child));
+                Addr::HeapCell(h)
             }
-            let children = Addr::HeapCell(self.heap.to_list(cvec.into_iter()));
+            _ => local_addr,
+        }
+    }
+
+    fn xml_attribute(&mut self, name: Addr, value: Addr) -> HeapCellValue {
+        let h = self.heap.h();
 
-            let chars = clause_name!(String::from(node.tag_name().name()), self.atom_tbl);
-            let tag = self.heap.to_unifiable(HeapCellValue::Atom(chars, None));
+        self.heap
+            .push(HeapCellValue::NamedStr(2, clause_name!("="), None));
+        self.heap.push(HeapCellValue::Addr(name));
+        self.heap.push(HeapCellValue::Addr(value));
 
-            let result = Addr::HeapCell(self.heap.h());
+        HeapCellValue::Addr(Addr::HeapCell(h))
+    }
 
-            self.heap
-                .push(HeapCellValue::NamedStr(3, clause_name!("element"), None));
-            self.heap.push(HeapCellValue::Addr(tag));
-            self.heap.push(HeapCellValue::Addr(attrs));
-            self.heap.push(HeapCellValue::Addr(children));
+    fn xml_element(&mut self, tag: Addr, avec: Vec<HeapCellValue>, cvec: Vec<Addr>) -> Addr {
+        let attrs = Addr::HeapCell(self.heap.to_list(avec.into_iter()));
+        let children = Addr::HeapCell(self.heap.to_list(cvec.into_iter()));
 
-            result
-        }
+        let result = Addr::HeapCell(self.heap.h());
+
+        self.heap
+            .push(HeapCellValue::NamedStr(3, clause_name!("element"), None));
+        self.heap.push(HeapCellValue::Addr(tag));
+        self.heap.push(HeapCellValue::Addr(attrs));
+        self.heap.push(HeapCellValue::Addr(children));
+
+        result
     }
 
-    pub(super) fn html_node_to_term(
+    pub(super) fn xml_node_to_term(
         &mut self,
-        indices: &mut IndexStore,
-        node: select::node::Node,
+        doc: &roxmltree::Document,
+        node: roxmltree::Node,
+        options: &XmlOptions,
     ) -> Addr {
-        match node.name() {
-            None => {
-                let string = String::from(node.text());
-                self.heap.put_complete_string(&string)
-            }
-            Some(name) => {
-                let mut avec = Vec::new();
-                for attr in node.attrs() {
-                    let chars = clause_name!(String::from(attr.0), self.atom_tbl);
-                    let name = self.heap.to_unifiable(HeapCellValue::Atom(chars, None));
+        let mut avec = Vec::new();
 
-                    let value = self.heap.put_complete_string(&String::from(attr.1));
+        if options.line_numbers {
+            let line = doc.text_pos_at(node.range().start).row as isize + options.line_offset;
+            let name = self
+                .heap
+                .to_unifiable(HeapCellValue::Atom(clause_name!("$line"), None));
 
-                    avec.push(HeapCellValue::Addr(Addr::HeapCell(self.heap.h())));
+            let value = Addr::Fixnum(line);
+            avec.push(self.xml_attribute(name, value));
+        }
 
-                    self.heap
-                        .push(HeapCellValue::NamedStr(2, clause_name!("="), None));
-                    self.heap.push(HeapCellValue::Addr(name));
-                    self.heap.push(HeapCellValue::Addr(value));
+        if !options.xmlns {
+            // namespace declarations are kept as attributes on the
+            // element that makes them.
+            let inherited = node
+                .parent_element()
+                .map(|parent| parent.namespaces())
+                .unwrap_or(&[]);
+
+            for ns in node.namespaces() {
+                if inherited.contains(ns) {
+                    continue;
                 }
-                let attrs = Addr::HeapCell(self.heap.to_list(avec.into_iter()));
 
-                let mut cvec = Vec::new();
-                for child in node.children() {
-                    cvec.push(self.html_node_to_term(indices, child));
+                let key = match ns.name() {
+                    Some(prefix) => format!("xmlns:{}", prefix),
+                    None => "xmlns".to_string(),
+                };
+
+                let name = self.xml_name(None, None, &key, options);
+                let value = self.xml_text_value(ns.uri(), options.atom_values);
+
+                avec.push(self.xml_attribute(name, value));
+            }
+        }
+
+        for attr in node.attributes() {
+            let prefix = attr.namespace().and_then(|uri| node.lookup_prefix(uri));
+            let name = self.xml_name(attr.namespace(), prefix, attr.name(), options);
+            let value = self.xml_text_value(attr.value(), options.atom_values);
+
+            avec.push(self.xml_attribute(name, value));
+        }
+
+        let children: Vec<_> = node
+            .children()
+            .filter(|child| child.is_element() || child.is_text())
+            .collect();
+
+        let mut cvec = Vec::new();
+        let num_children = children.len();
+
+        for (i, child) in children.into_iter().enumerate() {
+            if child.is_text() {
+                let text = child.text().unwrap_or("");
+
+                if let Some(text) = options.space.apply(text, i == 0, i + 1 == num_children) {
+                    cvec.push(self.heap.put_complete_string(text));
                 }
-                let children = Addr::HeapCell(self.heap.to_list(cvec.into_iter()));
+            } else {
+                cvec.push(self.xml_node_to_term(doc, child, options));
+            }
+        }
 
-                let chars = clause_name!(String::from(name), self.atom_tbl);
-                let tag = self.heap.to_unifiable(HeapCellValue::Atom(chars, None));
+        let tag_name = node.tag_name();
+        let prefix = tag_name.namespace().and_then(|uri| node.lookup_prefix(uri));
+        let tag = self.xml_name(tag_name.namespace(), prefix, tag_name.name(), options);
 
-                let result = Addr::HeapCell(self.heap.h());
+        self.xml_element(tag, avec, cvec)
+    }
 
-                self.heap
-                    .push(HeapCellValue::NamedStr(3, clause_name!("element"), None));
-                self.heap.push(HeapCellValue::Addr(tag));
-                self.heap.push(HeapCellValue::Addr(attrs));
-                self.heap.push(HeapCellValue::Addr(children));
+    pub(super) fn html_node_to_term(
+        &mut self,
+        node: select::node::Node,
+        options: &XmlOptions,
+    ) -> Addr {
+        let mut avec = Vec::new();
 
-                result
+        for attr in node.attrs() {
+            let name = self.xml_name(None, None, attr.0, options);
+            let value = self.xml_text_value(attr.1, options.atom_values);
+
+            avec.push(self.xml_attribute(name, value));
+        }
+
+        let children: Vec<_> = node
+            .children()
+            .filter(|child| child.name().is_some() || child.as_text().is_some())
+            .collect();
+
+        let mut cvec = Vec::new();
+        let num_children = children.len();
+
+        for (i, child) in children.into_iter().enumerate() {
+            match child.name() {
+                None => {
+                    let text = child.text();
+
+                    if let Some(text) = options.space.apply(&text, i == 0, i + 1 == num_children) {
+                        cvec.push(self.heap.put_complete_string(text));
+                    }
+                }
+                Some(_) => {
+                    cvec.push(self.html_node_to_term(child, options));
+                }
             }
         }
+
+        let name = node.name().unwrap_or("");
+        let tag = self.xml_name(None, None, name, options);
+
+        self.xml_element(tag, avec, cvec)
     }
 }
 
+// the origin of '$monotonic_time'/1, fixed at its first use.
+fn monotonic_epoch() -> Instant {
+    lazy_static! {
+        static ref START: Instant = Instant::now();
+    }
+
+    *START
+}
+
 fn rng() -> &'static dyn SecureRandom {
     use std::ops::Deref;
 
@@ -5581,3 +6884,316 @@ impl hkdf::KeyType for MyKey<usize> {
         self.0
     }
 }
+
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum XmlSpace {
+    Preserve,
+    Sgml,
+    Remove,
+}
+
+impl XmlSpace {
+    // the text of a child node as it appears in the parsed term, or
+    // None if it is dropped.
+    fn apply(self, text: &str, first: bool, last: bool) -> Option<&str> {
+        let text = match self {
+            XmlSpace::Preserve => text,
+            XmlSpace::Sgml => {
+                let mut text = text;
+
+                if first {
+                    text = text
+                        .strip_prefix("\r\n")
+                        .or_else(|| text.strip_prefix('\n'))
+                        .unwrap_or(text);
+                }
+
+                if last {
+                    text = text
+                        .strip_suffix("\r\n")
+                        .or_else(|| text.strip_suffix('\n'))
+                        .unwrap_or(text);
+                }
+
+                text
+            }
+            XmlSpace::Remove => text.trim(),
+        };
+
+        if text.is_empty() {
+            None
+        } else {
+            Some(text)
+        }
+    }
+}
+
+pub(super) struct XmlOptions {
+    space: XmlSpace,
+    xmlns: bool,
+    atom_values: bool,
+    line_numbers: bool,
+    line_offset: isize,
+}
+
+impl Default for XmlOptions {
+    fn default() -> Self {
+        XmlOptions {
+            space: XmlSpace::Preserve,
+            xmlns: false,
+            atom_values: false,
+            line_numbers: false,
+            line_offset: 0,
+        }
+    }
+}
+
+// reads an XML document from a stream one child of the root at a
+// time, so that documents larger than memory can be processed. the
+// scanner only tracks markup boundaries; the children it returns are
+// parsed by roxmltree.
+struct XmlScanner {
+    stream: PrologStream,
+    lines: usize,
+    // the characters read past the end of a text child, which are
+    // put back into the stream.
+    pending: Vec<char>,
+}
+
+impl XmlScanner {
+    fn new(stream: PrologStream) -> Self {
+        XmlScanner {
+            stream,
+            lines: 0,
+            pending: vec![],
+        }
+    }
+
+    // the characters read from the stream but not consumed, in the
+    // order expected by Stream::pause_stream.
+    fn take_buf(&mut self) -> Vec<std::io::Result<char>> {
+        let mut buf = self.stream.take_buf();
+        buf.extend(self.pending.drain(..).rev().map(Ok));
+        buf
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        match self.stream.next() {
+            Some(Ok(c)) => {
+                if c == '\n' {
+                    self.lines += 1;
+                }
+
+                Some(c)
+            }
+            _ => None,
+        }
+    }
+
+    // reads up to and including the terminator, appending to buf.
+    fn read_until(&mut self, terminator: &str, buf: &mut String) -> Option<()> {
+        loop {
+            buf.push(self.next_char()?);
+
+            if buf.ends_with(terminator) {
+                return Some(());
+            }
+        }
+    }
+
+    // reads the rest of a tag after its '<', honouring quoted
+    // attribute values.
+    fn read_tag(&mut self, buf: &mut String) -> Option<()> {
+        let mut quote = None;
+
+        loop {
+            let c = self.next_char()?;
+            buf.push(c);
+
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None if c == '"' || c == '\'' => quote = Some(c),
+                None if c == '>' => return Some(()),
+                None => {}
+            }
+        }
+    }
+
+    // reads the markup that follows a '<' which is not the start of
+    // an element: a comment, a CDATA section, a processing
+    // instruction or a declaration.
+    fn read_markup(&mut self, c: char, buf: &mut String) -> Option<()> {
+        buf.push(c);
+
+        if c == '?' {
+            return self.read_until("?>", buf);
+        }
+
+        loop {
+            buf.push(self.next_char()?);
+
+            if buf.ends_with("<!--") {
+                return self.read_until("-->", buf);
+            } else if buf.ends_with("<![CDATA[") {
+                return self.read_until("]]>", buf);
+            } else if !"<![CDATA[".starts_with(buf.as_str()) && !"<!--".starts_with(buf.as_str()) {
+                // a declaration, possibly with an internal subset.
+                let mut depth = 0;
+
+                loop {
+                    match buf.chars().last() {
+                        Some('[') => depth += 1,
+                        Some(']') => depth -= 1,
+                        Some('>') if depth == 0 => return Some(()),
+                        _ => {}
+                    }
+
+                    buf.push(self.next_char()?);
+                }
+            }
+        }
+    }
+
+    // reads the rest of a text child, up to the next tag, comment or
+    // processing instruction, which is left in pending. CDATA sections
+    // are part of the text. returns whether the text is the last
+    // child of the root.
+    fn read_text(&mut self, buf: &mut String) -> Option<bool> {
+        loop {
+            let c = self.next_char()?;
+
+            if c != '<' {
+                buf.push(c);
+                continue;
+            }
+
+            let c = self.next_char()?;
+
+            if c == '!' {
+                let d = self.next_char()?;
+
+                if d == '[' {
+                    buf.push_str("<![");
+                    self.read_until("]]>", buf)?;
+                    continue;
+                }
+
+                self.pending = vec!['<', c, d];
+                return Some(false);
+            }
+
+            self.pending = vec!['<', c];
+            return Some(c == '/');
+        }
+    }
+
+    // the start tag of the root element, preceded by the DOCTYPE
+    // declaration, if there is one.
+    fn root(&mut self) -> Option<String> {
+        let mut doctype = String::new();
+
+        loop {
+            if self.next_char()? != '<' {
+                continue;
+            }
+
+            let mut buf = String::from("<");
+            let c = self.next_char()?;
+
+            if c == '?' || c == '!' {
+                self.read_markup(c, &mut buf)?;
+
+                if buf.starts_with("<!DOCTYPE") {
+                    doctype = buf;
+                }
+            } else {
+                buf.push(c);
+                self.read_tag(&mut buf)?;
+
+                return Some(doctype + &buf);
+            }
+        }
+    }
+
+    // the number of lines before the next child of the root, its
+    // text, and whether it is a text child that ends the root, or
+    // None at the end of the root.
+    fn child(&mut self, root: &str) -> Option<(usize, String, bool)> {
+        if root.ends_with("/>") {
+            return None;
+        }
+
+        loop {
+            let lines_before = self.lines;
+            let c = self.next_char()?;
+
+            if c != '<' {
+                let mut buf = c.to_string();
+                let last = self.read_text(&mut buf)?;
+
+                return Some((lines_before, buf, last));
+            }
+
+            let mut buf = String::from("<");
+            let c = self.next_char()?;
+
+            match c {
+                '/' => return None,
+                '?' | '!' => {
+                    self.read_markup(c, &mut buf)?;
+
+                    if buf.starts_with("<![CDATA[") {
+                        let last = self.read_text(&mut buf)?;
+                        return Some((lines_before, buf, last));
+                    }
+                }
+                _ => {
+                    buf.push(c);
+                    self.read_tag(&mut buf)?;
+
+                    let mut depth = if buf.ends_with("/>") { 0 } else { 1 };
+
+                    while depth > 0 {
+                        let c = self.next_char()?;
+                        buf.push(c);
+
+                        if c != '<' {
+                            continue;
+                        }
+
+                        let c = self.next_char()?;
+
+                        match c {
+                            '/' => {
+                                buf.push(c);
+                                self.read_tag(&mut buf)?;
+                                depth -= 1;
+                            }
+                            '?' | '!' => {
+                                buf.pop();
+
+                                let mut markup = String::from("<");
+                                self.read_markup(c, &mut markup)?;
+
+                                buf.push_str(&markup);
+                            }
+                            _ => {
+                                buf.push(c);
+
+                                let start = buf.len();
+                                self.read_tag(&mut buf)?;
+
+                                if !buf[start..].ends_with("/>") {
+                                    depth += 1;
+                                }
+                            }
+                        }
+                    }
+
+                    return Some((lines_before, buf, false));
+                }
+            }
+        }
+    }
+}
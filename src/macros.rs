@@ -197,6 +197,9 @@ macro_rules! from_constant {
 
                 HeapCellValue::Addr(Addr::PStrLocation(h, 0))
             }
+            &Constant::OpaqueString(ref s) => {
+                HeapCellValue::OpaqueString(s.clone())
+            }
             &Constant::Usize(u) => {
                 HeapCellValue::Addr(Addr::Usize(u))
             }
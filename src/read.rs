@@ -74,8 +74,57 @@ pub mod readline {
             Stream::from(Self::new(pending_input))
         }
 
+        // the query of the history event !N or !! in text, if it is
+        // one. an event not in the history is reported as an error.
+        fn history_event(&self, text: &str) -> Option<Result<String, String>> {
+            let event = text.trim();
+            let event = event.strip_suffix('.').unwrap_or(event).trim_end();
+            let history = self.rl.history();
+
+            let index = match event.strip_prefix('!')? {
+                "!" => history.len().checked_sub(1),
+                n if !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) => {
+                    n.parse::<usize>().ok().and_then(|n| n.checked_sub(1))
+                }
+                _ => return None,
+            };
+
+            match index.and_then(|index| history.get(index)) {
+                Some(query) => Some(Ok(query.clone())),
+                None => Some(Err(event.to_string())),
+            }
+        }
+
+        // the queries in the history, oldest first.
+        pub(crate) fn history(&self) -> Vec<String> {
+            self.rl
+                .history()
+                .iter()
+                .map(|query| query.trim_end().to_string())
+                .collect()
+        }
+
         fn call_readline(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-            match self.rl.readline(get_prompt()) {
+            let mut result = self.rl.readline(get_prompt());
+
+            // history events are expanded only at the start of a
+            // query. the query replaces the event in the history.
+            while let (Ok(text), true) = (&result, unsafe { PROMPT }) {
+                match self.history_event(text) {
+                    Some(Ok(query)) => {
+                        println!("{}", query.trim_end());
+                        result = Ok(query);
+                        break;
+                    }
+                    Some(Err(event)) => {
+                        println!("{}: event not found", event);
+                        result = self.rl.readline(get_prompt());
+                    }
+                    None => break,
+                }
+            }
+
+            match result {
                 Ok(text) => {
                     *self.pending_input.get_mut() = text;
                     self.pending_input.set_position(0);
@@ -185,6 +234,7 @@ impl MachineState {
         parser.devour_whitespace()?;
 
         inner.add_lines_read(parser.num_lines_read());
+        inner.advance_line_position(parser.num_lines_read(), parser.col_num());
 
         let result = parser.eof();
         let buf = stream.take_buf();
@@ -202,17 +252,23 @@ impl MachineState {
     ) -> Result<TermWriteResult, ParserError> {
         let mut stream = parsing_stream(inner.clone())?;
 
-        let (term, num_lines_read) = {
+        let (term, num_lines_read, col_num) = {
             let prior_num_lines_read = inner.lines_read();
             let mut parser = Parser::new(&mut stream, atom_tbl, self.flags);
 
             parser.add_lines_read(prior_num_lines_read);
 
             let term = parser.read_term(&CompositeOpDir::new(op_dir, None))?;
-            (term, parser.num_lines_read() - prior_num_lines_read)
+
+            (
+                term,
+                parser.num_lines_read() - prior_num_lines_read,
+                parser.col_num(),
+            )
         };
 
         inner.add_lines_read(num_lines_read);
+        inner.advance_line_position(num_lines_read, col_num);
 
         // 'pausing' the stream saves the pending top buffer
         // created by the parsing stream, which was created in this
@@ -1,10 +1,13 @@
 :- module('$toplevel', [argv/1,
-                        copy_term/3]).
+                        copy_term/3,
+                        h/0]).
 
 :- use_module(library(charsio)).
 :- use_module(library(files)).
+:- use_module(library(format), [portray_output_chars/2]).
 :- use_module(library(iso_ext)).
 :- use_module(library(lists)).
+:- use_module(library(messages)).
 :- use_module(library(si)).
 
 :- use_module(library('$project_atts')).
@@ -46,6 +49,11 @@ delegate_task([], Goals0) :-
     reverse(Goals0, Goals),
     run_goals(Goals),
     repl.
+delegate_task([Arg0|Args0], Goals0) :-
+    memory_limit_option(Arg0, Flag),
+    !,
+    set_memory_limit(Flag, Args0, Args),
+    delegate_task(Args, Goals0).
 delegate_task([Arg0|Args], Goals0) :-
     (   member(Arg0, ["-h", "--help"]) -> print_help
     ;   member(Arg0, ["-v", "--version"]) -> print_version
@@ -65,6 +73,12 @@ print_help :-
     write('Print version information and exit'), nl,
     write('   -g, --goal GOAL      '),
     write('Run the query GOAL'), nl,
+    write('   --heap-limit BYTES   '),
+    write('Limit the size of the heap'), nl,
+    write('   --stack-limit BYTES  '),
+    write('Limit the size of the stack'), nl,
+    write('   --trail-limit BYTES  '),
+    write('Limit the size of the trail'), nl,
     % write('                        '),
     halt.
 
@@ -82,6 +96,17 @@ gather_goal(Type, Args0, Goals) :-
     Gs =.. [Type, Gs1],
     delegate_task(Args, [Gs|Goals]).
 
+memory_limit_option("--heap-limit", heap_limit).
+memory_limit_option("--stack-limit", stack_limit).
+memory_limit_option("--trail-limit", trail_limit).
+
+set_memory_limit(Flag, Args0, Args) :-
+    (   Args0 = [Cs|Args],
+        catch(number_chars(Bytes, Cs), _, false) ->
+        catch(set_prolog_flag(Flag, Bytes), E, print_exception(E))
+    ;   print_help, halt
+    ).
+
 arg_type(g).
 arg_type(t).
 arg_type(g(_)).
@@ -117,7 +142,7 @@ run_goals([Goal|_]) :-
     halt.
 
 repl :-
-    catch(read_and_match, E, print_exception(E)),
+    catch_with_backtrace(read_and_match, E, print_exception(E)),
     false. %% this is for GC, until we get actual GC.
 repl :-
     repl.
@@ -134,9 +159,26 @@ expand_op_list([Op | OtherOps], Pred, Spec, [(:- op(Pred, Spec, Op)) | OtherResu
 
 
 read_and_match :-
-    '$read_query_term'(_, Term, _, _, VarList),
+    '$set_trace'(false),
+    read_query_term(Term, VarList),
     instruction_match(Term, VarList).
 
+%% While a query is read, $ is a prefix operator of priority 1, so
+%% that $X can be written wherever X can. The $ operator in effect
+%% before, such as that of library(debug), is restored once the query
+%% is read.
+
+read_query_term(Term, VarList) :-
+    (  current_op(Priority, Spec, $),
+       memberchk(Spec, [fx, fy]) ->
+       true
+    ;  Priority = 0,
+       Spec = fx
+    ),
+    setup_call_cleanup(op(1, fx, $),
+                       '$read_query_term'(_, Term, _, _, VarList),
+                       op(Priority, Spec, $)).
+
 
 instruction_match(Term, VarList) :-
     (  var(Term) ->
@@ -174,13 +216,92 @@ submit_query_and_print_results_(_, _) :-
 
 
 submit_query_and_print_results(Term0, VarList) :-
-    expand_goal(call(Term0), user, call(Term)),
+    toplevel_variables(Term0, VarList, Term1),
+    expand_goal(call(Term1), user, call(Term)),
     !,
     setup_call_cleanup(bb_put('$first_answer', true),
                        submit_query_and_print_results_(Term, VarList),
                        bb_put('$first_answer', false)).
 
 
+%% $X in an argument of a goal of a query stands for the value X was
+%% bound to in the last answer that bound it. If no answer has bound
+%% X, it is left as it is. $G in the place of a goal is left to mean
+%% the $/1 of library(debug).
+
+toplevel_variables(Goal0, VarList, Goal) :-
+    (  var(Goal0) ->
+       Goal = Goal0
+    ;  toplevel_control_goal(Goal0) ->
+       Goal0 =.. [F | Goals0],
+       maplist(toplevel_variables_(VarList), Goals0, Goals),
+       Goal =.. [F | Goals]
+    ;  toplevel_term_variables(Goal0, VarList, Goal)
+    ).
+
+toplevel_variables_(VarList, Goal0, Goal) :-
+    toplevel_variables(Goal0, VarList, Goal).
+
+toplevel_control_goal((_, _)).
+toplevel_control_goal((_ ; _)).
+toplevel_control_goal((_ -> _)).
+toplevel_control_goal(\+ _).
+toplevel_control_goal($(_)).
+
+toplevel_term_variables(Term0, VarList, Term) :-
+    (  var(Term0) ->
+       Term = Term0
+    ;  Term0 = $(Var),
+       var(Var),
+       member(Name = Var0, VarList),
+       Var0 == Var,
+       toplevel_variable_value(Name, Value) ->
+       Term = Value
+    ;  compound(Term0) ->
+       Term0 =.. [F | Args0],
+       maplist(toplevel_term_variables_(VarList), Args0, Args),
+       (  Args == Args0 ->
+          Term = Term0
+       ;  Term =.. [F | Args]
+       )
+    ;  Term = Term0
+    ).
+
+toplevel_term_variables_(VarList, Term0, Term) :-
+    toplevel_term_variables(Term0, VarList, Term).
+
+toplevel_variable_value(Name, Value) :-
+    toplevel_variable_key(Name, Key),
+    bb_get(Key, Value).
+
+toplevel_variable_key(Name, Key) :-
+    atom_concat('$toplevel_variable_', Name, Key).
+
+record_toplevel_variables(VarList) :-
+    maplist(record_toplevel_variable, VarList).
+
+record_toplevel_variable(Name = Value) :-
+    (  var(Value) ->
+       true
+    ;  toplevel_variable_key(Name, Key),
+       bb_put(Key, Value)
+    ).
+
+%% h/0 lists the queries in the history, for !N to run the Nth of them
+%% again and !! the last.
+
+h :-
+    '$query_history'(Queries),
+    foldl(write_history_query, Queries, 1, _).
+
+write_history_query(Query, N0, N) :-
+    write('   '),
+    write(N0),
+    write('  '),
+    write_chars(Query),
+    nl,
+    N is N0 + 1.
+
 needs_bracketing(Value, Op) :-
     catch((functor(Value, F, _),
 	       current_op(EqPrec, EqSpec, Op),
@@ -197,7 +318,7 @@ needs_bracketing(Value, Op) :-
        memberchk(EqSpec, [fx,xfx,yfx])
     ).
 
-write_goal(G, VarList, MaxDepth) :-
+write_goal(G, VarList, Options) :-
     (  G = (Var = Value) ->
        (  var(Value) ->
 	      select((Var = _), VarList, NewVarList)
@@ -207,16 +328,16 @@ write_goal(G, VarList, MaxDepth) :-
        write(' = '),
        (  needs_bracketing(Value, (=)) ->
 	      write('('),
-	      write_term(Value, [quoted(true), variable_names(NewVarList), max_depth(MaxDepth)]),
+	      write_answer_term(Value, NewVarList, Options),
 	      write(')')
-       ;  write_term(Value, [quoted(true), variable_names(NewVarList), max_depth(MaxDepth)])
+       ;  write_answer_term(Value, NewVarList, Options)
        )
     ;  G == [] ->
        write('true')
-    ;  write_term(G, [quoted(true), variable_names(VarList), max_depth(MaxDepth)])
+    ;  write_answer_term(G, VarList, Options)
     ).
 
-write_last_goal(G, VarList, MaxDepth) :-
+write_last_goal(G, VarList, Options) :-
     (  G = (Var = Value) ->
        (  var(Value) ->
 	      select((Var = _), VarList, NewVarList)
@@ -226,9 +347,9 @@ write_last_goal(G, VarList, MaxDepth) :-
        write(' = '),
        (  needs_bracketing(Value, (=)) ->
 	      write('('),
-	      write_term(Value, [quoted(true), variable_names(NewVarList), max_depth(MaxDepth)]),
+	      write_answer_term(Value, NewVarList, Options),
 	      write(')')
-       ;  write_term(Value, [quoted(true), variable_names(NewVarList), max_depth(MaxDepth)]),
+       ;  write_answer_term(Value, NewVarList, Options),
 	      (  trailing_period_is_ambiguous(Value) ->
 	         write(' ')
 	      ;  true
@@ -236,16 +357,123 @@ write_last_goal(G, VarList, MaxDepth) :-
        )
     ;  G == [] ->
        write('true')
-    ;  write_term(G, [quoted(true), variable_names(VarList), max_depth(MaxDepth)])
+    ;  write_answer_term(G, VarList, Options)
     ).
 
-write_eq((G1, G2), VarList, MaxDepth) :-
+write_eq((G1, G2), VarList, Options) :-
     !,
-    write_goal(G1, VarList, MaxDepth),
+    write_goal(G1, VarList, Options),
     write(', '),
-    write_eq(G2, VarList, MaxDepth).
-write_eq(G, VarList, MaxDepth) :-
-    write_last_goal(G, VarList, MaxDepth).
+    write_eq(G2, VarList, Options).
+write_eq(G, VarList, Options) :-
+    write_last_goal(G, VarList, Options).
+
+%% portray(true) has the subterms of answers for which user:portray/1
+%% succeeds written as it writes them. The output of portray/1 stands
+%% in for the subterm as the name of a fresh variable.
+
+write_answer_term(Term, VarList, Options0) :-
+    findall(Option, (member(Option, Options0), Option \= portray(_)), Options),
+    (  memberchk(portray(true), Options0),
+       '$predicate_defined'(user, portray, 1),
+       acyclic_term(Term) ->
+       portray_subterms(Term, Term1, VarList, VarList1)
+    ;  Term1 = Term,
+       VarList1 = VarList
+    ),
+    write_term(Term1, [variable_names(VarList1) | Options]).
+
+portray_subterms(Term0, Term, VarList0, VarList) :-
+    (  var(Term0) ->
+       Term = Term0,
+       VarList = VarList0
+    ;  portray_output_chars(Term0, Chars) ->
+       atom_chars(Name, Chars),
+       VarList = [Name = Term | VarList0]
+    ;  compound(Term0) ->
+       Term0 =.. [F | Args0],
+       foldl(portray_subterms, Args0, Args, VarList0, VarList),
+       Term =.. [F | Args]
+    ;  Term = Term0,
+       VarList = VarList0
+    ).
+
+%% The answer write options in effect, those of the
+%% answer_write_options flag unless "w" was pressed to write answers
+%% in full.
+
+effective_answer_write_options(Options) :-
+    current_prolog_flag(answer_write_options, Options0),
+    (  bb_get('$answer_write_mode', write) ->
+       findall(Option,
+               (  member(Option, Options0),
+                  Option \= max_depth(_),
+                  Option \= portray(_)
+               ),
+               Options1),
+       Options = [max_depth(0) | Options1]
+    ;  Options = Options0
+    ).
+
+%% Answers longer than this many characters are printed only if the
+%% user confirms it.
+
+huge_answer_length(10000).
+
+write_answer(ThreadedGoals, VarList) :-
+    write_answer(ThreadedGoals, VarList, '').
+
+%% End is written after the answer only if it is printed in full.
+
+write_answer(ThreadedGoals, VarList, End) :-
+    effective_answer_write_options(Options),
+    answer_chars(ThreadedGoals, VarList, Options, Chars),
+    length(Chars, Length),
+    huge_answer_length(MaxLength),
+    (  Length > MaxLength ->
+       write('The answer has '),
+       write(Length),
+       write(' characters. Print it? (y/n) '),
+       flush_output,
+       get_single_char(C),
+       nl,
+       write('   '),
+       (  C == y ->
+          write_chars(Chars),
+          write(End)
+       ;  write_answer_skeleton(ThreadedGoals)
+       )
+    ;  write_chars(Chars),
+       write(End)
+    ).
+
+%% An answer not printed in full is printed with its bindings elided.
+
+write_answer_skeleton((G1, G2)) :-
+    !,
+    write_answer_skeleton(G1),
+    write(', '),
+    write_answer_skeleton(G2).
+write_answer_skeleton(G) :-
+    (  G = (Var = _) ->
+       write(Var),
+       write(' = ...')
+    ;  write('...')
+    ).
+
+answer_chars(ThreadedGoals, VarList, Options, Chars) :-
+    '$open_chars_output_stream'(S),
+    current_output(Out),
+    set_output(S),
+    catch(write_eq(ThreadedGoals, VarList, Options), E, true),
+    set_output(Out),
+    (  nonvar(E) -> throw(E)
+    ;  '$chars_output_stream_contents'(S, Chars)
+    ).
+
+write_chars(Chars) :-
+    current_output(Out),
+    '$put_chars'(Out, Chars).
 
 graphic_token_char(C) :-
     memberchk(C, ['#', '$', '&', '*', '+', '-', '.', ('/'), ':',
@@ -262,11 +490,56 @@ trailing_period_is_ambiguous(Value) :-
     ValueChars \== ['.'],
     graphic_token_char(Char).
 
+%% Residual goals are written as the user would call them: without
+%% the module qualifier of their library if user can call them
+%% unqualified, and without user: on their goal arguments.
+
+residual_goal(Module:Goal0, Goal) :-
+    (  atom(Goal0) -> true
+    ;  compound(Goal0)
+    ),
+    !,
+    unqualified_goal_args(Module:Goal0, Goal1),
+    functor(Goal1, Name, Arity),
+    (  '$predicate_defined'(user, Name, Arity) ->
+       Goal = Goal1
+    ;  Goal = Module:Goal1
+    ).
+residual_goal(Goal, Goal).
+
+unqualified_goal_args(Module:Goal0, Goal) :-
+    (  predicate_property(Module:Goal0, meta_predicate(MetaSpecs)) ->
+       Goal0 =.. [Name | Args0],
+       maplist(unqualified_goal_arg, MetaSpecs, Args0, Args),
+       Goal =.. [Name | Args]
+    ;  Goal = Goal0
+    ).
+
+unqualified_goal_arg(Spec, Arg0, Arg) :-
+    (  integer(Spec) ->
+       unqualified_goal(Arg0, Arg)
+    ;  Arg = Arg0
+    ).
+
+unqualified_goal(Goal0, Goal) :-
+    (  var(Goal0) ->
+       Goal = Goal0
+    ;  Goal0 = user:Goal1 ->
+       unqualified_goal(Goal1, Goal)
+    ;  Goal0 = (Goal1, Goal2) ->
+       Goal = (Goal3, Goal4),
+       unqualified_goal(Goal1, Goal3),
+       unqualified_goal(Goal2, Goal4)
+    ;  Goal = Goal0
+    ).
+
 write_eqs_and_read_input(B, VarList) :-
+    record_toplevel_variables(VarList),
     term_variables(VarList, Vars0),
     '$term_attributed_variables'(VarList, AttrVars),
     '$project_atts':project_attributes(Vars0, AttrVars),
-    copy_term(AttrVars, AttrVars, AttrGoals),
+    copy_term(AttrVars, AttrVars, AttrGoals0),
+    maplist(residual_goal, AttrGoals0, AttrGoals),
     term_variables(AttrGoals, AttrGoalVars),
     append([Vars0, AttrGoalVars, AttrVars], Vars),
     charsio:extend_var_list(Vars, VarList, NewVarList, fabricated),
@@ -286,33 +559,34 @@ write_eqs_and_read_input(B, VarList) :-
        (  Goals == [] ->
 	      write('true.'), nl
        ;  loader:thread_goals(Goals, ThreadedGoals, (',')),
-	      write_eq(ThreadedGoals, NewVarList0, 20),
-	      write('.'),
+	      write_answer(ThreadedGoals, NewVarList0, '.'),
 	      nl
        )
     ;  loader:thread_goals(Goals, ThreadedGoals, (',')),
-       write_eq(ThreadedGoals, NewVarList0, 20),
+       write_answer(ThreadedGoals, NewVarList0),
        read_input(ThreadedGoals, NewVarList0)
     ).
 
 read_input(ThreadedGoals, NewVarList) :-
     get_single_char(C),
     (  C = w ->
+       bb_put('$answer_write_mode', write),
        nl,
        write('   '),
-       write_eq(ThreadedGoals, NewVarList, 0),
+       write_answer(ThreadedGoals, NewVarList),
        read_input(ThreadedGoals, NewVarList)
     ;  C = p ->
+       bb_put('$answer_write_mode', print),
        nl,
        write('   '),
-       write_eq(ThreadedGoals, NewVarList, 20),
+       write_answer(ThreadedGoals, NewVarList),
        read_input(ThreadedGoals, NewVarList)
     ;  member(C, [';', ' ', n]) ->
        nl, write(';  '), false
     ;  C = h ->
        help_message,
        read_input(ThreadedGoals, NewVarList)
-    ;  member(C, ['\n', .]) ->
+    ;  member(C, ['\n', ., end_of_file]) ->
        nl, write(';  ...'), nl
     ;  read_input(ThreadedGoals, NewVarList)
     ).
@@ -322,8 +596,8 @@ help_message :-
     write('SPACE, "n" or ";": next solution, if any\n'),
     write('RETURN or ".": stop enumeration\n'),
     write('"h": display this help message\n'),
-    write('"w": write terms without depth limit\n'),
-    write('"p": print terms with depth limit\n\n').
+    write('"w": write answers without depth limit from now on\n'),
+    write('"p": print answers with the answer_write_options flag from now on\n\n').
 
 gather_query_vars([_ = Var | Vars], QueryVars) :-
     (  var(Var) ->
@@ -367,6 +641,75 @@ gather_equations([Var = Value | Pairs], OrigVarList, Goals) :-
        gather_equations(Pairs, OrigVarList, Goals0)
     ).
 
+%% The machine calls interrupt_menu(Tracing) when a query is
+%% interrupted by Ctrl-C and a terminal is available to read the
+%% choice from, and retries the interrupted call after it. Tracing is
+%% suspended while the menu runs, Tracing being true if it was on.
+
+interrupt_menu(Tracing) :-
+    interrupt_prompt(Tracing),
+    '$return_from_verify_attr'.
+
+interrupt_prompt(Tracing) :-
+    nl,
+    write('Action (h for help) ? '),
+    flush_output,
+    get_single_char(C),
+    (  interrupt_action(C, Tracing) -> true
+    ;  interrupt_prompt(Tracing)
+    ).
+
+%% interrupt_action(C, Tracing) succeeds if the query is to be
+%% resumed, and fails if the prompt is to be shown again.
+
+interrupt_action(c, Tracing) :-
+    write(continue), nl,
+    '$set_trace'(Tracing).
+interrupt_action(a, _) :-
+    write(abort), nl,
+    throw(error('$interrupt_thrown', repl)).
+interrupt_action(t, _) :-
+    write(trace), nl,
+    '$set_trace'(true).
+interrupt_action(b, _) :-
+    write(backtrace), nl,
+    '$get_interrupt_backtrace'(Frames),
+    write_interrupt_frames(Frames, 0),
+    false.
+interrupt_action(e, _) :-
+    write(exit), nl,
+    halt.
+interrupt_action(C, _) :-
+    \+ member(C, [a,b,c,e,t]),
+    write(help), nl,
+    write('   a: abort       b: backtrace'), nl,
+    write('   c: continue    e: exit'), nl,
+    write('   t: trace       h: help'), nl,
+    false.
+
+%% The frames of the toplevel itself are omitted.
+
+write_interrupt_frames([], _).
+write_interrupt_frames([frame(Module:PI, ClauseNum)|Frames], N0) :-
+    (  Module == '$toplevel' ->
+       N = N0
+    ;  write('   ['), write(N0), write('] '),
+       writeq(Module:PI),
+       write(' (clause '), write(ClauseNum), write(')'), nl,
+       N is N0 + 1
+    ),
+    write_interrupt_frames(Frames, N).
+
+%% While tracing, the machine calls trace_call(Goal) before each call
+%% to a predicate visible in user, and retries the call after it.
+
+trace_call(Goal) :-
+    write('   Call: '),
+    writeq(Goal),
+    nl,
+    '$set_trace'(resume),
+    '$return_from_verify_attr'.
+
 print_exception(E) :-
     (  E == error('$interrupt_thrown', repl) -> nl % print the
     % exception on a
@@ -374,9 +717,7 @@ print_exception(E) :-
     % "^C".
     ;  true
     ),
-    write_term('caught: ', [quoted(false), max_depth(20)]),
-    writeq(E),
-    nl.
+    print_message(error, unhandled_exception(E)).
 
 print_exception_with_check(E) :-
     (  E = error(_, _:_) -> true % if the error source contains a line
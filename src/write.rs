@@ -131,6 +131,9 @@ impl fmt::Display for FactInstruction {
             &FactInstruction::GetValue(ref x, ref a) => {
                 write!(f, "get_value {}, A{}", x, a)
             }
+            &FactInstruction::GetValueWithOccursCheck(ref x, ref a, occurs_check) => {
+                write!(f, "get_value {}, A{}, {}", x, a, occurs_check.as_str())
+            }
             &FactInstruction::GetVariable(ref x, ref a) => {
                 write!(f, "fact:get_variable {}, A{}", x, a)
             }
@@ -143,9 +146,15 @@ impl fmt::Display for FactInstruction {
             &FactInstruction::UnifyLocalValue(ref r) => {
                 write!(f, "unify_local_value {}", r)
             }
+            &FactInstruction::UnifyLocalValueWithOccursCheck(ref r, occurs_check) => {
+                write!(f, "unify_local_value {}, {}", r, occurs_check.as_str())
+            }
             &FactInstruction::UnifyValue(ref r) => {
                 write!(f, "unify_value {}", r)
             }
+            &FactInstruction::UnifyValueWithOccursCheck(ref r, occurs_check) => {
+                write!(f, "unify_value {}, {}", r, occurs_check.as_str())
+            }
             &FactInstruction::UnifyVoid(n) => {
                 write!(f, "unify_void {}", n)
             }
@@ -253,6 +262,7 @@ impl fmt::Display for HeapCellValue {
                     has_tail,
                 )
             }
+            &HeapCellValue::OpaqueString(ref s) => write!(f, "\"{}\"", s),
             &HeapCellValue::Stream(ref stream) => {
                 write!(f, "$stream({})", stream.as_ptr() as usize)
             }
@@ -649,6 +659,39 @@ impl fmt::Display for ArithmeticInstruction {
             &ArithmeticInstruction::Ceiling(ref a, ref t) => write!(f, "ceiling {}, @{}", a, t),
             &ArithmeticInstruction::Floor(ref a, ref t) => write!(f, "floor {}, @{}", a, t),
             &ArithmeticInstruction::Float(ref a, ref t) => write!(f, "float {}, @{}", a, t),
+            &ArithmeticInstruction::LogBase(ref a1, ref a2, ref t) => {
+                write!(f, "log {}, {}, @{}", a1, a2, t)
+            }
+            &ArithmeticInstruction::CopySign(ref a1, ref a2, ref t) => {
+                write!(f, "copysign {}, {}, @{}", a1, a2, t)
+            }
+            &ArithmeticInstruction::NextToward(ref a1, ref a2, ref t) => {
+                write!(f, "nexttoward {}, {}, @{}", a1, a2, t)
+            }
+            &ArithmeticInstruction::Cot(ref a, ref t) => write!(f, "cot {}, @{}", a, t),
+            &ArithmeticInstruction::ACot(ref a, ref t) => write!(f, "acot {}, @{}", a, t),
+            &ArithmeticInstruction::Sinh(ref a, ref t) => write!(f, "sinh {}, @{}", a, t),
+            &ArithmeticInstruction::Cosh(ref a, ref t) => write!(f, "cosh {}, @{}", a, t),
+            &ArithmeticInstruction::Tanh(ref a, ref t) => write!(f, "tanh {}, @{}", a, t),
+            &ArithmeticInstruction::ASinh(ref a, ref t) => write!(f, "asinh {}, @{}", a, t),
+            &ArithmeticInstruction::ACosh(ref a, ref t) => write!(f, "acosh {}, @{}", a, t),
+            &ArithmeticInstruction::ATanh(ref a, ref t) => write!(f, "atanh {}, @{}", a, t),
+            &ArithmeticInstruction::Log2(ref a, ref t) => write!(f, "log2 {}, @{}", a, t),
+            &ArithmeticInstruction::FloatIntegerPart(ref a, ref t) => {
+                write!(f, "float_integer_part {}, @{}", a, t)
+            }
+            &ArithmeticInstruction::FloatFractionalPart(ref a, ref t) => {
+                write!(f, "float_fractional_part {}, @{}", a, t)
+            }
+            &ArithmeticInstruction::Msb(ref a, ref t) => write!(f, "msb {}, @{}", a, t),
+            &ArithmeticInstruction::Lsb(ref a, ref t) => write!(f, "lsb {}, @{}", a, t),
+            &ArithmeticInstruction::PopCount(ref a, ref t) => write!(f, "popcount {}, @{}", a, t),
+            &ArithmeticInstruction::SuccOrZero(ref a, ref t) => {
+                write!(f, "succ_or_zero {}, @{}", a, t)
+            }
+            &ArithmeticInstruction::Random(ref a, ref t) => write!(f, "random {}, @{}", a, t),
+            &ArithmeticInstruction::Integer(ref a, ref t) => write!(f, "integer {}, @{}", a, t),
+            &ArithmeticInstruction::Nullary(instr, ref t) => write!(f, "{} @{}", instr.name(), t),
         }
     }
 }
@@ -0,0 +1,14 @@
+:- use_module(library(between)).
+:- use_module(library(clpz)).
+:- use_module(library(dif)).
+:- use_module(library(freeze)).
+
+portray(secret(_)) :-
+    write('<hidden>').
+
+numbers(N, Ns) :-
+    findall(X, between(1, N, X), Ns).
+
+numbers_or_none(Ns) :-
+    numbers(30, Ns).
+numbers_or_none(none).
@@ -0,0 +1,13 @@
+:- use_module(library(lists)).
+:- set_prolog_flag(debug, true).
+
+a(X) :- b(X).
+
+b(X) :- c(X), true.
+b(_).
+
+c(X) :- atom_length(_, X).
+
+caller(PI) :-
+    catch_with_backtrace(a(1), error(_, context(prolog_stack(Frames), _)), true),
+    memberchk(frame(PI, 1), Frames).
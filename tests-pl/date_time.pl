@@ -0,0 +1,54 @@
+:- use_module(library(time)).
+:- use_module(library(dcgs)).
+
+show(T) :-
+    write(T),
+    nl.
+
+conversions :-
+    stamp_date_time(1591885472.5, D, 'UTC'),
+    show(D),
+    date_time_stamp(date(2020,6,11,14,24,32,-7200,-,-), S),
+    show(S),
+    date_time_stamp(date(2020,13,0), S1),
+    stamp_date_time(S1, D1, 0),
+    show(D1),
+    day_of_the_week(date(2024,2,29), W),
+    show(W).
+
+arithmetic :-
+    date_add(date(2020,1,31), months(1), D1),
+    show(D1),
+    date_add(date(2020,12,31,23,0,0,0,'UTC',-), [hours(2), minutes(30)], D2),
+    show(D2),
+    date_difference(date(2020,3,1), date(2020,2,1), Diff1),
+    show(Diff1),
+    date_difference(date(2020,1,1,1,0,0,0,'UTC',-), date(2020,1,1), Diff2),
+    show(Diff2).
+
+parsing :-
+    parse_date_time("2020-W01-1", F1, D1),
+    show(F1-D1),
+    parse_date_time("2020-06-11 14:30:00.25+02:00", iso_8601, D2),
+    show(D2),
+    parse_time("Thu, 11 Jun 2020 14:30:00 +0200", F3, S3),
+    show(F3-S3),
+    (   parse_time("2020-13-01", _) -> show(parsed)
+    ;   show(rejected)
+    ).
+
+formatting :-
+    phrase(format_time("%a %e %b %Y, %I:%M:%S.%f %p %z %Z|%j %u %w %U %W %G-W%V",
+                       date(2021,1,3,19,5,9.25,-3600,'CET',false)), Cs),
+    atom_chars(A, Cs),
+    show(A).
+
+local_zone :-
+    stamp_date_time(1591885472, D, local),
+    D = date(_,_,_,_,_,_,_,TZ,DST),
+    date_time_stamp(D, S),
+    show(S),
+    show(TZ),
+    (   ( DST == true ; DST == false ) -> show(dst_known)
+    ;   show(DST)
+    ).
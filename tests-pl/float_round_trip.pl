@@ -0,0 +1,29 @@
+:- use_module(library(between)).
+:- use_module(library(lists)).
+:- use_module(library(random)).
+
+round_trips(X) :-
+    number_codes(X, Cs),
+    number_codes(Y, Cs),
+    X == Y.
+
+edge_case(F) :-
+    member(E, [0.1, 0.3, 4.35, 100.0, 1/3, 2/3, 123456789.123456789,
+               1.0e-7, 1.0e-5, 1.0e15, 1.0e16, 1.0e17, 1.0e23,
+               9007199254740993.0, nexttoward(1.0, 2), nexttoward(1.0, 0),
+               epsilon, 1.0 + epsilon, -0.0,
+               2.0 ** -1074, 2.0 ** -1073, 2.0 ** -1022 - 2.0 ** -1074,
+               2.2250738585072014e-308, 1.7976931348623157e308,
+               -1.7976931348623157e308, inf, -inf, nan]),
+    F is E.
+edge_case(F) :-
+    between(-1074, 1023, N),
+    F is 2.0 ** N.
+% a fixed seed, so that a failure can be reproduced.
+edge_case(F) :-
+    set_random(seed(1)),
+    between(1, 1000, _),
+    F is (random_float - 0.5) * 10.0 ** (random(600) - 300).
+
+failures(Fs) :-
+    findall(F, (edge_case(F), \+ round_trips(F)), Fs).
@@ -0,0 +1,6 @@
+:- use_module(library(format)).
+
+portray(point(X, Y)) :-
+    format("<~w,~w>", [X, Y]).
+portray(broken) :-
+    throw(broken_portray).
@@ -0,0 +1,12 @@
+:- use_module(library(iso_ext)).
+:- use_module(library(dif)).
+:- use_module(library(between)).
+
+t1(Y) :- b_setval(k, X), g(X), b_getval(k, Y).
+g(a).
+
+t2(Y) :- dif(X, a), nb_setval(d, f(X)), nb_getval(d, Y).
+
+t3(C) :- nb_setval(c, 0), ( between(1, 10, _), nb_getval(c, C0), C1 is C0+1, nb_setval(c, C1), fail ; true ), nb_getval(c, C).
+
+t4(V) :- ( b_setval(v, 1), fail ; b_getval(v, V) ).
@@ -0,0 +1,13 @@
+:- use_module(library(lists)).
+:- use_module(library(apply_macros)).
+
+add(N, X, Y) :- Y is X + N.
+
+sum3(X, Y, Z, S0, S) :- S is S0 + X + Y + Z.
+
+test(L, R, S, T) :-
+    maplist(add(1), L, R0),
+    maplist(lists:length, [R0], [N]),
+    foldl(sum3, L, R0, [N, N, N], 0, S),
+    call(add(2), S, T),
+    maplist(add(N), R0, R).
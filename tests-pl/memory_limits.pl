@@ -0,0 +1,77 @@
+:- use_module(library(between)).
+:- use_module(library(lists)).
+
+grow(L) :- grow([a|L]).
+
+deep(N) :-
+    N1 is N + 1,
+    deep(N1),
+    true.
+
+heap_overflow :-
+    set_prolog_flag(heap_limit, 10000000),
+    catch(grow([]), error(E, _), true),
+    write(E),
+    nl.
+
+stack_overflow :-
+    set_prolog_flag(stack_limit, 10000000),
+    catch(deep(0), error(E, _), true),
+    write(E),
+    nl.
+
+bind_all([]).
+bind_all([a|Xs]) :- bind_all(Xs).
+
+% the variables of L are older than the choice point of member/2,
+% so binding each of them is trailed.
+trail_overflow :-
+    length(L, 20000),
+    set_prolog_flag(trail_limit, 100000),
+    catch((member(_, [1,2]), bind_all(L)), error(E, _), true),
+    !,
+    set_prolog_flag(trail_limit, infinite),
+    write(E),
+    nl.
+
+still_usable :-
+    catch(deep(0), error(resource_error(memory), _), true),
+    atom_length(abc, N),
+    write(N),
+    nl.
+
+limits :-
+    current_prolog_flag(heap_limit, H),
+    current_prolog_flag(stack_limit, S),
+    write(H-S),
+    nl,
+    set_prolog_flag(stack_limit, infinite),
+    current_prolog_flag(stack_limit, I),
+    write(I),
+    nl.
+
+bad_limits :-
+    catch(set_prolog_flag(heap_limit, 1), E1, true),
+    write(E1),
+    nl,
+    catch(set_prolog_flag(stack_limit, -5), E2, true),
+    write(E2),
+    nl.
+
+copy_and_build(L) :-
+    copy_term(L, _),
+    functor(_, f, 1).
+
+% the limit is set just above the heap in use, which copy_term/2
+% exceeds, so it throws the error.
+builtin_overflow :-
+    set_prolog_flag(heap_limit, infinite),
+    length(L, 200000),
+    between(1, 1000, K),
+    Limit is K * 100000,
+    catch(set_prolog_flag(heap_limit, Limit), _, false),
+    !,
+    catch(copy_and_build(L), error(E, Ctx), true),
+    set_prolog_flag(heap_limit, infinite),
+    write(E-Ctx),
+    nl.
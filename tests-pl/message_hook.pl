@@ -0,0 +1,3 @@
+message_hook(singletons(_:Line:Col, Names), warning, _) :-
+    write(singletons(Names, Line, Col)),
+    nl.
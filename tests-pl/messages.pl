@@ -0,0 +1,3 @@
+:- use_module(library(dcgs)).
+
+message(greeting(Name)) --> ["Hello, ~w!"-[Name], nl, "Bye."-[]].
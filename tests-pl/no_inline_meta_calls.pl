@@ -0,0 +1,6 @@
+:- use_module(library(lists)).
+
+inc(X, Y) :- Y is X + 1.
+
+test(L, R) :-
+    maplist(inc, L, R).
@@ -0,0 +1,9 @@
+:- module(occurs_check_module, [f/2, g/1, h/2]).
+
+:- set_prolog_flag(occurs_check, error).
+
+f(X, g(X)).
+
+g(X) :- X = s(X).
+
+h(X, X).
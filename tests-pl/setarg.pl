@@ -0,0 +1,72 @@
+:- use_module(library(between)).
+:- use_module(library(freeze)).
+:- use_module(library(iso_ext)).
+:- use_module(library(lists)).
+:- use_module(library(statistics)).
+
+count(N) :-
+    C = c(0),
+    (   between(1, 10, _),
+        arg(1, C, N0),
+        N1 is N0 + 1,
+        nb_setarg(1, C, N1),
+        fail
+    ;   arg(1, C, N)
+    ).
+
+collect(L) :-
+    A = acc([]),
+    (   member(X, [1,2,3]),
+        arg(1, A, L0),
+        nb_setarg(1, A, [X|L0]),
+        fail
+    ;   arg(1, A, L)
+    ).
+
+undone(T) :-
+    T = f(a),
+    (   setarg(1, T, b),
+        fail
+    ;   true
+    ).
+
+swap(Old, T) :-
+    T = f(a),
+    arg(1, T, Old),
+    setarg(1, T, b).
+
+% variables bound to atomic arguments by unification keep their
+% values.
+unified(T, X, Y) :-
+    T = f(a, c),
+    T = f(X, Y),
+    setarg(1, T, b),
+    nb_setarg(2, T, d),
+    compare(=, X, a),
+    atom(Y).
+
+frozen_copy(R) :-
+    A = box(none),
+    freeze(V, true),
+    nb_setarg(1, A, g(V)),
+    arg(1, A, g(W)),
+    (   '$get_attr_list'(W, L), nonvar(L) ->
+        R = attributed
+    ;   R = plain
+    ).
+
+% backtracking reclaims the copies made by nb_setarg/3, so the heap
+% doesn't grow with the number of iterations.
+nb_setarg_heap(Growth) :-
+    C = c(0),
+    statistics(heapused, H0),
+    (   between(1, 20000, I),
+        nb_setarg(1, C, f(I)),
+        fail
+    ;   true
+    ),
+    statistics(heapused, H),
+    (   H - H0 < 65536 ->
+        Growth = bounded
+    ;   Growth = H - H0
+    ).
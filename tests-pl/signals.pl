@@ -0,0 +1,48 @@
+:- use_module(library(os)).
+:- use_module(library(time)).
+:- use_module(library(iso_ext)).
+
+got(Signal) :-
+    bb_put(got, Signal).
+
+wait_for_signal :-
+    (   bb_get(got, Signal) ->
+        write(got(Signal)),
+        nl
+    ;   sleep(0.05),
+        wait_for_signal
+    ).
+
+spin(N) :-
+    N1 is N + 1,
+    spin(N1).
+
+handle_usr1 :-
+    on_signal(usr1, Old, got),
+    write(Old),
+    nl,
+    write(ready),
+    nl,
+    flush_output,
+    wait_for_signal.
+
+throw_on_term :-
+    on_signal(term, _, throw),
+    catch(( write(ready),
+            nl,
+            flush_output,
+            spin(0)
+          ),
+          E,
+          true),
+    write(E),
+    nl.
+
+handlers :-
+    on_signal(usr1, H1, H1),
+    on_signal(pipe, H2, H2),
+    write(H1-H2),
+    nl,
+    catch(on_signal(int, _, _), E, true),
+    write(E),
+    nl.
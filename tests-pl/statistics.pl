@@ -0,0 +1,56 @@
+:- use_module(library(statistics)).
+:- use_module(library(lists)).
+
+count(N, N) :- !.
+count(N0, N) :-
+    N1 is N0 + 1,
+    count(N1, N).
+
+keys :-
+    findall(K, statistics(K, _), Ks),
+    write(Ks),
+    nl.
+
+inferences :-
+    statistics(inferences, I0),
+    count(0, 1000),
+    statistics(inferences, I),
+    I - I0 > 1000.
+
+sizes :-
+    statistics(runtime, [T, _]),
+    integer(T),
+    statistics(walltime, [W, _]),
+    integer(W),
+    statistics(cputime, C),
+    float(C),
+    statistics(epoch, E),
+    float(E),
+    maplist(positive_statistic, [heapused, stack, trail, choicepoints, atoms,
+                                 clauses, modules, codes]).
+
+positive_statistic(K) :-
+    statistics(K, V),
+    integer(V),
+    V > 0.
+
+choicepoints :-
+    statistics(choicepoints, N0),
+    (   true
+    ;   true
+    ),
+    statistics(choicepoints, N),
+    N =:= N0 + 1,
+    !.
+
+bad_key :-
+    catch(statistics(foo, _), E, true),
+    write(E),
+    nl.
+
+% consulting the file again replaces its clauses.
+reconsult :-
+    statistics(clauses, C0),
+    consult('tests-pl/statistics.pl'),
+    statistics(clauses, C),
+    C =:= C0.
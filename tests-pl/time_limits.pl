@@ -0,0 +1,50 @@
+:- use_module(library(time)).
+:- use_module(library(iso_ext)).
+
+loop :- loop.
+
+sum(0, S, S) :- !.
+sum(N, S0, S) :-
+    S1 is S0 + N,
+    N1 is N - 1,
+    sum(N1, S1, S).
+
+exceeded :-
+    catch(call_with_time_limit(0.2, loop), E, true),
+    write(E),
+    nl.
+
+in_time :-
+    call_with_time_limit(5, (sleep(0.01), X = done)),
+    write(X),
+    nl.
+
+sleep_interrupted :-
+    catch(call_with_time_limit(0.2, sleep(60)), E, true),
+    write(E),
+    nl.
+
+alarm_resumes :-
+    alarm(0.01, bb_put(fired, true), _),
+    sum(300000, 0, S),
+    sleep(0.1),
+    bb_get(fired, F),
+    write(S-F),
+    nl.
+
+removed_alarm :-
+    bb_put(removed, not_fired),
+    alarm(0.05, bb_put(removed, fired), Id),
+    remove_alarm(Id),
+    sleep(0.1),
+    bb_get(removed, V),
+    write(V),
+    nl.
+
+bad_limits :-
+    catch(call_with_time_limit(0, true), E1, true),
+    write(E1),
+    nl,
+    catch(remove_alarm(foo), E2, true),
+    write(E2),
+    nl.
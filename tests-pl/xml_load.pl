@@ -0,0 +1,65 @@
+:- use_module(library(between)).
+:- use_module(library(format)).
+:- use_module(library(iso_ext)).
+:- use_module(library(lists)).
+:- use_module(library(sgml)).
+:- use_module(library(statistics)).
+
+catalog(Options) :-
+    load_xml(file("tests-pl/catalog.xml"), Es, Options),
+    write(Es),
+    nl.
+
+show(E) :-
+    write(E),
+    nl.
+
+stream_catalog :-
+    load_xml_stream(file("tests-pl/catalog.xml"), show, [space(remove), line_numbers(true)]).
+
+% the streamed children of the root are those load_xml/3 yields,
+% with the entities of the DOCTYPE resolved and the text kept.
+entities :-
+    load_xml(file("tests-pl/entities.xml"), [element(_, _, Es)], [space(sgml)]),
+    write(Es),
+    nl,
+    load_xml_stream(file("tests-pl/entities.xml"), show, [space(sgml)]).
+
+space :-
+    load_xml("<a>\n x \n</a>", Es, [space(sgml)]),
+    write(Es),
+    nl.
+
+write_items(File, N) :-
+    open(File, write, S),
+    format(S, "<items>~n", []),
+    (   between(1, N, I),
+        format(S, "<item n=\"~d\">item ~d</item>~n", [I, I]),
+        fail
+    ;   true
+    ),
+    format(S, "</items>~n", []),
+    close(S).
+
+% the heap in use once the last of many elements is read is what it
+% is for the first.
+stream_heap(Fs, Growth) :-
+    atom_chars(File, Fs),
+    write_items(File, 5000),
+    load_xml_stream(file(Fs), item_heap, [space(remove), line_numbers(true)]),
+    nb_getval(xml_stream_heap, H0-H),
+    (   H - H0 < 4096 ->
+        Growth = bounded
+    ;   Growth is H - H0
+    ).
+
+item_heap(element(item, Attrs, _)) :-
+    statistics(heapused, H),
+    (   memberchk(n="1", Attrs) ->
+        nb_setval(xml_stream_heap, H-H)
+    ;   memberchk(n="5000", Attrs) ->
+        memberchk('$line'=5001, Attrs),
+        nb_getval(xml_stream_heap, H0-_),
+        nb_setval(xml_stream_heap, H0-H)
+    ;   true
+    ).
@@ -0,0 +1,28 @@
+:- use_module(library(sgml)).
+:- use_module(library(dcgs)).
+
+atom_feed :-
+    Atom = 'http://www.w3.org/2005/Atom',
+    xml_write(user_output,
+              element(Atom:feed, [],
+                      [element(Atom:title, [], ["Q & A"]),
+                       element('urn:x':ext, ['urn:x':k="<v>"], [])]),
+              [nsmap([xmlns=Atom])]).
+
+html_page :-
+    html_write(user_output,
+               element(html, [],
+                       [element(head, [],
+                                [element(meta, [charset="utf-8"], []),
+                                 element(script, [], ["a < b"])]),
+                        element(body, [],
+                                [element(p, [], ["Hi ", element(b, [], ["there"])])])]),
+               []).
+
+row(X) --> html(li(X)).
+
+template :-
+    phrase(html(p([class=note], ['1 < 2', br([]), &(amp), ul([\row(a), \row(b)])])), Cs),
+    atom_chars(A, Cs),
+    write(A),
+    nl.
@@ -1,5 +1,10 @@
+use assert_cmd::cargo::CommandCargoExt;
 use assert_cmd::Command;
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
 use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStderr, ChildStdin, ChildStdout, Stdio};
 
 pub(crate) trait Expectable {
     #[track_caller]
@@ -82,3 +87,118 @@ pub fn run_top_level_test_with_args<
         .stdout(expected_stdout.into_output())
         .success();
 }
+
+/// Test whether scryer-prolog
+/// produces the expected output and error output when called with
+/// the supplied arguments and fed the supplied input
+pub fn run_top_level_test_with_stderr<
+    A: IntoIterator<Item = AS>,
+    S: Into<Vec<u8>>,
+    O: assert_cmd::assert::IntoOutputPredicate<P>,
+    E: assert_cmd::assert::IntoOutputPredicate<Q>,
+    AS: AsRef<OsStr>,
+    P: predicates_core::Predicate<[u8]>,
+    Q: predicates_core::Predicate<[u8]>,
+>(
+    args: A,
+    stdin: S,
+    expected_stdout: O,
+    expected_stderr: E,
+) {
+    Command::cargo_bin(SCRYER_PROLOG)
+        .unwrap()
+        .args(args)
+        .write_stdin(stdin)
+        .assert()
+        .stdout(expected_stdout.into_output())
+        .stderr(expected_stderr.into_output())
+        .success();
+}
+
+/// A running scryer-prolog, for tests that interact with it. Its
+/// input is written to a pipe and its output read from pipes a line
+/// at a time, so a test waits on what it prints rather than for a
+/// fixed time.
+pub(crate) struct TopLevelProcess {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+    stderr: BufReader<ChildStderr>,
+}
+
+impl TopLevelProcess {
+    /// Spawns scryer-prolog with the supplied arguments
+    pub(crate) fn spawn<A: IntoIterator<Item = AS>, AS: AsRef<OsStr>>(args: A) -> Self {
+        Self::spawn_with_env(args, &[])
+    }
+
+    /// Spawns scryer-prolog with the supplied arguments and
+    /// environment variables
+    pub(crate) fn spawn_with_env<A: IntoIterator<Item = AS>, AS: AsRef<OsStr>>(
+        args: A,
+        env: &[(&str, &OsStr)],
+    ) -> Self {
+        let mut child = std::process::Command::cargo_bin(SCRYER_PROLOG)
+            .unwrap()
+            .args(args)
+            .envs(env.iter().cloned())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        TopLevelProcess {
+            stdin: child.stdin.take(),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            stderr: BufReader::new(child.stderr.take().unwrap()),
+            child,
+        }
+    }
+
+    pub(crate) fn write(&mut self, input: &[u8]) {
+        self.stdin.as_mut().unwrap().write_all(input).unwrap();
+    }
+
+    /// The next line of output, without its line break
+    pub(crate) fn next_line(&mut self) -> String {
+        next_line(&mut self.stdout)
+    }
+
+    /// The next line of error output, without its line break
+    pub(crate) fn next_error_line(&mut self) -> String {
+        next_line(&mut self.stderr)
+    }
+
+    pub(crate) fn signal(&self, signal: Signal) {
+        kill(Pid::from_raw(self.child.id() as i32), signal).unwrap();
+    }
+
+    /// Closes the input and waits for a successful exit, returning the
+    /// output not read yet
+    pub(crate) fn finish(mut self) -> String {
+        drop(self.stdin.take());
+
+        let mut output = String::new();
+        self.stdout.read_to_string(&mut output).unwrap();
+
+        assert!(self.child.wait().unwrap().success());
+        output
+    }
+}
+
+fn next_line<R: BufRead>(reader: &mut R) -> String {
+    let mut line = String::new();
+
+    assert_ne!(
+        reader.read_line(&mut line).unwrap(),
+        0,
+        "unexpected end of output"
+    );
+
+    if line.ends_with('\n') {
+        line.pop();
+    }
+
+    line
+}
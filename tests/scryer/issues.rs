@@ -1,4 +1,8 @@
-use crate::helper::{load_module_test, run_top_level_test_no_args, run_top_level_test_with_args};
+use crate::helper::{
+    run_top_level_test_no_args, run_top_level_test_with_args, run_top_level_test_with_stderr,
+    TopLevelProcess,
+};
+use nix::sys::signal::Signal;
 
 // issue #857
 #[test]
@@ -13,7 +17,7 @@ fn display_constraints() {
         X = 1.\n   \
         true.\n   \
         X = 1.\n   \
-        dif:dif(X,1).\n\
+        dif(X,1).\n\
         ",
     );
 }
@@ -21,17 +25,14 @@ fn display_constraints() {
 // issue #852
 #[test]
 fn do_not_duplicate_path_components() {
-    run_top_level_test_no_args(
+    run_top_level_test_with_stderr(
+        &[] as &[&str],
         "\
             ['tests-pl/issue852-throw_e.pl'].\n\
             ['tests-pl/issue852-throw_e.pl'].\n\
             ",
-        "\
-        caught: e\n\
-        false.\n\
-        caught: e\n\
-        false.\n\
-        ",
+        "false.\nfalse.\n",
+        "error: caught: e\nerror: caught: e\n",
     );
 }
 
@@ -56,9 +57,9 @@ fn handle_residual_goal() {
         true.\n\
         false.\n   \
         X = - X.\n   \
-        dif:dif(- X,X).\n\
+        dif(- X,X).\n\
         false.\n   \
-        Vars = [X], dif:dif(- X,X).\n   \
+        Vars = [X], dif(- X,X).\n   \
         true.\n   \
         true.\n   \
         true.\n\
@@ -78,6 +79,25 @@ fn occurs_check_flag() {
     )
 }
 
+#[test]
+fn occurs_check_module_flag() {
+    run_top_level_test_with_stderr(
+        &["tests-pl/occurs-check-module.pl"],
+        "\
+            f(X, X).\n\
+            g(X).\n\
+            h(X, Y).\n\
+            h(X, f(X)).\n\
+            current_prolog_flag(occurs_check, F).\n\
+            X = s(X).\n\
+            ",
+        "   X = Y.\n   F = false.\n   X = s(X).\n",
+        "error: unify_with_occurs_check/2: Representation error: cannot represent term\n\
+         error: unify_with_occurs_check/2: Representation error: cannot represent term\n\
+         error: unify_with_occurs_check/2: Representation error: cannot represent term\n",
+    )
+}
+
 #[test]
 fn occurs_check_flag2() {
     run_top_level_test_no_args(
@@ -132,14 +152,55 @@ fn no_stutter() {
 
 // issue #812
 #[test]
-#[ignore] // FIXME: line is of by one, empty line not accounted for or starting to count at line 0?
 fn singleton_warning() {
-    run_top_level_test_no_args(
+    let path = std::fs::canonicalize("tests-pl/issue812-singleton-warning.pl").unwrap();
+
+    run_top_level_test_with_stderr(
+        &[] as &[&str],
         "['tests-pl/issue812-singleton-warning.pl'].",
-        "\
-        Warning: singleton variables X at line 4 of issue812-singleton-warning.pl\n   \
-        true.\n\
-        ",
+        "   true.\n",
+        format!("Warning: {}:4:1: singleton variables X\n", path.display()),
+    );
+}
+
+#[test]
+fn message_hook() {
+    run_top_level_test_with_args(
+        &["tests-pl/message_hook.pl"],
+        "['tests-pl/issue812-singleton-warning.pl'].",
+        "singletons(\"X\",4,1)\n   true.\n",
+    );
+}
+
+#[test]
+fn print_message() {
+    run_top_level_test_with_stderr(
+        &["tests-pl/messages.pl"],
+        "print_message(informational, greeting(world)), \
+         print_message(error, error(type_error(integer, a), foo/2)).",
+        "% Hello, world!\n% Bye.\n   true.\n",
+        "error: foo/2: Type error: expected integer, found a\n",
+    );
+}
+
+#[test]
+fn catch_with_backtrace() {
+    run_top_level_test_with_args(
+        &["tests-pl/backtrace.pl"],
+        "caller(user:c/1), caller(user:b/1).\n\
+         catch_with_backtrace(a(1), error(_, context(_, context(PI, _:L:C))), true).\n\
+         set_prolog_flag(debug, false), catch_with_backtrace(a(1), E, true).",
+        "   true.\n   \
+            PI = atom_length/2, L = 9, C = 1.\n   \
+            E = error(instantiation_error,atom_length/2).\n",
+    );
+}
+
+#[test]
+fn output_line_position() {
+    run_top_level_test_no_args(
+        "current_output(S), write(ab), nl, write(cde), once(stream_property(S, line_position(P))).",
+        "ab\ncde   S = user_output, P = 3.\n",
     );
 }
 
@@ -148,15 +209,530 @@ fn singleton_warning() {
 fn ignored_constraint() {
     run_top_level_test_no_args(
         "use_module(library(freeze)), freeze(X,false), X \\=a.",
-        "   freeze:freeze(X,user:false).\n",
+        "   freeze(X,false).\n",
     );
 }
 
 // issue #831
 #[test]
 fn call_0() {
-    load_module_test(
-        "tests-pl/issue831-call0.pl",
-        "caught: error(existence_error(procedure,call/0),call/0)\n",
+    run_top_level_test_with_stderr(
+        &["tests-pl/issue831-call0.pl"],
+        "",
+        "",
+        "error: call/0: Unknown procedure: call/0\n",
+    );
+}
+
+#[test]
+fn inline_meta_calls() {
+    run_top_level_test_with_args(
+        &["tests-pl/inline_meta_calls.pl"],
+        "test([1,2,3], R, S, T), \
+         once(current_predicate('__aux_maplist/3_add+1'/3)), \
+         once(current_predicate('__aux_foldl/5_sum3+0'/5)).",
+        "   R = [5,6,7], S = 24, T = 26.\n",
+    );
+    run_top_level_test_with_args(
+        &["tests-pl/no_inline_meta_calls.pl"],
+        "test([1,2,3], R), \\+ current_predicate('__aux_maplist/2_inc+0'/2).",
+        "   R = [2,3,4].\n",
+    );
+    // library(apply_macros) only applies to the file loading it.
+    run_top_level_test_with_args(
+        &[
+            "tests-pl/inline_meta_calls.pl",
+            "tests-pl/no_inline_meta_calls.pl",
+        ],
+        "test([1,2,3], R), \\+ current_predicate('__aux_maplist/2_inc+0'/2).",
+        "   R = [2,3,4].\n",
+    );
+}
+
+#[test]
+fn extended_evaluable_functions() {
+    run_top_level_test_no_args(
+        "A is msb(1000), B is lsb(12), C is popcount(255), D is log(2,8), \
+         E is copysign(2,-0.0), F is succ_or_zero(-3), G is trunc(2.7), \
+         H is integer(2.5), I is float_fractional_part(2.5).\n\
+         A is integer(-2.5), B is round(-2.5), C is cmpflags, X = cmpflags, D is X, \
+         M is max_tagged_integer, Y = max_tagged_integer, M =:= Y, M > 0.\n",
+        "   A = 9, B = 2, C = 8, D = 3.0, E = -2.0, F = 0, G = 2, H = 3, I = 0.5.\n   \
+            A = -3, B = -2, C = 0, X = cmpflags, D = 0, M = 9223372036854775807, \
+            Y = max_tagged_integer.\n",
+    );
+}
+
+#[test]
+fn ieee_float_flags() {
+    run_top_level_test_no_args(
+        "catch(_ is 1.0e308*10, error(E, _), true).\n\
+         set_prolog_flag(float_overflow, infinity), set_prolog_flag(float_zero_div, infinity), \
+         set_prolog_flag(float_undefined, nan), X is 1.0e308*10, Y is -1/0.0, Z is inf - inf.\n\
+         read_term(T, []), T = f(A, B, C), write_canonical(T), nl, A > 0, B < 0, C \\== 1.0.\n\
+         f(1.0Inf, -1.0Inf, 1.5NaN).\n",
+        "   E = evaluation_error(float_overflow).\n   \
+            X = 1.0Inf, Y = -1.0Inf, Z = 1.5NaN.\n\
+         f(1.0Inf,-1.0Inf,1.5NaN)\n   \
+            T = f(1.0Inf,-1.0Inf,1.5NaN), A = 1.0Inf, B = -1.0Inf, C = 1.5NaN.\n",
+    );
+}
+
+#[test]
+fn random_state_round_trip() {
+    run_top_level_test_no_args(
+        "use_module(library(random)), \
+         findall(Q, (getrand(S), random(A), random_between(1, 100, B), \
+                     random_permutation([1,2,3,4], P), setrand(S), random(A1), \
+                     random_between(1, 100, B), random_permutation([1,2,3,4], P), \
+                     A == A1, sort(P, Q)), Qs).\n",
+        "   Qs = [[1,2,3,4]].\n",
+    );
+}
+
+#[test]
+fn float_round_trip() {
+    run_top_level_test_with_args(
+        &["tests-pl/float_round_trip.pl"],
+        "failures(Fs).\n\
+         set_prolog_flag(float_format, '~3g'), X is pi, Y is 1.0e-10, Z is 123456.0, \
+         write([X,Y,Z]), nl, writeq(3.0), nl, write_canonical(X), nl.\n\
+         set_prolog_flag(float_format, ''), X is 2.0 ** -1074, Y is 1.0e300.\n",
+        "   Fs = [].\n\
+         [3.14,1e-10,1.23e+05]\n\
+         3.0\n\
+         3.141592653589793\n   \
+            X = 3.141592653589793, Y = 1.0e-10, Z = 123456.0.\n   \
+            X = 5.0e-324, Y = 1.0e300.\n",
+    );
+}
+
+#[test]
+fn format_exact_numbers_and_portray() {
+    run_top_level_test_with_args(
+        &["tests-pl/format_portray.pl"],
+        "format(\"~2f|~3f|~2f|~0f|~2f~n\", [1 rdiv 3, 12345678901234567890123, 2.675, 2.5, -1 rdiv 3]).\n\
+         format(\"~e|~2e|~g|~3g~n\", [1.5, 12345, 0.0001, 1234567]).\n\
+         format(\"~c~3c|~:d|~2:d~n\", [0'a, 0'b, 1234567, 1234567]).\n\
+         format(\"~p ~p ~q~n\", [point(1,2), 'A b', point(1,2)]).\n",
+        "0.33|12345678901234567890123.000|2.67|3|-0.33\n   \
+            true.\n\
+         1.500000e+00|1.23e+04|0.0001|1.23e+06\n   \
+            true.\n\
+         abbb|1,234,567|12,345.67\n   \
+            true.\n\
+         <1,2> 'A b' point(1,2)\n   \
+            true.\n",
+    );
+}
+
+#[test]
+fn native_strings() {
+    run_top_level_test_no_args(
+        "use_module(library(strings)).\n\
+         split_string(\"SWI-Prolog, 7.0\", \",\", \" \", Ss), split_string(\"  a word \", \"\", \" \", Ws).\n\
+         string_concat(abc, \"déf\", S), string_length(S, N), string_code(4, S, C).\n\
+         findall(A-B, string_concat(A, B, \"ab\"), Ps), string_concat(X, \"c\", abc).\n\
+         sub_string(\"hello world\", B, L, 0, \"world\"), sub_string(\"hello world\", 0, 5, _, Sub).\n\
+         string_chars(S, [x,y]), string_chars(hi, Cs), Cs = [h|Hs].\n\
+         string_chars(S, [a,b,c]), string(S), \\+ string([a,b,c]), S \\== [a,b,c], \\+ S = [a|_].\n",
+        "   true.\n   \
+            Ss = [\"SWI-Prolog\",\"7.0\"], Ws = [\"a word\"].\n   \
+            S = \"abcdéf\", N = 6, C = 100.\n   \
+            Ps = [\"\"-\"ab\",\"a\"-\"b\",\"ab\"-\"\"], X = \"ab\".\n   \
+            B = 6, L = 5, Sub = \"hello\".\n   \
+            S = \"xy\", Cs = \"hi\", Hs = \"i\".\n   \
+            S = \"abc\".\n",
+    );
+}
+
+#[test]
+fn packed_partial_string_operations() {
+    run_top_level_test_no_args(
+        "use_module(library(pstrings)), use_module(library(lists)).\n\
+         X = \"héllo\", partial_string_length(X, N), partial_string_prefix(X, hé), partial_string_suffix(X, \"llo\").\n\
+         findall(I, partial_string_search(\"hello world\", \"o\", I), Is), partial_string_split(\"a,b,,c\", \",\", Ps).\n\
+         append(\"abc\", T, Zs), T = \"de\", length(Zs, N), nth0(3, Zs, C).\n\
+         findall(X-Y, append(X, Y, \"ab\"), Ls).\n\
+         atom_chars(A, \"héllo\"), atom_chars(B, \"[]\"), atom_chars(C, []), append([a,b], [c], Ds).\n\
+         catch(partial_string_length(f(x), _), error(E, _), true).\n",
+        "   true.\n   \
+            X = \"héllo\", N = 5.\n   \
+            Is = [4,7], Ps = [\"a\",\"b\",[],\"c\"].\n   \
+            T = \"de\", Zs = \"abcde\", N = 5, C = d.\n   \
+            Ls = [[]-\"ab\",\"a\"-\"b\",\"ab\"-[]].\n   \
+            A = héllo, B = [], C = '', Ds = \"abc\".\n   \
+            E = type_error(partial_string,f(x)).\n",
+    );
+}
+
+#[test]
+fn when_coroutining() {
+    run_top_level_test_no_args(
+        "use_module(library(when)), use_module(library(freeze)).\n\
+         when(ground(f(X,Y)), write(g)), X = 1, write(-), Y = 2.\n\
+         when(?=(X,Y), write(done)), X = f(A), Y = f(b).\n\
+         when((nonvar(X);nonvar(Y)), write(d)), Y = 1, X = 2.\n\
+         when(nonvar(X), true), freeze(X, true), frozen(X, G).\n\
+         catch(when(foo, true), error(E, _), true).\n",
+        "   true.\n\
+         -g   X = 1, Y = 2.\n   \
+            X = f(A), Y = f(b), when(?=(f(A),f(b)),write(done)).\n\
+         d   Y = 1, X = 2.\n   \
+            G = (freeze(X,user:true),when(nonvar(X),user:true)), freeze(X,true), when(nonvar(X),true).\n   \
+            E = domain_error(when_condition,foo).\n",
+    );
+}
+
+#[test]
+fn global_variables() {
+    run_top_level_test_with_args(
+        &["tests-pl/global_vars.pl"],
+        "t1(Y).\n\
+         t2(f(Z)), (Z = a -> R = bound ; R = blocked).\n\
+         t3(C).\n\
+         catch(t4(V), error(E, _), true).\n\
+         nb_setval(x, 1), b_setval(y, 2), findall(K-V, (nb_current(K, V), (K == x ; K == y)), L).\n",
+        "   Y = a.\n   \
+            R = blocked, dif(Z,a).\n   \
+            C = 10.\n   \
+            E = existence_error(variable,v).\n   \
+            L = [x-1,y-2].\n",
+    );
+}
+
+#[test]
+fn setarg_and_nb_setarg() {
+    run_top_level_test_with_args(
+        &["tests-pl/setarg.pl"],
+        "count(N), collect(L).\n\
+         undone(T), swap(Old, S).\n\
+         unified(T, X, Y).\n\
+         frozen_copy(R).\n\
+         nb_setarg_heap(Growth).\n\
+         T = f(a, b), setarg(3, T, c).\n\
+         catch(setarg(a, f(x), c), error(E, _), true).\n",
+        "   N = 10, L = [3,2,1].\n   \
+            T = f(a), Old = a, S = f(b).\n   \
+            T = f(b,d), X = a, Y = c.\n   \
+            R = attributed.\n   \
+            Growth = bounded.\n\
+         false.\n   \
+            E = type_error(integer,a).\n",
+    );
+}
+
+#[test]
+fn xml_and_html_writing() {
+    run_top_level_test_with_args(
+        &["tests-pl/xml_write.pl"],
+        "atom_feed.\n\
+         html_page.\n\
+         template.\n",
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\" xmlns:ns1=\"urn:x\">\n  \
+           <title>Q &amp; A</title>\n  \
+           <ns1:ext ns1:k=\"&lt;v>\"/>\n\
+         </feed>\n   \
+            true.\n\
+         <!DOCTYPE html>\n\
+         <html>\n  \
+           <head>\n    \
+             <meta charset=\"utf-8\">\n    \
+             <script>a < b</script>\n  \
+           </head>\n  \
+           <body>\n    \
+             <p>Hi <b>there</b></p>\n  \
+           </body>\n\
+         </html>\n   \
+            true.\n\
+         <p class=\"note\">1 &lt; 2<br>&amp;<ul><li>a</li><li>b</li></ul></p>\n   \
+            true.\n",
+    );
+}
+
+#[test]
+fn xml_loading_options() {
+    run_top_level_test_with_args(
+        &["tests-pl/xml_load.pl"],
+        "catalog([space(remove)]).\n\
+         catalog([space(remove), dialect(xmlns), attribute_value(atom)]).\n\
+         stream_catalog.\n\
+         entities.\n\
+         space.\n",
+        "[element(catalog,[xmlns=\"http://example.org/c\",xmlns:d=\"http://example.org/d\"],\
+         [element(book,[id=\"b1\",d:lang=\"en\"],[element(title,[],[\"First\"])]),\
+         element(book,[id=\"b2\"],[element(title,[],[\"a </book> b\"])]),\
+         element(d:note,[],[\"end\"])])]\n   \
+            true.\n\
+         [element(http://example.org/c:catalog,[],\
+         [element(http://example.org/c:book,[id=b1,http://example.org/d:lang=en],[element(http://example.org/c:title,[],[\"First\"])]),\
+         element(http://example.org/c:book,[id=b2],[element(http://example.org/c:title,[],[\"a </book> b\"])]),\
+         element(http://example.org/d:note,[],[\"end\"])])]\n   \
+            true.\n\
+         element(book,[$line=4,id=\"b1\",d:lang=\"en\"],[element(title,[$line=5],[\"First\"])])\n\
+         element(book,[$line=8,id=\"b2\"],[element(title,[$line=8],[\"a </book> b\"])])\n\
+         element(d:note,[$line=9],[\"end\"])\n   \
+            true.\n\
+         [\"by Ada\",element(note,[],[\"Ada & co\"]),\"end\"]\n\
+         \"by Ada\"\n\
+         element(note,[],[\"Ada & co\"])\n\
+         \"end\"\n   \
+            true.\n\
+         [element(a,[],[\" x \"])]\n   \
+            true.\n",
+    );
+}
+
+// streaming a document keeps the memory of one element at a time.
+#[test]
+fn xml_stream_heap() {
+    let path = std::env::temp_dir().join(format!("scryer-items-{}.xml", std::process::id()));
+    let input = format!("stream_heap({:?}, Growth).\n", path.to_str().unwrap());
+
+    run_top_level_test_with_args(&["tests-pl/xml_load.pl"], input, "   Growth = bounded.\n");
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn date_and_time_arithmetic() {
+    run_top_level_test_with_args(
+        &["tests-pl/date_time.pl"],
+        "conversions.\n\
+         arithmetic.\n\
+         parsing.\n\
+         formatting.\n\
+         local_zone.\n",
+        "date(2020,6,11,14,24,32.5,0,UTC,-)\n\
+         1591878272\n\
+         date(2020,12,31,0,0,0,0,-,-)\n\
+         4\n   \
+            true.\n\
+         date(2020,2,29)\n\
+         date(2021,1,1,1,30,0,0,UTC,-)\n\
+         days(29)\n\
+         seconds(3600)\n   \
+            true.\n\
+         iso_8601-date(2019,12,30)\n\
+         date(2020,6,11,14,30,0.25,-7200,-,-)\n\
+         rfc_2822-1591878600\n\
+         rejected\n   \
+            true.\n\
+         Sun  3 Jan 2021, 07:05:09.250000 PM +0100 CET|003 7 0 01 00 2020-W53\n   \
+            true.\n\
+         1591885472\n\
+         -\n\
+         dst_known\n   \
+            true.\n",
+    );
+}
+
+#[test]
+fn statistics_keys() {
+    run_top_level_test_with_args(
+        &["tests-pl/statistics.pl"],
+        "keys.\n\
+         inferences.\n\
+         sizes.\n\
+         choicepoints.\n\
+         reconsult.\n\
+         bad_key.\n",
+        "[runtime,walltime,cputime,inferences,heapused,stack,trail,choicepoints,atoms,clauses,modules,codes,epoch]\n   \
+            true.\n   \
+            true.\n   \
+            true.\n   \
+            true.\n   \
+            true.\n\
+         error(domain_error(statistics_key,foo),statistics/2)\n   \
+            true.\n",
+    );
+}
+
+#[test]
+fn memory_limits() {
+    run_top_level_test_with_args(
+        &["tests-pl/memory_limits.pl"],
+        "heap_overflow.\n\
+         stack_overflow.\n\
+         trail_overflow.\n\
+         still_usable.\n\
+         limits.\n\
+         bad_limits.\n\
+         builtin_overflow.\n",
+        "resource_error(memory)\n   \
+            true.\n\
+         resource_error(memory)\n   \
+            true.\n\
+         resource_error(memory)\n   \
+            true.\n\
+         3\n   \
+            true.\n\
+         10000000-10000000\n\
+         infinite\n   \
+            true.\n\
+         error(domain_error(flag_value,heap_limit+1),set_prolog_flag/2)\n\
+         error(domain_error(flag_value,stack_limit+ -5),set_prolog_flag/2)\n   \
+            true.\n\
+         resource_error(memory)-copy_term/2\n   \
+            true.\n",
+    );
+}
+
+#[test]
+fn time_limits() {
+    run_top_level_test_with_args(
+        &["tests-pl/time_limits.pl"],
+        "exceeded.\n\
+         in_time.\n\
+         sleep_interrupted.\n\
+         alarm_resumes.\n\
+         removed_alarm.\n\
+         bad_limits.\n",
+        "time_limit_exceeded\n   \
+            true.\n\
+         done\n   \
+            true.\n\
+         time_limit_exceeded\n   \
+            true.\n\
+         45000150000-true\n   \
+            true.\n\
+         not_fired\n   \
+            true.\n\
+         error(domain_error(positive_number,0),call_with_time_limit/2)\n\
+         error(domain_error(alarm,foo),remove_alarm/1)\n   \
+            true.\n",
+    );
+    run_top_level_test_no_args(
+        "use_module(library(time)).\n\
+         remove_alarm('$alarm'(0)).\n",
+        "   true.\n   true.\n",
+    );
+}
+
+#[test]
+fn signal_handlers() {
+    let mut top_level = TopLevelProcess::spawn(&["tests-pl/signals.pl"]);
+
+    top_level.write(b"handle_usr1.\n");
+    assert_eq!(top_level.next_line(), "default");
+    assert_eq!(top_level.next_line(), "ready");
+    top_level.signal(Signal::SIGUSR1);
+    assert_eq!(top_level.next_line(), "got(usr1)");
+    assert_eq!(top_level.next_line(), "   true.");
+
+    top_level.write(b"throw_on_term.\n");
+    assert_eq!(top_level.next_line(), "ready");
+    top_level.signal(Signal::SIGTERM);
+    assert_eq!(top_level.next_line(), "signal(term)");
+    assert_eq!(top_level.next_line(), "   true.");
+
+    top_level.write(b"handlers.\n");
+    assert_eq!(top_level.next_line(), "(user:got)-ignore");
+    assert_eq!(
+        top_level.next_line(),
+        "error(domain_error(signal,int),on_signal/3)"
+    );
+    assert_eq!(top_level.next_line(), "   true.");
+
+    assert_eq!(top_level.finish(), "");
+}
+
+// without a terminal to offer the interrupt menu on, an interrupt
+// aborts the query.
+#[test]
+fn interrupt_without_terminal() {
+    let mut top_level = TopLevelProcess::spawn(&["tests-pl/signals.pl"]);
+
+    top_level.write(b"write(ready), nl, flush_output, spin(0).\n");
+    assert_eq!(top_level.next_line(), "ready");
+    top_level.signal(Signal::SIGINT);
+    assert_eq!(top_level.next_line(), "");
+    assert_eq!(top_level.next_error_line(), "error: Execution interrupted");
+
+    top_level.write(b"true.\n");
+    assert_eq!(top_level.next_line(), "   true.");
+
+    assert_eq!(top_level.finish(), "");
+}
+
+#[test]
+fn answer_write_options() {
+    run_top_level_test_with_stderr(
+        &["tests-pl/answer_write_options.pl"],
+        "dif(X, a), freeze(X, true).\n\
+         X #> Y, Y #> 2.\n\
+         X = f(secret(1), [1,2,3,4,5]).\n\
+         set_prolog_flag(answer_write_options, [quoted(true), max_depth(3)]).\n\
+         X = f(secret(1), [1,2,3,4,5]).\n\
+         set_prolog_flag(answer_write_options, [max_depth(a)]).\n\
+         set_prolog_flag(answer_write_options, [quoted(true), portray(true), max_depth(20)]).\n\
+         numbers_or_none(Ns).\n\
+         w;numbers(3000, Ns).\n\
+         n",
+        "   dif(X,a), freeze(X,true).\n   \
+            Y#=<X+ -1, X in 4..sup, Y in 3..sup.\n   \
+            X = f(<hidden>,[1,2,3,4,5]).\n   \
+            true.\n   \
+            X = f(secret(1),[1|...]).\n   \
+            true.\n   \
+            Ns = [1,2,3,4,5,6,7,8,9,10|...]\n   \
+            Ns = [1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30]\n\
+         ;  Ns = none.\n   \
+            The answer has 13899 characters. Print it? (y/n) \n   \
+            Ns = ...\n",
+        "error: set_prolog_flag/2: Domain error: expected flag_value, \
+         found answer_write_options+[max_depth(a)]\n",
+    );
+}
+
+// the history is read from and saved to the home directory, so the
+// toplevel runs with an empty one of its own.
+#[test]
+fn query_history_and_toplevel_variables() {
+    use std::fs;
+
+    let home = std::env::temp_dir().join(format!("scryer-history-{}", std::process::id()));
+    fs::create_dir_all(&home).unwrap();
+
+    let mut top_level =
+        TopLevelProcess::spawn_with_env(&[] as &[&str], &[("HOME", home.as_os_str())]);
+
+    top_level.write(
+        b"X = f(5).\n\
+          Y = g($X).\n\
+          Z = $W.\n\
+          h.\n\
+          !1\n\
+          !!\n\
+          !9\n\
+          use_module(library(debug)).\n\
+          G = true, $G.\n\
+          Y = $Y.\n",
+    );
+
+    let output = top_level.finish();
+    fs::remove_dir_all(&home).unwrap();
+
+    assert_eq!(
+        output,
+        "   X = f(5).\n   \
+            Y = g(f(5)).\n   \
+            Z = $(W).\n   \
+            1  X = f(5).\n   \
+            2  Y = g($X).\n   \
+            3  Z = $W.\n   \
+            4  h.\n   \
+            true.\n\
+         X = f(5).\n   \
+            X = f(5).\n\
+         X = f(5).\n   \
+            X = f(5).\n\
+         !9: event not found\n   \
+            true.\n\
+         call:user:true.\n\
+         exit:user:true.\n   \
+            G = true.\n   \
+            Y = g(f(5)).\n"
     );
 }
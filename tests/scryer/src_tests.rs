@@ -1,4 +1,4 @@
-use crate::helper::{load_module_test, run_top_level_test_with_args};
+use crate::helper::{load_module_test, run_top_level_test_with_stderr};
 
 #[test]
 fn builtins() {
@@ -22,9 +22,16 @@ fn hello_world() {
 
 #[test]
 fn syntax_error() {
-    load_module_test(
-        "tests-pl/syntax_error.pl",
-        "caught: error(syntax_error(incomplete_reduction),read_term/3:6)\n",
+    let path = std::fs::canonicalize("tests-pl/syntax_error.pl").unwrap();
+
+    run_top_level_test_with_stderr(
+        &["tests-pl/syntax_error.pl"],
+        "",
+        "",
+        format!(
+            "error: {}:4:1: read_term/3:6: Syntax error: incomplete_reduction\n",
+            path.display()
+        ),
     );
 }
 
@@ -40,15 +47,16 @@ fn rules() {
 
 #[test]
 fn setup_call_cleanup_load() {
-    load_module_test("src/tests/setup_call_cleanup.pl", "caught: unthrown\n");
+    load_module_test("src/tests/setup_call_cleanup.pl", "");
 }
 
 #[test]
 fn setup_call_cleanup_process() {
-    run_top_level_test_with_args(
+    run_top_level_test_with_stderr(
         &["src/tests/setup_call_cleanup.pl"],
         "",
-        "caught: unthrown\n",
+        "",
+        "error: caught: unthrown\n",
     );
 }
 